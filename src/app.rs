@@ -1,29 +1,169 @@
+use crate::apply::{
+    apply_edits, default_backup_dir, parse_response, plan_edits, EditStatus, PlannedEdit,
+};
+use crate::archive::is_archive;
+use crate::bundle::{render_bundle_with_graph, select_files, BundleOptions};
 use crate::clipboard::copy_to_clipboard;
-use crate::formatter::{build_tree, format_file_contents, format_project_summary};
-use crate::scanner::scan_project;
+use crate::config::{with_prompt, AppConfig, ScanProfile, PROMPT_PRESETS};
+use crate::deps::DependencyGraph;
+use crate::filelist::{parse_file_list, scan_file_list};
+use crate::formatter::{build_tree, FormatOptions};
+use crate::graph::{render_graph, GraphExportOptions, GraphFormat};
+use crate::manifest::{Manifest, ManifestOptions};
+use crate::notebook::NotebookOptions;
+use crate::order::FileOrder;
+use crate::relevance::Budget;
+use crate::roots::{scan_roots, DiskFiles, ProjectRoot};
+use crate::sample::SampleRule;
+use crate::scanner::ScannedFile;
+use crate::search::{find_matches, tree_anchors, SearchOptions, TreeAnchor, MAX_MATCHES};
+use crate::truncate::TruncateRule;
 use crate::utils::load_custom_font;
+use crate::watch::{ProjectState, ProjectWatcher, DEFAULT_DEBOUNCE};
 use eframe::egui;
+use eframe::egui::text::{CCursor, LayoutJob};
 use rfd::FileDialog;
-use std::path::PathBuf;
-use std::sync::mpsc;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
 use std::thread;
+use std::time::Duration;
+
+/// A file edit from a model's reply, awaiting review.
+struct PendingEdit {
+    edit: PlannedEdit,
+    diff: String, // Computed once, not every frame
+    accepted: bool,
+}
+
+/// Search within the expanded preview.
+#[derive(Default)]
+struct PreviewSearch {
+    query: String,
+    options: SearchOptions,
+    matches: Vec<Range<usize>>,
+    error: Option<String>,                   // Invalid regular expression
+    current: usize,                          // Index of the selected match
+    scroll_to: Option<(usize, egui::Align)>, // Byte offset to bring into view
+    anchors: Vec<TreeAnchor>,                // Tree entries of the preview, for jumping to files
+    layout: Option<(egui::Color32, Arc<egui::Galley>)>, // Highlighted preview and its text color
+}
+
+/// A root folder as edited in the UI.
+struct RootEntry {
+    root: ProjectRoot,
+    ignore_patterns: String, // Comma-separated, applied to this root only
+}
+
+impl RootEntry {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            root: ProjectRoot::new(path),
+            ignore_patterns: String::new(),
+        }
+    }
+
+    fn from_root(root: ProjectRoot) -> Self {
+        Self {
+            ignore_patterns: root.ignore_patterns.join(", "),
+            root,
+        }
+    }
+
+    fn to_root(&self) -> ProjectRoot {
+        ProjectRoot {
+            ignore_patterns: self
+                .ignore_patterns
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string)
+                .collect(),
+            ..self.root.clone()
+        }
+    }
+}
 
 pub struct ContextorApp {
-    selected_folder: Option<PathBuf>,
+    roots: Vec<RootEntry>,
     output_preview: String,
+    preview_tree: Vec<(String, String)>, // Tree entries of the summary in the preview
+    summary_files: Option<(Vec<ProjectRoot>, Vec<String>)>, // Roots and paths of the summary
+    sent_manifest: Option<Manifest>,     // Files of the summary as they were when last copied
+    search: PreviewSearch,
     max_file_size: String, // Store as string for UI input handling
     error_message: Option<String>,
-    output_expanded: bool, // Track if output is expanded or collapsed
+    output_expanded: bool,     // Track if output is expanded or collapsed
+    line_numbers: bool,        // Prefix file content lines with line numbers
+    query: String,             // Only keep files relevant to this query (if set)
+    token_budget: String,      // Token limit for query results, empty = unlimited
+    include_with_deps: String, // Only keep this file and what it imports (if set)
+    order: FileOrder,          // Order of the file sections
+    priority: String,          // Comma-separated patterns of files to write first
+    manifest: bool,            // List the included files with their hashes
+    manifest_hash: bool,       // End the summary with a hash of its content
+    append_graph: bool,        // Append the dependency graph to the summary
+    graph_options: GraphExportOptions,
+    graph_focus: String, // Restrict the graph to this file's imports (if set)
+    last_graph: Option<DependencyGraph>, // Graph of the last scan, for export
+    watch_enabled: bool, // Regenerate the summary on file changes
+    watch_clipboard: bool, // Also copy each regenerated summary
+    watch: Option<(ProjectState, ProjectWatcher)>,
+    use_cache: bool,              // Reuse unchanged files from the on-disk scan cache
+    file_list: String,            // Paths to bundle instead of walking the folder, one per line
+    config: AppConfig,            // Recent projects and saved profiles
+    config_path: Option<PathBuf>, // Where `config` is persisted (if anywhere)
+    profile_name: String,         // Name for the next saved profile
+    truncate_rules: String,       // One PATTERN:SPEC rule per line
+    notebook_outputs: bool,       // Include text outputs of notebook cells
+    sample_data: bool,            // Reduce data files to samples and outlines
+    expand_archives: bool,        // Scan inside zip/tar archives found in the folders
+    prompt: String,               // Instructions copied after the summary
+    model_reply: String,          // Pasted model reply with file edits
+    pending_edits: Vec<PendingEdit>,
+    apply_message: Option<String>, // Outcome of the last preview or apply
 }
 
 impl Default for ContextorApp {
     fn default() -> Self {
         Self {
-            selected_folder: None,
+            roots: Vec::new(),
             output_preview: "Select a folder to generate a summary".to_string(),
+            preview_tree: Vec::new(),
+            summary_files: None,
+            sent_manifest: None,
+            search: PreviewSearch::default(),
             max_file_size: "1000000".to_string(), // Default 1MB
             error_message: None,
             output_expanded: false, // Start in collapsed mode
+            line_numbers: false,
+            query: String::new(),
+            token_budget: String::new(),
+            include_with_deps: String::new(),
+            order: FileOrder::default(),
+            priority: String::new(),
+            manifest: false,
+            manifest_hash: false,
+            append_graph: false,
+            graph_options: GraphExportOptions::default(),
+            graph_focus: String::new(),
+            last_graph: None,
+            watch_enabled: false,
+            watch_clipboard: false,
+            watch: None,
+            use_cache: true,
+            file_list: String::new(),
+            config: AppConfig::default(),
+            config_path: None,
+            profile_name: String::new(),
+            truncate_rules: String::new(),
+            notebook_outputs: false,
+            sample_data: false,
+            expand_archives: false,
+            prompt: String::new(),
+            model_reply: String::new(),
+            pending_edits: Vec::new(),
+            apply_message: None,
         }
     }
 }
@@ -33,27 +173,257 @@ impl eframe::App for ContextorApp {
         // Load the custom font
         load_custom_font(ctx);
 
+        if self.watch.is_some() {
+            self.poll_watch();
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|f| f.path.clone())
+                .collect()
+        });
+        if !dropped.is_empty() {
+            self.handle_drop(dropped);
+        }
+        let hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Contextor");
 
-            if ui.button("Select Folder").clicked() {
-                if let Some(path) = FileDialog::new().pick_folder() {
-                    self.selected_folder = Some(path.clone());
-                    self.output_preview = format!("Selected folder: {:?}", path);
+            ui.horizontal(|ui| {
+                if ui.button("Select Folder").clicked() {
+                    if let Some(path) = FileDialog::new().pick_folder() {
+                        self.set_preview_message(format!("Selected folder: {:?}", path));
+                        self.roots = vec![RootEntry::new(path)];
+                    }
+                }
+                if ui.button("Add Folder").clicked() {
+                    if let Some(path) = FileDialog::new().pick_folder() {
+                        self.roots.push(RootEntry::new(path));
+                    }
+                }
+                let mut picked = None;
+                ui.add_enabled_ui(!self.config.recent_projects.is_empty(), |ui| {
+                    ui.menu_button("Recent", |ui| {
+                        for path in &self.config.recent_projects {
+                            if ui.button(path.display().to_string()).clicked() {
+                                picked = Some(path.clone());
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+                if let Some(path) = picked {
+                    self.set_preview_message(format!("Selected folder: {:?}", path));
+                    self.roots = vec![RootEntry::new(path)];
+                }
+            });
+
+            if let Some(project) = self.roots.first().map(|e| e.root.path.clone()) {
+                let mut load = None;
+                let mut delete = None;
+                ui.horizontal(|ui| {
+                    ui.label("Profiles:");
+                    for profile in self.config.profiles(&project) {
+                        let button = ui
+                            .button(&profile.name)
+                            .on_hover_text("Click to load, right-click to delete");
+                        if button.clicked() {
+                            load = Some(profile.clone());
+                        }
+                        if button.secondary_clicked() {
+                            delete = Some(profile.name.clone());
+                        }
+                    }
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.profile_name)
+                            .hint_text("Profile name")
+                            .desired_width(120.0),
+                    );
+                    if ui.button("Save Profile").clicked() {
+                        self.save_profile(&project);
+                    }
+                });
+                if let Some(profile) = load {
+                    self.apply_profile(profile);
+                }
+                if let Some(name) = delete {
+                    self.config.remove_profile(&project, &name);
+                    self.save_config();
                 }
             }
 
+            // Drop zone, highlighted while files are dragged over the window
+            let stroke = if hovering {
+                ui.visuals().selection.stroke
+            } else {
+                ui.visuals().widgets.noninteractive.bg_stroke
+            };
+            egui::Frame::group(ui.style()).stroke(stroke).show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.vertical_centered(|ui| {
+                    ui.label(if hovering {
+                        "Release to add"
+                    } else {
+                        "Drop folders here to add them, or files to add them to the file list"
+                    });
+                });
+            });
+
+            // One row per root when bundling several folders together
+            if self.roots.len() > 1 {
+                let mut remove = None;
+                for (i, entry) in self.roots.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(entry.root.path.display().to_string());
+                        ui.label("Alias:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut entry.root.alias).desired_width(100.0),
+                        );
+                        ui.label("Ignore:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut entry.ignore_patterns)
+                                .hint_text("e.g. *.lock, dist/")
+                                .desired_width(160.0),
+                        );
+                        if ui.button("✖").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    self.roots.remove(i);
+                }
+            } else if let Some(entry) = self.roots.first_mut() {
+                ui.horizontal(|ui| {
+                    ui.label("Ignore patterns:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut entry.ignore_patterns)
+                            .hint_text("e.g. *.lock, dist/"),
+                    );
+                });
+            }
+
+            ui.collapsing("Explicit file list", |ui| {
+                ui.label("Only these files (one path per line, relative to the folder, or drop files here):");
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.file_list)
+                        .hint_text("src/main.rs")
+                        .desired_rows(4),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Load List…").clicked() {
+                        self.load_file_list();
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.file_list.clear();
+                    }
+                });
+            });
+
             ui.separator();
             ui.label("Max file size to scan (in bytes):");
             ui.text_edit_singleline(&mut self.max_file_size);
+            ui.checkbox(&mut self.line_numbers, "Line numbers");
+            ui.checkbox(&mut self.use_cache, "Use scan cache");
+            ui.checkbox(&mut self.expand_archives, "Look inside zip/tar archives");
+            ui.checkbox(&mut self.notebook_outputs, "Include notebook outputs");
+            ui.checkbox(
+                &mut self.sample_data,
+                "Sample data files (CSV/TSV rows, JSON/YAML outlines, log tails)",
+            );
+
+            ui.label("Truncation rules (one per line, e.g. *.log:tail=100 or src/app.rs:100-220):");
+            ui.add(
+                egui::TextEdit::multiline(&mut self.truncate_rules)
+                    .hint_text("*.json:head=40,tail=10")
+                    .desired_rows(2),
+            );
+
+            ui.label("Relevance query (optional):");
+            ui.text_edit_singleline(&mut self.query);
+            ui.label("Token budget for query results (empty = unlimited):");
+            ui.text_edit_singleline(&mut self.token_budget);
+
+            ui.label("Include file and everything it imports (relative path, optional):");
+            ui.text_edit_singleline(&mut self.include_with_deps);
+            ui.horizontal(|ui| {
+                ui.label("Section order:");
+                egui::ComboBox::from_id_salt("section_order")
+                    .selected_text(self.order.name())
+                    .show_ui(ui, |ui| {
+                        for order in FileOrder::ALL {
+                            ui.selectable_value(&mut self.order, order, order.name());
+                        }
+                    });
+                ui.checkbox(&mut self.manifest, "Append file manifest");
+                ui.checkbox(&mut self.manifest_hash, "Append manifest hash");
+            });
+            ui.label("Write these first (comma-separated patterns, optional):");
+            ui.text_edit_singleline(&mut self.priority);
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.append_graph, "Append dependency graph");
+                ui.radio_value(&mut self.graph_options.format, GraphFormat::Dot, "DOT");
+                ui.radio_value(
+                    &mut self.graph_options.format,
+                    GraphFormat::Mermaid,
+                    "Mermaid",
+                );
+                ui.checkbox(
+                    &mut self.graph_options.collapse_by_directory,
+                    "Collapse by directory",
+                );
+            });
+            ui.label("Graph focus file (optional):");
+            ui.text_edit_singleline(&mut self.graph_focus);
+
+            ui.horizontal(|ui| {
+                ui.label("Prompt copied after the summary (optional):");
+                egui::ComboBox::from_id_salt("prompt_preset")
+                    .selected_text("Presets")
+                    .show_ui(ui, |ui| {
+                        for (name, text) in PROMPT_PRESETS {
+                            if ui.selectable_label(self.prompt == text, name).clicked() {
+                                self.prompt = text.to_string();
+                            }
+                        }
+                    });
+            });
+            ui.add(
+                egui::TextEdit::multiline(&mut self.prompt)
+                    .hint_text("e.g. Find the cause of the crash described below.")
+                    .desired_rows(2),
+            );
 
             if let Some(err) = &self.error_message {
                 ui.colored_label(egui::Color32::RED, format!("Error: {}", err));
             }
 
-            if ui.button("Start Scan").clicked() {
-                self.start_scan();
-            }
+            ui.horizontal(|ui| {
+                if ui.button("Start Scan").clicked() {
+                    self.start_scan();
+                }
+                if ui
+                    .checkbox(&mut self.watch_enabled, "Watch for changes")
+                    .changed()
+                {
+                    self.toggle_watch();
+                }
+                ui.checkbox(&mut self.watch_clipboard, "Copy on change");
+                if ui
+                    .add_enabled(
+                        self.last_graph.is_some(),
+                        egui::Button::new("Export Graph…"),
+                    )
+                    .clicked()
+                {
+                    self.export_graph();
+                }
+            });
 
             ui.separator();
             ui.label("Project Summary:");
@@ -62,19 +432,18 @@ impl eframe::App for ContextorApp {
             ui.group(|ui| {
                 if self.output_expanded {
                     // Expanded View (Full Scrollable Output)
-                    if ui.button("Collapse").clicked() {
-                        self.output_expanded = false;
-                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Collapse").clicked() {
+                            self.output_expanded = false;
+                        }
+                        ui.separator();
+                        self.show_search_bar(ui);
+                    });
+                    self.show_jump_to_file(ui);
 
                     egui::ScrollArea::vertical()
                         .max_height(400.0) // Set max height for scrolling
-                        .show(ui, |ui| {
-                            ui.add(
-                                egui::TextEdit::multiline(&mut self.output_preview)
-                                    .font(egui::TextStyle::Monospace)
-                                    .desired_rows(20),
-                            );
-                        });
+                        .show(ui, |ui| self.show_preview_text(ui));
                 } else {
                     // Collapsed View (Show only first 10 lines)
                     let preview_text = self
@@ -89,7 +458,7 @@ impl eframe::App for ContextorApp {
 
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             if ui.button("📋 Copy").clicked() {
-                                copy_to_clipboard(&self.output_preview);
+                                self.copy_summary();
                             }
                             if ui.button("⬆️ Expand").clicked() {
                                 self.output_expanded = true;
@@ -98,11 +467,121 @@ impl eframe::App for ContextorApp {
                     });
                 }
             });
+
+            ui.collapsing("Apply Model Edits", |ui| self.show_apply_panel(ui));
         });
     }
 }
 
 impl ContextorApp {
+    /// Starts with the recent projects and profiles of earlier sessions.
+    pub fn with_saved_config() -> Self {
+        let config_path = AppConfig::default_path();
+        let config = config_path
+            .as_deref()
+            .map(AppConfig::load)
+            .unwrap_or_default();
+        Self {
+            config,
+            config_path,
+            ..Self::default()
+        }
+    }
+
+    fn save_config(&mut self) {
+        let Some(path) = &self.config_path else {
+            return;
+        };
+        if let Err(e) = self.config.save(path) {
+            self.error_message = Some(format!("Failed to save settings: {}", e));
+        }
+    }
+
+    /// Saves the current settings under the entered name (replacing a
+    /// profile of the same name).
+    fn save_profile(&mut self, project: &Path) {
+        let name = self.profile_name.trim().to_string();
+        if name.is_empty() {
+            self.error_message = Some("Enter a profile name.".to_string());
+            return;
+        }
+        let Ok(max_file_size) = self.max_file_size.parse() else {
+            self.error_message = Some("Invalid file size. Enter a positive number.".to_string());
+            return;
+        };
+
+        let profile = ScanProfile {
+            name,
+            roots: self.project_roots(),
+            files: parse_file_list(&self.file_list),
+            max_file_size,
+            line_numbers: self.line_numbers,
+            query: self.query.clone(),
+            token_budget: self.token_budget.trim().parse().ok(),
+            include_with_deps: self.include_with_deps.clone(),
+            order: self.order,
+            priority: self.priority_patterns(),
+            manifest: self.manifest,
+            manifest_hash: self.manifest_hash,
+            graph: self.append_graph.then_some(self.graph_options.format),
+            graph_collapse: self.graph_options.collapse_by_directory,
+            graph_focus: self.graph_focus.clone(),
+            truncation: self
+                .truncate_rules
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            notebook_outputs: self.notebook_outputs,
+            sample_data: self.sample_data,
+            prompt: self.prompt.clone(),
+        };
+        self.config.save_profile(project, profile);
+        self.profile_name.clear();
+        self.error_message = None;
+        self.save_config();
+    }
+
+    fn apply_profile(&mut self, profile: ScanProfile) {
+        if !profile.roots.is_empty() {
+            self.expand_archives = profile.roots.iter().any(|root| root.expand_archives);
+            self.roots = profile
+                .roots
+                .into_iter()
+                .map(RootEntry::from_root)
+                .collect();
+        }
+        self.file_list = profile
+            .files
+            .iter()
+            .map(|file| format!("{}\n", file))
+            .collect();
+        self.max_file_size = profile.max_file_size.to_string();
+        self.line_numbers = profile.line_numbers;
+        self.query = profile.query;
+        self.token_budget = profile
+            .token_budget
+            .map(|budget| budget.to_string())
+            .unwrap_or_default();
+        self.include_with_deps = profile.include_with_deps;
+        self.order = profile.order;
+        self.priority = profile.priority.join(", ");
+        self.manifest = profile.manifest;
+        self.manifest_hash = profile.manifest_hash;
+        self.append_graph = profile.graph.is_some();
+        if let Some(format) = profile.graph {
+            self.graph_options.format = format;
+        }
+        self.graph_options.collapse_by_directory = profile.graph_collapse;
+        self.graph_focus = profile.graph_focus;
+        self.truncate_rules = profile.truncation.join("\n");
+        self.notebook_outputs = profile.notebook_outputs;
+        self.sample_data = profile.sample_data;
+        self.prompt = profile.prompt;
+        self.profile_name = profile.name;
+    }
+
     fn start_scan(&mut self) {
         self.error_message = None; // Reset errors
 
@@ -115,28 +594,655 @@ impl ContextorApp {
             }
         };
 
-        if let Some(folder) = &self.selected_folder {
-            let folder_path = folder.clone();
-            let (tx, rx) = mpsc::channel();
+        let Some(options) = self.bundle_options() else {
+            return;
+        };
+
+        if self.roots.is_empty() {
+            self.error_message = Some("No folder selected.".to_string());
+            return;
+        }
+
+        let roots = self.project_roots();
+        for root in &roots {
+            self.config.add_recent(&root.path);
+        }
+        self.save_config();
+        let listed = parse_file_list(&self.file_list);
+        if !listed.is_empty() && roots.len() > 1 {
+            self.error_message =
+                Some("An explicit file list needs exactly one folder.".to_string());
+            return;
+        }
+        let use_cache = self.use_cache;
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let scan_result = if listed.is_empty() {
+                scan_roots(&roots, max_file_size, use_cache).map(|files| (files, Vec::new()))
+            } else {
+                Ok(scan_file_list(&roots[0].path, &listed, max_file_size))
+            };
+            tx.send(scan_result).unwrap();
+        });
+
+        match rx.recv() {
+            Ok(Ok((files, rejected))) => {
+                if !rejected.is_empty() {
+                    let skipped: Vec<String> = rejected
+                        .iter()
+                        .map(|r| format!("{} ({})", r.path, r.reason))
+                        .collect();
+                    self.error_message = Some(format!("Skipped {}", skipped.join(", ")));
+                }
+                self.show_summary(files, &options);
+            }
+            Ok(Err(message)) => self.error_message = Some(message),
+            Err(_) => self.error_message = Some("Scan failed.".to_string()),
+        }
+    }
+
+    /// Dropped folders (and archives) are added as roots (the first one if none is selected
+    /// yet), dropped files go to the explicit file list. Folders are handled
+    /// first so files of a mixed drop resolve against them.
+    fn handle_drop(&mut self, paths: Vec<PathBuf>) {
+        let (folders, files): (Vec<PathBuf>, Vec<PathBuf>) =
+            paths.into_iter().partition(|p| p.is_dir() || is_archive(p));
+
+        for folder in folders {
+            let known = self
+                .roots
+                .iter()
+                .any(|entry| same_path(&entry.root.path, &folder));
+            if !known {
+                self.roots.push(RootEntry::new(folder));
+            }
+        }
+
+        let files: Vec<PathBuf> = files.into_iter().filter(|p| p.is_file()).collect();
+        if self.roots.is_empty() {
+            if let Some(parent) = common_parent(&files) {
+                self.roots.push(RootEntry::new(parent));
+            }
+        }
+        self.add_to_file_list(&files);
+    }
+
+    fn project_roots(&self) -> Vec<ProjectRoot> {
+        self.roots
+            .iter()
+            .map(|entry| ProjectRoot {
+                expand_archives: self.expand_archives,
+                ..entry.to_root()
+            })
+            .collect()
+    }
+
+    fn load_file_list(&mut self) {
+        let Some(path) = FileDialog::new().pick_file() else {
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => self.file_list = text,
+            Err(e) => {
+                self.error_message = Some(format!("Failed to read {}: {}", path.display(), e))
+            }
+        }
+    }
+
+    /// Appends paths to the file list, relative to the (first) folder when
+    /// they are inside it.
+    fn add_to_file_list(&mut self, paths: &[PathBuf]) {
+        let root = self
+            .roots
+            .first()
+            .and_then(|entry| entry.root.path.canonicalize().ok());
+        for path in paths {
+            let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            let shown = root
+                .as_ref()
+                .and_then(|root| path.strip_prefix(root).ok())
+                .unwrap_or(&path);
+            if !self.file_list.is_empty() && !self.file_list.ends_with('\n') {
+                self.file_list.push('\n');
+            }
+            self.file_list
+                .push_str(&shown.to_string_lossy().replace('\\', "/"));
+            self.file_list.push('\n');
+        }
+    }
+
+    /// Collects the UI settings, or reports an error if one is invalid.
+    fn bundle_options(&mut self) -> Option<BundleOptions> {
+        let token_budget = match self.token_budget.trim() {
+            "" => None,
+            text => match text.parse() {
+                Ok(val) => Some(Budget::Tokens(val)),
+                Err(_) => {
+                    self.error_message =
+                        Some("Invalid token budget. Enter a number or leave it empty.".to_string());
+                    return None;
+                }
+            },
+        };
+        let truncation = match self
+            .truncate_rules
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(TruncateRule::parse)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(rules) => rules,
+            Err(e) => {
+                self.error_message = Some(format!("Invalid truncation rule: {}", e));
+                return None;
+            }
+        };
+        let non_empty = |text: &str| {
+            let text = text.trim();
+            (!text.is_empty()).then(|| text.to_string())
+        };
+
+        Some(BundleOptions {
+            format: FormatOptions {
+                line_numbers: self.line_numbers,
+            },
+            query: non_empty(&self.query),
+            budget: token_budget,
+            include_with_deps: non_empty(&self.include_with_deps),
+            order: self.order,
+            priority: self.priority_patterns(),
+            graph: self.append_graph.then(|| self.graph_options()),
+            truncation,
+            notebooks: NotebookOptions {
+                include_outputs: self.notebook_outputs,
+                ..NotebookOptions::default()
+            },
+            sampling: if self.sample_data {
+                SampleRule::defaults()
+            } else {
+                Vec::new()
+            },
+            disk: self
+                .max_file_size
+                .parse()
+                .ok()
+                .map(|max_file_size| DiskFiles::new(&self.project_roots(), max_file_size)),
+            manifest: self.manifest.then(|| ManifestOptions {
+                roots: self.project_roots(),
+                section: true,
+                sidecar: None,
+            }),
+            manifest_hash: self.manifest_hash,
+        })
+    }
+
+    fn priority_patterns(&self) -> Vec<String> {
+        self.priority
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn show_summary(&mut self, files: Vec<ScannedFile>, options: &BundleOptions) {
+        let files = select_files(files, options);
+        let graph = DependencyGraph::build(&files);
+        self.output_preview = render_bundle_with_graph(&files, &graph, options);
+        self.preview_tree = build_tree(&files);
+        self.summary_files = Some((
+            self.project_roots(),
+            files.iter().map(|file| file.path.clone()).collect(),
+        ));
+        self.last_graph = Some(graph);
+        self.refresh_search();
+    }
+
+    /// Shows a status message instead of a summary.
+    fn set_preview_message(&mut self, message: String) {
+        self.output_preview = message;
+        self.preview_tree.clear();
+        self.summary_files = None;
+        self.refresh_search();
+    }
+
+    /// Finds the matches and file sections again after the preview or the
+    /// search changed.
+    fn refresh_search(&mut self) {
+        let search = &mut self.search;
+        search.anchors = tree_anchors(&self.output_preview, &self.preview_tree);
+        match find_matches(&self.output_preview, &search.query, search.options) {
+            Ok(matches) => {
+                search.matches = matches;
+                search.error = None;
+            }
+            Err(err) => {
+                search.matches.clear();
+                search.error = Some(err);
+            }
+        }
+        search.current = search.current.min(search.matches.len().saturating_sub(1));
+        search.layout = None;
+    }
+
+    /// Selects the match `step` places after the current one, wrapping around.
+    fn step_match(&mut self, step: isize) {
+        let search = &mut self.search;
+        let count = search.matches.len();
+        if count == 0 {
+            return;
+        }
+        search.current = (search.current as isize + step).rem_euclid(count as isize) as usize;
+        search.layout = None;
+        search.scroll_to = Some((search.matches[search.current].start, egui::Align::Center));
+    }
+
+    fn show_search_bar(&mut self, ui: &mut egui::Ui) {
+        let query = ui.add(
+            egui::TextEdit::singleline(&mut self.search.query)
+                .hint_text("Search preview")
+                .desired_width(200.0),
+        );
+        let mut changed = query.changed();
+        changed |= ui
+            .checkbox(&mut self.search.options.case_sensitive, "Aa")
+            .on_hover_text("Match case")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.search.options.regex, ".*")
+            .on_hover_text("Regular expression")
+            .changed();
+        if changed {
+            self.search.current = 0;
+            self.refresh_search();
+            self.step_match(0);
+        }
+
+        // Enter goes to the next match, Shift+Enter to the previous one
+        let (enter, shift) = ui.input(|i| (i.key_pressed(egui::Key::Enter), i.modifiers.shift));
+        let submitted = query.lost_focus() && enter;
+        if submitted {
+            query.request_focus();
+        }
+        let found = !self.search.matches.is_empty();
+        if ui
+            .add_enabled(found, egui::Button::new("Previous"))
+            .clicked()
+            || (submitted && shift)
+        {
+            self.step_match(-1);
+        }
+        if ui.add_enabled(found, egui::Button::new("Next")).clicked() || (submitted && !shift) {
+            self.step_match(1);
+        }
 
-            thread::spawn(move || {
-                let scan_result = scan_project(&folder_path.to_string_lossy(), max_file_size);
-                tx.send(scan_result).unwrap();
+        let search = &self.search;
+        if let Some(err) = &search.error {
+            ui.colored_label(egui::Color32::RED, "Invalid regular expression")
+                .on_hover_text(err);
+        } else if !search.query.is_empty() {
+            let count = search.matches.len();
+            let more = if count == MAX_MATCHES { "+" } else { "" };
+            ui.label(match count {
+                0 => "No matches".to_string(),
+                _ => format!("{} of {}{}", search.current + 1, count, more),
             });
+        }
+    }
 
-            match rx.recv() {
-                Ok(files) => {
-                    // Generate structured output
-                    let tree = build_tree(&files);
-                    let file_contents = format_file_contents(&files);
-                    let formatted_summary = format_project_summary(tree, file_contents);
+    fn show_jump_to_file(&mut self, ui: &mut egui::Ui) {
+        if self.search.anchors.is_empty() {
+            return;
+        }
+        ui.collapsing("Jump to File", |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt("jump_to_file")
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    for anchor in &self.search.anchors {
+                        let line = egui::RichText::new(&anchor.line).monospace();
+                        match anchor.offset {
+                            Some(offset) => {
+                                if ui
+                                    .selectable_label(false, line)
+                                    .on_hover_text(&anchor.path)
+                                    .clicked()
+                                {
+                                    self.search.scroll_to = Some((offset, egui::Align::Min));
+                                }
+                            }
+                            // Folders, and files left out of the sections
+                            None => {
+                                ui.label(line.weak());
+                            }
+                        }
+                    }
+                });
+        });
+    }
 
-                    self.output_preview = formatted_summary;
+    /// The editable preview text with the search matches highlighted. The
+    /// layout is only built again when the text, the matches, the width or
+    /// the theme changed.
+    fn show_preview_text(&mut self, ui: &mut egui::Ui) {
+        let search = &mut self.search;
+        let current = search.matches.get(search.current).cloned();
+        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+            let color = ui.visuals().text_color();
+            if let Some((laid_out_color, galley)) = &search.layout {
+                if *laid_out_color == color
+                    && galley.job.wrap.max_width == wrap_width
+                    && galley.text() == text
+                {
+                    return Arc::clone(galley);
                 }
-                Err(_) => self.error_message = Some("Scan failed.".to_string()),
             }
-        } else {
-            self.error_message = Some("No folder selected.".to_string());
+            let mut job = highlighted_job(ui, text, &search.matches, current.as_ref());
+            job.wrap.max_width = wrap_width;
+            let galley = ui.fonts(|fonts| fonts.layout_job(job));
+            search.layout = Some((color, Arc::clone(&galley)));
+            galley
+        };
+        let output = egui::TextEdit::multiline(&mut self.output_preview)
+            .font(egui::TextStyle::Monospace)
+            .desired_rows(20)
+            .layouter(&mut layouter)
+            .show(ui);
+
+        if let Some((offset, align)) = search.scroll_to.take() {
+            if let Some(before) = self.output_preview.get(..offset) {
+                let cursor = CCursor::new(before.chars().count());
+                let rect = output
+                    .galley
+                    .pos_from_ccursor(cursor)
+                    .translate(output.galley_pos.to_vec2());
+                ui.scroll_to_rect(rect, Some(align));
+            }
+        }
+        if output.response.changed() {
+            self.refresh_search();
+        }
+    }
+
+    fn toggle_watch(&mut self) {
+        if !self.watch_enabled {
+            self.watch = None;
+            return;
+        }
+
+        let ([entry], Ok(max_file_size)) =
+            (self.roots.as_slice(), self.max_file_size.parse::<u64>())
+        else {
+            self.error_message =
+                Some("Watching needs exactly one folder and a valid file size.".to_string());
+            self.watch_enabled = false;
+            return;
+        };
+
+        let root = entry.to_root();
+        let state =
+            ProjectState::scan_with_ignores(&root.path, max_file_size, root.ignore_patterns);
+        match ProjectWatcher::new(state.root(), DEFAULT_DEBOUNCE) {
+            Ok(watcher) => {
+                self.error_message = None;
+                self.watch = Some((state, watcher));
+                self.refresh_from_watch();
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to watch folder: {}", e));
+                self.watch_enabled = false;
+            }
         }
     }
+
+    fn poll_watch(&mut self) {
+        let mut changed = false;
+        if let Some((state, watcher)) = &mut self.watch {
+            while let Some(batch) = watcher.try_next_batch() {
+                changed |= state.apply_changes(&batch);
+            }
+        }
+        if changed {
+            self.refresh_from_watch();
+        }
+    }
+
+    fn refresh_from_watch(&mut self) {
+        let Some(options) = self.bundle_options() else {
+            return;
+        };
+        let Some((state, _)) = &self.watch else {
+            return;
+        };
+        let files = state.files().to_vec();
+        self.show_summary(files, &options);
+        if self.watch_clipboard {
+            self.copy_summary();
+        }
+    }
+
+    /// Copies the summary with the prompt and records its files as they are
+    /// now, so edits made for it can later be checked for conflicts.
+    fn copy_summary(&mut self) {
+        copy_to_clipboard(&with_prompt(&self.output_preview, &self.prompt));
+        if let Some((roots, paths)) = &self.summary_files {
+            self.sent_manifest = Some(Manifest::build(roots, paths));
+        }
+    }
+
+    fn show_apply_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label("Model reply with whole files (path: blocks) or unified diffs:");
+        ui.add(
+            egui::TextEdit::multiline(&mut self.model_reply)
+                .font(egui::TextStyle::Monospace)
+                .desired_rows(6),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Load Reply…").clicked() {
+                if let Some(path) = FileDialog::new().pick_file() {
+                    match std::fs::read_to_string(&path) {
+                        Ok(text) => self.model_reply = text,
+                        Err(e) => self.error_message = Some(format!("Failed to read reply: {}", e)),
+                    }
+                }
+            }
+            if ui.button("Preview Edits").clicked() {
+                self.preview_edits();
+            }
+        });
+
+        for pending in &mut self.pending_edits {
+            ui.horizontal(|ui| {
+                ui.add_enabled(
+                    pending.edit.status.can_apply(),
+                    egui::Checkbox::new(&mut pending.accepted, &pending.edit.path),
+                );
+                let color = match pending.edit.status {
+                    EditStatus::Ready => egui::Color32::GREEN,
+                    EditStatus::Unchanged => egui::Color32::GRAY,
+                    EditStatus::Conflict(_) => egui::Color32::ORANGE,
+                    EditStatus::Failed(_) => egui::Color32::RED,
+                };
+                ui.colored_label(color, pending.edit.status.label());
+            });
+            if pending.diff.is_empty() {
+                continue;
+            }
+            egui::CollapsingHeader::new(format!("Diff of {}", pending.edit.path))
+                .id_salt(&pending.edit.path)
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            for line in pending.diff.lines() {
+                                let color = match line.chars().next() {
+                                    Some('+') => egui::Color32::GREEN,
+                                    Some('-') => egui::Color32::RED,
+                                    Some('@') => egui::Color32::LIGHT_BLUE,
+                                    _ => ui.visuals().text_color(),
+                                };
+                                ui.label(egui::RichText::new(line).monospace().color(color));
+                            }
+                        });
+                });
+        }
+
+        let any_accepted = self.pending_edits.iter().any(|p| p.accepted);
+        if ui
+            .add_enabled(any_accepted, egui::Button::new("Apply Accepted"))
+            .clicked()
+        {
+            self.apply_accepted();
+        }
+        if let Some(message) = &self.apply_message {
+            ui.label(message);
+        }
+    }
+
+    /// Plans the edits of the pasted reply. Conflicts are checked against the
+    /// files of the summary as they were when it was last copied.
+    fn preview_edits(&mut self) {
+        self.pending_edits.clear();
+        let roots = self.project_roots();
+        if roots.is_empty() {
+            self.apply_message = Some("Select the project folder first.".to_string());
+            return;
+        }
+        let edits = parse_response(&self.model_reply);
+        if edits.is_empty() {
+            self.apply_message = Some("No file edits found in the reply.".to_string());
+            return;
+        }
+
+        let sent = self.sent_manifest.as_ref();
+        self.pending_edits = plan_edits(&roots, &edits, sent)
+            .into_iter()
+            .map(|edit| PendingEdit {
+                diff: edit.diff(),
+                accepted: edit.status == EditStatus::Ready,
+                edit,
+            })
+            .collect();
+        self.apply_message = sent.is_none().then(|| {
+            "No summary was copied yet, so changes made since cannot be detected.".to_string()
+        });
+    }
+
+    fn apply_accepted(&mut self) {
+        let Some(backup_dir) = default_backup_dir() else {
+            self.apply_message = Some("No folder for backups is available.".to_string());
+            return;
+        };
+        let accepted: Vec<PlannedEdit> = self
+            .pending_edits
+            .drain(..)
+            .filter(|pending| pending.accepted)
+            .map(|pending| pending.edit)
+            .collect();
+        let report = apply_edits(&self.project_roots(), &accepted, &backup_dir);
+
+        let mut message = format!(
+            "Wrote {} and deleted {} files.",
+            report.written.len(),
+            report.deleted.len()
+        );
+        if let Some(dir) = &report.backup_dir {
+            message.push_str(&format!(" Previous versions are in {}.", dir.display()));
+        }
+        for entry in &report.skipped {
+            message.push_str(&format!("\nSkipped {}: {}", entry.path, entry.reason));
+        }
+        self.apply_message = Some(message);
+    }
+
+    fn graph_options(&self) -> GraphExportOptions {
+        let focus = self.graph_focus.trim();
+        GraphExportOptions {
+            focus: (!focus.is_empty()).then(|| focus.to_string()),
+            ..self.graph_options.clone()
+        }
+    }
+
+    fn export_graph(&mut self) {
+        let Some(graph) = &self.last_graph else {
+            return;
+        };
+        let extension = match self.graph_options.format {
+            GraphFormat::Dot => "dot",
+            GraphFormat::Mermaid => "mmd",
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter(extension, &[extension])
+            .set_file_name(format!("dependencies.{}", extension))
+            .save_file()
+        else {
+            return;
+        };
+
+        if let Err(e) = std::fs::write(&path, render_graph(graph, &self.graph_options())) {
+            self.error_message = Some(format!("Failed to write {}: {}", path.display(), e));
+        }
+    }
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Deepest folder containing all of `files`.
+fn common_parent(files: &[PathBuf]) -> Option<PathBuf> {
+    let mut parents = files
+        .iter()
+        .filter_map(|f| f.canonicalize().ok()?.parent().map(Path::to_path_buf));
+    let mut common = parents.next()?;
+    for parent in parents {
+        while !parent.starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+    Some(common)
+}
+
+/// Lays out `text` in the monospace font with a background behind each
+/// match, a stronger one behind the `current` match.
+fn highlighted_job(
+    ui: &egui::Ui,
+    text: &str,
+    matches: &[Range<usize>],
+    current: Option<&Range<usize>>,
+) -> LayoutJob {
+    let plain = egui::TextFormat::simple(
+        egui::TextStyle::Monospace.resolve(ui.style()),
+        ui.visuals().text_color(),
+    );
+    let mut job = LayoutJob::default();
+    let mut end = 0;
+    for range in matches {
+        // The text may have been edited since the search ran
+        if range.start < end || text.get(range.clone()).is_none() {
+            continue;
+        }
+        job.append(&text[end..range.start], 0.0, plain.clone());
+        let background = if Some(range) == current {
+            egui::Color32::from_rgb(255, 160, 40)
+        } else {
+            egui::Color32::from_rgb(255, 230, 120)
+        };
+        job.append(
+            &text[range.clone()],
+            0.0,
+            egui::TextFormat {
+                color: egui::Color32::BLACK,
+                background,
+                ..plain.clone()
+            },
+        );
+        end = range.end;
+    }
+    job.append(&text[end..], 0.0, plain);
+    job
 }
@@ -0,0 +1,609 @@
+use crate::diff::unified_diff;
+use crate::filelist::RejectedPath;
+use crate::manifest::Manifest;
+use crate::roots::{split_root_path, unique_aliases, ProjectRoot};
+use crate::unpack::{strip_line_numbers, unrestorable};
+use crate::utils::{resolve_inside, sha256_hex};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A change to one file found in a model's reply.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileEdit {
+    /// Path as in the bundle (prefixed by the root alias for several roots).
+    pub path: String,
+    pub action: EditAction,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditAction {
+    /// Replace the whole file, or create it.
+    Write(String),
+    /// Apply the hunks of a unified diff.
+    Patch(Vec<Hunk>),
+    Delete,
+}
+
+/// One `@@` section of a unified diff.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hunk {
+    /// Line number from the header: the first line replaced, or the line
+    /// after which lines are inserted when nothing is replaced.
+    pub old_start: usize,
+    pub lines: Vec<HunkLine>,
+    /// The old side ends without a newline (`\ No newline at end of file`).
+    pub old_no_newline: bool,
+    /// The new side ends without a newline.
+    pub new_no_newline: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Finds the file edits in a model's reply: whole files in contextor's own
+/// `path:` format (the content optionally in a code fence) and unified diffs
+/// (`---`/`+++` headers, optionally after `diff --git`). Prose between them
+/// is skipped. Several edits of one file are applied in order.
+pub fn parse_response(text: &str) -> Vec<FileEdit> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut edits = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some((edit, next)) = parse_diff(&lines, i) {
+            edits.push(edit);
+            i = next;
+            continue;
+        }
+        if let Some(path) = block_header(lines[i]) {
+            let content_start = if is_fence(lines.get(i + 1)) {
+                i + 2
+            } else {
+                i + 1
+            };
+            // A header above a diff only names the file again
+            if !starts_diff(&lines, content_start) {
+                let (content, next) = parse_block(&lines, i + 1);
+                edits.push(FileEdit {
+                    path,
+                    action: EditAction::Write(content),
+                });
+                i = next;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    edits
+}
+
+/// The path of a `path:` line, allowing Markdown emphasis around it. Only
+/// path-like text counts, so prose ending in a colon is not a header.
+fn block_header(line: &str) -> Option<String> {
+    let decoration = |c: char| c == '*' || c == '`';
+    let path = line
+        .trim()
+        .trim_matches(decoration)
+        .strip_suffix(':')?
+        .trim_matches(decoration);
+    let is_path = path
+        .chars()
+        .all(|c| c.is_alphanumeric() || "._-/".contains(c))
+        && (path.contains('/') || path.contains('.'))
+        && !path.ends_with('.')
+        && !path.ends_with('/');
+    is_path.then(|| path.to_string())
+}
+
+fn is_fence(line: Option<&&str>) -> bool {
+    line.is_some_and(|line| fence_length(line).is_some())
+}
+
+/// The number of backticks opening a code fence on `line`, if it is one.
+fn fence_length(line: &str) -> Option<usize> {
+    let length = line.trim_start().chars().take_while(|&c| c == '`').count();
+    (length >= 3).then_some(length)
+}
+
+fn starts_diff(lines: &[&str], i: usize) -> bool {
+    let line = |i: usize| lines.get(i).copied().unwrap_or("");
+    line(i).starts_with("diff --git ")
+        || (line(i).starts_with("--- ") && line(i + 1).starts_with("+++ "))
+}
+
+/// The content of a whole-file block starting at `start`, and the line
+/// after it. Fenced content ends at the closing fence; otherwise the block
+/// ends before a blank line followed by another header or diff, as
+/// sections do in a bundle.
+fn parse_block(lines: &[&str], start: usize) -> (String, usize) {
+    let (body, next) = if let Some(open) = lines.get(start).and_then(|l| fence_length(l)) {
+        let end = closing_fence(lines, start + 1, open).unwrap_or(lines.len());
+        (&lines[start + 1..end], (end + 1).min(lines.len()))
+    } else {
+        let end = (start..lines.len())
+            .find(|&i| {
+                lines[i].trim().is_empty()
+                    && (lines.get(i + 1).is_some_and(|l| block_header(l).is_some())
+                        || starts_diff(lines, i + 1)
+                        || (is_fence(lines.get(i + 1)) && starts_diff(lines, i + 2)))
+            })
+            .unwrap_or(lines.len());
+        (&lines[start..end], end)
+    };
+
+    let kept = body.len()
+        - body
+            .iter()
+            .rev()
+            .take_while(|line| line.trim().is_empty())
+            .count();
+    let mut content = body[..kept].join("\n");
+    if kept > 0 {
+        content.push('\n');
+    }
+    (content, next)
+}
+
+/// The line closing a code fence of `open` backticks whose content starts
+/// at `start`: a bare fence at least as long. Markdown files often contain
+/// fences of their own, so a fence with a language after it opens a nested
+/// block, and the next bare fence closes that one instead.
+fn closing_fence(lines: &[&str], start: usize, open: usize) -> Option<usize> {
+    let mut nested = 0;
+    (start..lines.len()).find(|&i| {
+        let line = lines[i].trim();
+        let Some(length) = fence_length(line) else {
+            return false;
+        };
+        if line.len() > length {
+            nested += 1;
+            false
+        } else if nested > 0 {
+            nested -= 1;
+            false
+        } else {
+            length >= open
+        }
+    })
+}
+
+/// A unified diff of one file starting at line `i`, and the line after it.
+fn parse_diff(lines: &[&str], mut i: usize) -> Option<(FileEdit, usize)> {
+    if lines[i].starts_with("diff --git ") {
+        i += 1;
+        // Mode and index lines
+        while i < lines.len() && !lines[i].starts_with("--- ") && !lines[i].starts_with("diff ") {
+            i += 1;
+        }
+    }
+    let old_path = diff_path(lines.get(i)?.strip_prefix("--- ")?);
+    let new_path = diff_path(lines.get(i + 1)?.strip_prefix("+++ ")?);
+    i += 2;
+
+    let mut hunks = Vec::new();
+    while let Some((old_start, old_count, new_count)) =
+        lines.get(i).and_then(|line| parse_hunk_header(line))
+    {
+        i += 1;
+        let mut hunk = Hunk {
+            old_start,
+            ..Hunk::default()
+        };
+        let (mut old_left, mut new_left) = (old_count, new_count);
+        while i < lines.len() {
+            let line = lines[i];
+            let done = old_left == 0 && new_left == 0;
+            match line.chars().next() {
+                Some('\\') => match hunk.lines.last() {
+                    Some(HunkLine::Removed(_)) => hunk.old_no_newline = true,
+                    Some(HunkLine::Added(_)) => hunk.new_no_newline = true,
+                    _ => {
+                        hunk.old_no_newline = true;
+                        hunk.new_no_newline = true;
+                    }
+                },
+                _ if done => break,
+                // Some models drop the space of empty context lines
+                Some(' ') | None => {
+                    hunk.lines
+                        .push(HunkLine::Context(line.get(1..).unwrap_or("").to_string()));
+                    old_left = old_left.saturating_sub(1);
+                    new_left = new_left.saturating_sub(1);
+                }
+                Some('-') => {
+                    hunk.lines.push(HunkLine::Removed(line[1..].to_string()));
+                    old_left = old_left.saturating_sub(1);
+                }
+                Some('+') => {
+                    hunk.lines.push(HunkLine::Added(line[1..].to_string()));
+                    new_left = new_left.saturating_sub(1);
+                }
+                _ => break,
+            }
+            i += 1;
+        }
+        hunks.push(hunk);
+    }
+    if hunks.is_empty() {
+        return None;
+    }
+
+    let edit = match (old_path, new_path) {
+        (_, Some(path)) => FileEdit {
+            path,
+            action: EditAction::Patch(hunks),
+        },
+        (Some(path), None) => FileEdit {
+            path,
+            action: EditAction::Delete,
+        },
+        (None, None) => return None,
+    };
+    Some((edit, i))
+}
+
+/// The path of a `---`/`+++` line without the `a/`/`b/` prefix, or `None`
+/// for `/dev/null`.
+fn diff_path(text: &str) -> Option<String> {
+    // Some tools append a timestamp after a tab
+    let path = text.split('\t').next().unwrap_or("").trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Start line and line counts of the old and new side from
+/// `@@ -a,b +c,d @@`.
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize)> {
+    let (ranges, _) = line.strip_prefix("@@ -")?.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = range(old)?;
+    let (_, new_count) = range(new)?;
+    Some((old_start, old_count, new_count))
+}
+
+/// Applies `hunks` to `original`. Each hunk goes where its old lines match
+/// (ignoring trailing whitespace) closest to the line its header names, so
+/// line numbers that are a little off still apply. The file keeps its line
+/// endings, `\r\n` or `\n`.
+pub fn apply_patch(original: &str, hunks: &[Hunk]) -> Result<String, String> {
+    let line_ending = match original.find('\n') {
+        Some(end) if original[..end].ends_with('\r') => "\r\n",
+        _ => "\n",
+    };
+    let lines: Vec<&str> = original.lines().collect();
+    let mut output: Vec<&str> = Vec::new();
+    let mut position = 0;
+    let mut ends_with_newline = original.is_empty() || original.ends_with('\n');
+
+    for (n, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk
+            .lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Removed(text) => Some(text.as_str()),
+                HunkLine::Added(_) => None,
+            })
+            .collect();
+        let expected = if old.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let at = find_lines(&lines, &old, expected, position)
+            .ok_or_else(|| format!("hunk {} does not match the file", n + 1))?;
+
+        output.extend(&lines[position..at]);
+        let mut current = at;
+        for line in &hunk.lines {
+            match line {
+                HunkLine::Context(_) => {
+                    output.push(lines[current]);
+                    current += 1;
+                }
+                HunkLine::Removed(_) => current += 1,
+                HunkLine::Added(text) => output.push(text),
+            }
+        }
+        position = current;
+        if hunk.new_no_newline {
+            ends_with_newline = false;
+        } else if hunk.old_no_newline {
+            ends_with_newline = true;
+        }
+    }
+    output.extend(&lines[position..]);
+
+    let mut result = output.join(line_ending);
+    if ends_with_newline && !output.is_empty() {
+        result.push_str(line_ending);
+    }
+    Ok(result)
+}
+
+/// Where `needle` occurs in `lines` at or after `from`, closest to `expected`.
+fn find_lines(lines: &[&str], needle: &[&str], expected: usize, from: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(expected.clamp(from, lines.len().max(from)));
+    }
+    let last = lines.len().checked_sub(needle.len())?;
+    (from..=last)
+        .filter(|&at| {
+            lines[at..at + needle.len()]
+                .iter()
+                .zip(needle)
+                .all(|(a, b)| a.trim_end() == b.trim_end())
+        })
+        .min_by_key(|&at| at.abs_diff(expected))
+}
+
+/// Whether a planned edit can be written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditStatus {
+    Ready,
+    /// The edit would leave the file as it is.
+    Unchanged,
+    /// The file is not as it was in the bundle the edit was made for, so
+    /// the edit may undo other changes. Can still be written on request.
+    Conflict(String),
+    /// The edit cannot be written.
+    Failed(String),
+}
+
+impl EditStatus {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Ready => "ready".to_string(),
+            Self::Unchanged => "unchanged".to_string(),
+            Self::Conflict(reason) => format!("conflict: {}", reason),
+            Self::Failed(reason) => format!("failed: {}", reason),
+        }
+    }
+
+    /// Ready, or a conflict the user may accept.
+    pub fn can_apply(&self) -> bool {
+        matches!(self, Self::Ready | Self::Conflict(_))
+    }
+}
+
+/// The outcome of all edits of one file, worked out against the files on
+/// disk without writing anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlannedEdit {
+    pub path: String,
+    /// Content on disk, `None` if the file does not exist.
+    pub old: Option<String>,
+    /// Content after the edits, `None` to delete the file.
+    pub new: Option<String>,
+    pub status: EditStatus,
+}
+
+impl PlannedEdit {
+    /// The change as a unified diff, for review.
+    pub fn diff(&self) -> String {
+        unified_diff(&self.path, self.old.as_deref(), self.new.as_deref())
+    }
+}
+
+/// Works out every edit against the files in `roots`. With the manifest of
+/// the bundle that was `sent`, files that changed since, or were not in it,
+/// are conflicts.
+pub fn plan_edits(
+    roots: &[ProjectRoot],
+    edits: &[FileEdit],
+    sent: Option<&Manifest>,
+) -> Vec<PlannedEdit> {
+    let mut roots = roots.to_vec();
+    unique_aliases(&mut roots);
+
+    let mut paths: Vec<&str> = Vec::new();
+    for edit in edits {
+        if !paths.contains(&edit.path.as_str()) {
+            paths.push(&edit.path);
+        }
+    }
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let file_edits = edits.iter().filter(|edit| edit.path == path);
+            plan_file(&roots, path, file_edits, sent).unwrap_or_else(|reason| PlannedEdit {
+                path: path.to_string(),
+                old: None,
+                new: None,
+                status: EditStatus::Failed(reason),
+            })
+        })
+        .collect()
+}
+
+fn plan_file<'a>(
+    roots: &[ProjectRoot],
+    path: &str,
+    edits: impl Iterator<Item = &'a FileEdit>,
+    sent: Option<&Manifest>,
+) -> Result<PlannedEdit, String> {
+    let destination = locate(roots, path)?;
+    let (old, hash) = read_text(&destination)?;
+
+    let mut new = old.clone();
+    for edit in edits {
+        new = match &edit.action {
+            EditAction::Write(content) => Some(whole_file(path, content)?),
+            EditAction::Delete => None,
+            EditAction::Patch(hunks) => {
+                let adds_only = hunks.iter().all(|hunk| {
+                    hunk.lines
+                        .iter()
+                        .all(|line| matches!(line, HunkLine::Added(_)))
+                });
+                match &new {
+                    Some(content) => Some(apply_patch(content, hunks)?),
+                    None if adds_only => Some(apply_patch("", hunks)?),
+                    None => return Err("the diff changes a file that does not exist".to_string()),
+                }
+            }
+        };
+    }
+
+    let status = if new == old {
+        EditStatus::Unchanged
+    } else {
+        match sent.map(|manifest| manifest.files.iter().find(|f| f.path == path)) {
+            Some(Some(file)) if hash.as_ref() != Some(&file.sha256) => {
+                EditStatus::Conflict(if hash.is_some() {
+                    "changed since the bundle was made".to_string()
+                } else {
+                    "deleted since the bundle was made".to_string()
+                })
+            }
+            Some(None) if old.is_some() => {
+                EditStatus::Conflict("not in the bundle the edit was made for".to_string())
+            }
+            _ => EditStatus::Ready,
+        }
+    };
+    Ok(PlannedEdit {
+        path: path.to_string(),
+        old,
+        new,
+        status,
+    })
+}
+
+/// The content of a whole-file edit. Sections copied from the bundle that
+/// are not the whole file (placeholders, truncated or sampled content, line
+/// numbers) would overwrite the real file with them, so they fail.
+fn whole_file(path: &str, content: &str) -> Result<String, String> {
+    if let Some(reason) = unrestorable(path, content) {
+        return Err(format!("not the whole file ({})", reason));
+    }
+    if strip_line_numbers(content).is_some() {
+        return Err("not the whole file (has line numbers)".to_string());
+    }
+    if content.trim_end() == "[Empty file]" {
+        return Ok(String::new());
+    }
+    Ok(content.to_string())
+}
+
+/// The file on disk behind a bundle path, which must stay inside its root.
+fn locate(roots: &[ProjectRoot], path: &str) -> Result<PathBuf, String> {
+    let (root, relative) =
+        split_root_path(roots, path).ok_or("not in any of the folders".to_string())?;
+    let root = root
+        .path
+        .canonicalize()
+        .map_err(|e| format!("cannot resolve {}: {}", root.path.display(), e))?;
+    resolve_inside(&root, relative)
+}
+
+/// A file's text and the hash of its bytes, or `None`s if it is missing.
+fn read_text(path: &Path) -> Result<(Option<String>, Option<String>), String> {
+    match fs::read(path) {
+        Ok(data) => {
+            let hash = sha256_hex(&data);
+            let text = String::from_utf8(data).map_err(|_| "not a text file".to_string())?;
+            Ok((Some(text), Some(hash)))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((None, None)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// What [`apply_edits`] did.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ApplyReport {
+    pub written: Vec<String>,
+    pub deleted: Vec<String>,
+    pub skipped: Vec<RejectedPath>,
+    /// Where the previous versions of changed files were copied, if any.
+    pub backup_dir: Option<PathBuf>,
+}
+
+/// A new folder for backups in the user's local data directory, named by
+/// the current time.
+pub fn default_backup_dir() -> Option<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    dirs::data_local_dir().map(|dir| {
+        dir.join("contextor")
+            .join("backups")
+            .join(millis.to_string())
+    })
+}
+
+/// Writes the planned `edits` the user accepted. Every file is first copied
+/// to the same path below `backup_dir`. Edits that cannot be applied, and
+/// files that changed since they were planned, are skipped.
+pub fn apply_edits(roots: &[ProjectRoot], edits: &[PlannedEdit], backup_dir: &Path) -> ApplyReport {
+    let mut roots = roots.to_vec();
+    unique_aliases(&mut roots);
+
+    let mut report = ApplyReport::default();
+    for edit in edits {
+        let result = if edit.status.can_apply() {
+            apply_edit(&roots, edit, backup_dir, &mut report.backup_dir)
+        } else {
+            Err(edit.status.label())
+        };
+        match result {
+            Ok(()) if edit.new.is_some() => report.written.push(edit.path.clone()),
+            Ok(()) => report.deleted.push(edit.path.clone()),
+            Err(reason) => report.skipped.push(RejectedPath {
+                path: edit.path.clone(),
+                reason,
+            }),
+        }
+    }
+    report
+}
+
+fn apply_edit(
+    roots: &[ProjectRoot],
+    edit: &PlannedEdit,
+    backup_dir: &Path,
+    backup_used: &mut Option<PathBuf>,
+) -> Result<(), String> {
+    let destination = locate(roots, &edit.path)?;
+    let (current, _) = read_text(&destination)?;
+    if current != edit.old {
+        return Err("changed since the preview".to_string());
+    }
+
+    if edit.old.is_some() {
+        let backup = backup_dir.join(&edit.path);
+        if let Some(parent) = backup.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("cannot back up: {}", e))?;
+        }
+        fs::copy(&destination, &backup).map_err(|e| format!("cannot back up: {}", e))?;
+        *backup_used = Some(backup_dir.to_path_buf());
+    }
+
+    match &edit.new {
+        Some(content) => {
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::write(&destination, content).map_err(|e| e.to_string())
+        }
+        None => fs::remove_file(&destination).map_err(|e| e.to_string()),
+    }
+}
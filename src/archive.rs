@@ -0,0 +1,287 @@
+use crate::scanner::{file_from_bytes, size_placeholder, LineSpan, ScannedFile};
+use flate2::read::GzDecoder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// Whether `path` names an archive [`scan_archive`] can read.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    [".zip", ".tar", ".tar.gz", ".tgz"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// Path of the entry added when an archive was cut off at its
+/// [`ArchiveLimits`].
+pub const TRUNCATED_ARCHIVE_ENTRY: &str = "[archive truncated]";
+
+/// First line of that entry's content.
+pub const TRUNCATED_ARCHIVE_PREFIX: &str = "[Archive truncated: ";
+
+/// How much of an archive is read into memory, so a zip bomb or an archive
+/// of countless small files cannot exhaust it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    /// Entries read; later ones are left out.
+    pub max_entries: usize,
+    /// Bytes of entry content kept, over all entries.
+    pub max_bytes: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 100_000,
+            max_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// An archive entry as read into memory. Oversized entries are not read.
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    data: Option<Vec<u8>>,
+}
+
+/// The entries read so far, and whether the limits cut the archive off.
+struct Entries {
+    limits: ArchiveLimits,
+    entries: Vec<Entry>,
+    bytes: u64,
+    truncated: bool,
+}
+
+impl Entries {
+    fn new(limits: ArchiveLimits) -> Self {
+        Self {
+            limits,
+            entries: Vec::new(),
+            bytes: 0,
+            truncated: false,
+        }
+    }
+
+    /// Whether an entry of `size` may still be read. Entries over the size
+    /// limit cost no bytes, as they are not read.
+    fn fits(&mut self, size: u64, max_file_size: u64) -> bool {
+        let bytes = if size > max_file_size { 0 } else { size };
+        self.truncated = self.entries.len() >= self.limits.max_entries
+            || self.bytes.saturating_add(bytes) > self.limits.max_bytes;
+        !self.truncated
+    }
+
+    fn push(&mut self, entry: Entry) {
+        self.bytes += entry.data.as_ref().map_or(0, |data| data.len() as u64);
+        self.entries.push(entry);
+    }
+}
+
+/// Scans the files inside a zip or (gzipped) tar archive as if it were an
+/// extracted folder: the same size limit and binary detection apply, and so
+/// do `ignore_patterns` and any `.gitignore` files inside the archive. Paths
+/// are relative to the archive root. Entries whose path would escape it are
+/// skipped.
+pub fn scan_archive(
+    archive_path: &Path,
+    max_file_size: u64,
+    ignore_patterns: &[String],
+) -> io::Result<Vec<ScannedFile>> {
+    scan_archive_with_limits(
+        archive_path,
+        max_file_size,
+        ignore_patterns,
+        ArchiveLimits::default(),
+    )
+}
+
+/// Like [`scan_archive`], reading no more than `limits` allow. An archive
+/// that was cut off gets a [`TRUNCATED_ARCHIVE_ENTRY`] saying so.
+pub fn scan_archive_with_limits(
+    archive_path: &Path,
+    max_file_size: u64,
+    ignore_patterns: &[String],
+    limits: ArchiveLimits,
+) -> io::Result<Vec<ScannedFile>> {
+    let read = read_entries(archive_path, max_file_size, limits)?;
+    let matchers = ignore_matchers(&read.entries, ignore_patterns);
+
+    let mut files: Vec<ScannedFile> = read
+        .entries
+        .iter()
+        .filter(|entry| !is_ignored(&matchers, &entry.path))
+        .map(|entry| match &entry.data {
+            Some(data) => file_from_bytes(&entry.path, data, max_file_size),
+            None => size_placeholder(&entry.path, entry.size, max_file_size),
+        })
+        .collect();
+    if read.truncated {
+        files.push(ScannedFile {
+            path: TRUNCATED_ARCHIVE_ENTRY.to_string(),
+            content: Some(format!(
+                "{}entries after the first {} were not read (limits: {} entries, {:.1}MB)]",
+                TRUNCATED_ARCHIVE_PREFIX,
+                read.entries.len(),
+                limits.max_entries,
+                limits.max_bytes as f64 / 1_000_000.0
+            )),
+            is_binary: false,
+            line_origins: Some(vec![LineSpan::Marker]),
+        });
+    }
+    Ok(files)
+}
+
+/// Replaces every archive among `files` (scanned from `root`) with its
+/// entries, nested under the archive's path like a directory, e.g.
+/// `vendor/drop.zip/src/main.rs`. Archives that cannot be read are kept.
+pub fn expand_archives(
+    root: &Path,
+    files: Vec<ScannedFile>,
+    max_file_size: u64,
+    ignore_patterns: &[String],
+) -> Vec<ScannedFile> {
+    let mut expanded = Vec::with_capacity(files.len());
+    for file in files {
+        if !is_archive(Path::new(&file.path)) {
+            expanded.push(file);
+            continue;
+        }
+        match scan_archive(&root.join(&file.path), max_file_size, ignore_patterns) {
+            Ok(entries) => expanded.extend(entries.into_iter().map(|mut entry| {
+                entry.path = format!("{}/{}", file.path, entry.path);
+                entry
+            })),
+            Err(_) => expanded.push(file),
+        }
+    }
+    expanded
+}
+
+fn read_entries(
+    archive_path: &Path,
+    max_file_size: u64,
+    limits: ArchiveLimits,
+) -> io::Result<Entries> {
+    let name = archive_path.to_string_lossy().to_lowercase();
+    let file = File::open(archive_path)?;
+    let entries = Entries::new(limits);
+    if name.ends_with(".zip") {
+        read_zip(file, max_file_size, entries)
+    } else if name.ends_with(".tar") {
+        read_tar(file, max_file_size, entries)
+    } else {
+        read_tar(GzDecoder::new(file), max_file_size, entries)
+    }
+}
+
+fn read_zip(file: File, max_file_size: u64, mut entries: Entries) -> io::Result<Entries> {
+    let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(io::Error::other)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(path) = entry.enclosed_name().filter(|p| is_safe(p)) else {
+            continue;
+        };
+        let size = entry.size();
+        if !entries.fits(size, max_file_size) {
+            break;
+        }
+        let data = read_limited(&mut entry, size, max_file_size)?;
+        entries.push(Entry { path, size, data });
+    }
+    Ok(entries)
+}
+
+fn read_tar(reader: impl Read, max_file_size: u64, mut entries: Entries) -> io::Result<Entries> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        if !is_safe(&path) {
+            continue;
+        }
+        let size = entry.size();
+        if !entries.fits(size, max_file_size) {
+            break;
+        }
+        let data = read_limited(&mut entry, size, max_file_size)?;
+        entries.push(Entry { path, size, data });
+    }
+    Ok(entries)
+}
+
+/// Reads an entry unless its declared size is over the limit. The read is
+/// capped too, since the declared size may lie.
+fn read_limited(reader: impl Read, size: u64, max_file_size: u64) -> io::Result<Option<Vec<u8>>> {
+    if size > max_file_size {
+        return Ok(None);
+    }
+    let mut data = Vec::new();
+    reader.take(max_file_size).read_to_end(&mut data)?;
+    Ok(Some(data))
+}
+
+/// Only plain relative paths, and nothing inside `.git`.
+fn is_safe(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_)))
+        && !path.components().any(|c| c.as_os_str() == ".git")
+}
+
+/// The extra patterns first, then one matcher per `.gitignore` in the
+/// archive, deepest first.
+fn ignore_matchers(entries: &[Entry], ignore_patterns: &[String]) -> Vec<Gitignore> {
+    let mut gitignores: Vec<(PathBuf, &[u8])> = entries
+        .iter()
+        .filter(|e| e.path.file_name().is_some_and(|n| n == ".gitignore"))
+        .filter_map(|e| {
+            let dir = e.path.parent().unwrap_or(Path::new("")).to_path_buf();
+            Some((dir, e.data.as_deref()?))
+        })
+        .collect();
+    gitignores.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+
+    let build = |dir: &Path, lines: &mut dyn Iterator<Item = &str>| {
+        let mut builder = GitignoreBuilder::new(dir);
+        for line in lines {
+            // Invalid patterns are skipped rather than failing the whole scan
+            let _ = builder.add_line(None, line);
+        }
+        builder.build().ok()
+    };
+
+    let mut matchers = Vec::new();
+    matchers.extend(build(
+        Path::new(""),
+        &mut ignore_patterns.iter().map(String::as_str),
+    ));
+    for (dir, data) in gitignores {
+        matchers.extend(build(&dir, &mut String::from_utf8_lossy(data).lines()));
+    }
+    matchers
+}
+
+fn is_ignored(matchers: &[Gitignore], path: &Path) -> bool {
+    for matcher in matchers {
+        if !path.starts_with(matcher.path()) {
+            continue;
+        }
+        match matcher.matched_path_or_any_parents(path, false) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+    false
+}
@@ -0,0 +1,381 @@
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek};
+use std::path::Path;
+
+/// Archive entries listed in a description before the rest is summarised.
+const MAX_LISTED_ENTRIES: usize = 20;
+
+/// Bytes read from the start of a file too large to read whole, enough for
+/// the headers [`describe_binary_file`] looks at.
+pub const HEADER_BYTES: u64 = 64 * 1024;
+
+/// Tar data read to list the entries of an archive. Listing stops there, so
+/// a huge archive or a gzip bomb cannot exhaust time or memory.
+const MAX_TAR_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Where an archive's bytes can be read again to list its entries.
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Bytes(&'a [u8]),
+    File(&'a Path),
+}
+
+/// Describes a binary file without its bytes: the detected type, its size,
+/// and what the header reveals cheaply (image dimensions and color format,
+/// archive entries, target of compiled artifacts).
+///
+/// For example `[Binary file: PNG image, 640x480, RGBA 8-bit, 12.3 KB]`.
+pub fn describe_binary(path: &str, data: &[u8]) -> String {
+    describe(path, data, Source::Bytes(data), data.len() as u64)
+}
+
+/// Like [`describe_binary`] for a file too large to read: `head` holds its
+/// first [`HEADER_BYTES`], and archives are listed from `file` without
+/// loading it.
+pub fn describe_binary_file(path: &str, file: &Path, head: &[u8], size: u64) -> String {
+    describe(path, head, Source::File(file), size)
+}
+
+/// The first [`HEADER_BYTES`] of `file`.
+pub fn read_head(file: &Path) -> io::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    File::open(file)?
+        .take(HEADER_BYTES)
+        .read_to_end(&mut head)?;
+    Ok(head)
+}
+
+fn describe(path: &str, data: &[u8], source: Source, size: u64) -> String {
+    let size = format_size(size);
+    match detect(path, data, source) {
+        Some(kind) => format!("[Binary file: {}, {}]", kind, size),
+        None => format!("[Binary file: {}]", size),
+    }
+}
+
+/// `512 bytes`, `12.3 KB`, `4.0 MB`.
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=999 => format!("{} bytes", bytes),
+        1_000..=999_999 => format!("{:.1} KB", bytes as f64 / 1_000.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_000_000.0),
+    }
+}
+
+fn detect(path: &str, data: &[u8], source: Source) -> Option<String> {
+    let lower = path.to_lowercase();
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(describe_png(data));
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        let (w, h) = (u16_le(data, 6)?, u16_le(data, 8)?);
+        return Some(format!("GIF image, {}x{}", w, h));
+    }
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(describe_jpeg(data));
+    }
+    if data.starts_with(b"BM") && data.len() >= 30 {
+        let width = u32_le(data, 18)? as i32;
+        let height = u32_le(data, 22)? as i32;
+        let bits = u16_le(data, 28)?;
+        return Some(format!(
+            "BMP image, {}x{}, {}-bit",
+            width.unsigned_abs(),
+            height.unsigned_abs(),
+            bits
+        ));
+    }
+    if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        return Some(describe_webp(data));
+    }
+    if data.starts_with(&[0, 0, 1, 0]) && lower.ends_with(".ico") {
+        return Some(format!("ICO icon, {} images", u16_le(data, 4)?));
+    }
+    if data.starts_with(b"%PDF-") {
+        let version: String = data[5..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit() || **b == b'.')
+            .map(|&b| b as char)
+            .collect();
+        return Some(format!("PDF document, version {}", version));
+    }
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        return Some(match source {
+            Source::Bytes(data) => describe_zip(Cursor::new(data)),
+            Source::File(file) => {
+                File::open(file).map_or_else(|_| unreadable("ZIP archive"), describe_zip)
+            }
+        });
+    }
+    if data.starts_with(&[0x1F, 0x8B]) {
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            let tar = match source {
+                Source::Bytes(data) => describe_tar(GzDecoder::new(data)),
+                Source::File(file) => File::open(file).map_or_else(
+                    |_| unreadable("tar archive"),
+                    |f| describe_tar(GzDecoder::new(f)),
+                ),
+            };
+            return Some(format!("gzip-compressed {}", tar));
+        }
+        return Some("gzip compressed data".to_string());
+    }
+    if data.get(257..262) == Some(b"ustar") {
+        return Some(match source {
+            Source::Bytes(data) => describe_tar(data),
+            Source::File(file) => {
+                File::open(file).map_or_else(|_| unreadable("tar archive"), describe_tar)
+            }
+        });
+    }
+    if data.starts_with(b"\x7FELF") {
+        return describe_elf(data);
+    }
+    if data.starts_with(b"MZ") {
+        return Some(describe_pe(data).unwrap_or_else(|| "DOS executable".to_string()));
+    }
+    if let Some(kind) = describe_mach_o(data) {
+        return Some(kind);
+    }
+    if data.starts_with(b"\0asm") {
+        return Some(format!("WebAssembly module, version {}", u32_le(data, 4)?));
+    }
+    if data.starts_with(b"SQLite format 3\0") {
+        return Some("SQLite 3 database".to_string());
+    }
+    if data.starts_with(b"wOFF") || data.starts_with(b"wOF2") {
+        return Some("WOFF font".to_string());
+    }
+    if data.starts_with(&[0, 1, 0, 0]) && (lower.ends_with(".ttf") || lower.ends_with(".otf"))
+        || data.starts_with(b"OTTO")
+    {
+        return Some("TrueType/OpenType font".to_string());
+    }
+    if lower.ends_with(".pyc") {
+        return Some("Python bytecode".to_string());
+    }
+    None
+}
+
+fn u16_le(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u16_be(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u32_be(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn describe_png(data: &[u8]) -> String {
+    let (Some(width), Some(height), Some(&depth), Some(&color)) = (
+        u32_be(data, 16),
+        u32_be(data, 20),
+        data.get(24),
+        data.get(25),
+    ) else {
+        return "PNG image".to_string();
+    };
+    let color = match color {
+        0 => "grayscale",
+        2 => "RGB",
+        3 => "indexed",
+        4 => "grayscale+alpha",
+        6 => "RGBA",
+        _ => "unknown color",
+    };
+    format!("PNG image, {}x{}, {} {}-bit", width, height, color, depth)
+}
+
+fn describe_jpeg(data: &[u8]) -> String {
+    // Walk the segments up to the start-of-frame marker
+    let mut at = 2;
+    while at + 9 < data.len() && data[at] == 0xFF {
+        let marker = data[at + 1];
+        let is_frame = (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker);
+        if is_frame {
+            let (Some(height), Some(width)) = (u16_be(data, at + 5), u16_be(data, at + 7)) else {
+                break;
+            };
+            let color = match data[at + 9] {
+                1 => "grayscale",
+                3 => "YCbCr",
+                4 => "CMYK",
+                _ => "unknown color",
+            };
+            return format!("JPEG image, {}x{}, {}", width, height, color);
+        }
+        let Some(length) = u16_be(data, at + 2) else {
+            break;
+        };
+        at += 2 + length as usize;
+    }
+    "JPEG image".to_string()
+}
+
+fn describe_webp(data: &[u8]) -> String {
+    let u24 = |at: usize| -> Option<u32> {
+        let b = data.get(at..at + 3)?;
+        Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+    };
+    let size = match data.get(12..16) {
+        Some(b"VP8X") => u24(24).zip(u24(27)).map(|(w, h)| (w + 1, h + 1)),
+        Some(b"VP8 ") => u16_le(data, 26)
+            .zip(u16_le(data, 28))
+            .map(|(w, h)| (u32::from(w & 0x3FFF), u32::from(h & 0x3FFF))),
+        Some(b"VP8L") => {
+            u32_le(data, 21).map(|bits| ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        _ => None,
+    };
+    match size {
+        Some((w, h)) => format!("WebP image, {}x{}", w, h),
+        None => "WebP image".to_string(),
+    }
+}
+
+fn unreadable(kind: &str) -> String {
+    format!("{} (unreadable)", kind)
+}
+
+/// `complete` is false if only the first `names` could be listed.
+fn list_entries(kind: &str, names: Vec<String>, complete: bool) -> String {
+    let more = if complete { "" } else { "+" };
+    let mut description = format!("{}, {}{} entries", kind, names.len(), more);
+    if !names.is_empty() {
+        let listed: Vec<&str> = names
+            .iter()
+            .take(MAX_LISTED_ENTRIES)
+            .map(String::as_str)
+            .collect();
+        description.push_str(": ");
+        description.push_str(&listed.join(", "));
+        if names.len() > MAX_LISTED_ENTRIES {
+            description.push_str(&format!(", … ({} more)", names.len() - MAX_LISTED_ENTRIES));
+        }
+    }
+    description
+}
+
+/// Only the central directory is read, so this is cheap for files as well.
+fn describe_zip(reader: impl Read + Seek) -> String {
+    match zip::ZipArchive::new(reader) {
+        Ok(archive) => list_entries(
+            "ZIP archive",
+            archive.file_names().map(str::to_string).collect(),
+            true,
+        ),
+        Err(_) => unreadable("ZIP archive"),
+    }
+}
+
+/// Streams the entry headers, reading at most [`MAX_TAR_BYTES`].
+fn describe_tar(reader: impl Read) -> String {
+    let mut archive = tar::Archive::new(reader.take(MAX_TAR_BYTES));
+    let Ok(entries) = archive.entries() else {
+        return unreadable("tar archive");
+    };
+    let mut names = Vec::new();
+    let mut complete = true;
+    for entry in entries {
+        let Ok(entry) = entry else {
+            // Corrupt from here on, or cut off at the limit
+            complete = false;
+            break;
+        };
+        if let Ok(path) = entry.path() {
+            names.push(path.to_string_lossy().to_string());
+        }
+    }
+    // The limit may also end the archive where an entry header would start
+    complete &= archive.into_inner().limit() > 0;
+    list_entries("tar archive", names, complete)
+}
+
+fn describe_elf(data: &[u8]) -> Option<String> {
+    let bits = match data.get(4)? {
+        1 => "32-bit",
+        2 => "64-bit",
+        _ => "unknown class",
+    };
+    let big_endian = *data.get(5)? == 2;
+    let half = |at| {
+        if big_endian {
+            u16_be(data, at)
+        } else {
+            u16_le(data, at)
+        }
+    };
+    let kind = match half(16)? {
+        1 => "relocatable object",
+        2 => "executable",
+        3 => "shared object",
+        4 => "core dump",
+        _ => "file",
+    };
+    let machine = match half(18)? {
+        0x03 => "x86",
+        0x08 => "MIPS",
+        0x14 | 0x15 => "PowerPC",
+        0x28 => "ARM",
+        0x3E => "x86-64",
+        0xB7 => "AArch64",
+        0xF3 => "RISC-V",
+        _ => "unknown architecture",
+    };
+    Some(format!("ELF {} {}, {}", bits, kind, machine))
+}
+
+fn describe_pe(data: &[u8]) -> Option<String> {
+    let header = u32_le(data, 0x3C)? as usize;
+    if data.get(header..header + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = match u16_le(data, header + 4)? {
+        0x014C => "x86",
+        0x8664 => "x86-64",
+        0xAA64 => "ARM64",
+        0x01C4 => "ARM",
+        _ => "unknown architecture",
+    };
+    let format = match u16_le(data, header + 24) {
+        Some(0x20B) => "PE32+",
+        _ => "PE32",
+    };
+    let kind = if u16_le(data, header + 22)? & 0x2000 != 0 {
+        "DLL"
+    } else {
+        "executable"
+    };
+    Some(format!("{} {}, {}", format, kind, machine))
+}
+
+fn describe_mach_o(data: &[u8]) -> Option<String> {
+    let cpu = |cpu_type: u32| match cpu_type {
+        7 => "x86",
+        0x0100_0007 => "x86-64",
+        12 => "ARM",
+        0x0100_000C => "arm64",
+        _ => "unknown architecture",
+    };
+    match u32_be(data, 0)? {
+        0xCEFA_EDFE => Some(format!("Mach-O 32-bit, {}", cpu(u32_le(data, 4)?))),
+        0xCFFA_EDFE => Some(format!("Mach-O 64-bit, {}", cpu(u32_le(data, 4)?))),
+        // Shared with Java class files, which have a much larger version here
+        0xCAFE_BABE => match u32_be(data, 4)? {
+            count @ 1..=20 => Some(format!("Mach-O universal binary, {} architectures", count)),
+            _ => Some(format!(
+                "Java class file (Java {})",
+                u16_be(data, 6)?.saturating_sub(44)
+            )),
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,188 @@
+use crate::deps::{select_with_dependencies, DependencyGraph};
+use crate::formatter::{build_tree, write_project_summary, FormatOptions};
+use crate::graph::{render_graph, GraphExportOptions};
+use crate::manifest::{Manifest, ManifestOptions};
+use crate::notebook::{extract_notebooks, NotebookOptions};
+use crate::order::{order_paths, FileOrder};
+use crate::relevance::{select_relevant, Budget};
+use crate::roots::DiskFiles;
+use crate::sample::{sample_data_files, SampleRule};
+use crate::scanner::ScannedFile;
+use crate::truncate::{apply_truncation, TruncateRule};
+use crate::utils::{sha256_hex, to_hex};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Write};
+
+/// Start of the last line of a bundle written with
+/// [`BundleOptions::manifest_hash`], followed by the hex SHA-256 of every
+/// byte before that line.
+pub const MANIFEST_HASH_PREFIX: &str = "Manifest hash: sha256:";
+
+/// Everything that decides which scanned files end up in a bundle and how
+/// they are rendered. Shared by the GUI and the command line.
+#[derive(Clone, Debug, Default)]
+pub struct BundleOptions {
+    pub format: FormatOptions,
+    /// Keep only files relevant to this query.
+    pub query: Option<String>,
+    /// Limit for query results (unlimited when `None`).
+    pub budget: Option<Budget>,
+    /// Keep only this file and everything it transitively imports.
+    pub include_with_deps: Option<String>,
+    /// Order of the file sections.
+    pub order: FileOrder,
+    /// Gitignore-style patterns of files to write first, in this order.
+    pub priority: Vec<String>,
+    /// Append the dependency graph after the file sections.
+    pub graph: Option<GraphExportOptions>,
+    /// Keep only some lines of matching files (the last matching rule wins).
+    pub truncation: Vec<TruncateRule>,
+    pub notebooks: NotebookOptions,
+    /// Reduce matching data files to their shape (the last matching rule wins).
+    pub sampling: Vec<SampleRule>,
+    /// Where to read oversize files from for `truncation` and `sampling`;
+    /// without it they keep their size placeholder.
+    pub disk: Option<DiskFiles>,
+    /// List the included files with their hashes on disk, in the bundle
+    /// and/or a sidecar file.
+    pub manifest: Option<ManifestOptions>,
+    /// End the bundle with a hash of its bytes (see [`MANIFEST_HASH_PREFIX`]).
+    pub manifest_hash: bool,
+}
+
+/// Extracts notebooks and samples data files, then applies the dependency
+/// filter, the truncation rules and the relevance filter from `options`, in
+/// that order: imports are resolved on complete files, while budgets count
+/// what is actually written.
+pub fn select_files(files: Vec<ScannedFile>, options: &BundleOptions) -> Vec<ScannedFile> {
+    let files = extract_notebooks(files, &options.notebooks);
+    let files = sample_data_files(files, &options.sampling, options.disk.as_ref());
+    let files = match options.include_with_deps.as_deref() {
+        Some(path) => select_with_dependencies(files, &[path]),
+        None => files,
+    };
+    let files = apply_truncation(files, &options.truncation, options.disk.as_ref());
+
+    match options.query.as_deref() {
+        Some(query) => select_relevant(
+            files,
+            query,
+            options.budget.unwrap_or(Budget::Bytes(usize::MAX)),
+        ),
+        None => files,
+    }
+}
+
+/// Renders already selected files into the final bundle text.
+pub fn render_bundle(files: &[ScannedFile], options: &BundleOptions) -> String {
+    render_bundle_with_graph(files, &DependencyGraph::build(files), options)
+}
+
+/// Same as [`render_bundle`] for callers that already built the graph.
+pub fn render_bundle_with_graph(
+    files: &[ScannedFile],
+    graph: &DependencyGraph,
+    options: &BundleOptions,
+) -> String {
+    let mut output = Vec::new();
+    // Writing to a Vec cannot fail, and only `str`s are written
+    write_bundle_with_graph(&mut output, files, graph, options).expect("write to Vec");
+    String::from_utf8(output).expect("bundle is UTF-8")
+}
+
+/// Writes the bundle of already selected files to `out` section by section,
+/// without building the whole text in memory first. The bytes are the same
+/// as [`render_bundle`] returns.
+pub fn write_bundle(
+    out: &mut dyn Write,
+    files: &[ScannedFile],
+    options: &BundleOptions,
+) -> io::Result<()> {
+    write_bundle_with_graph(out, files, &DependencyGraph::build(files), options)
+}
+
+/// Same as [`write_bundle`] for callers that already built the graph.
+pub fn write_bundle_with_graph(
+    out: &mut dyn Write,
+    files: &[ScannedFile],
+    graph: &DependencyGraph,
+    options: &BundleOptions,
+) -> io::Result<()> {
+    write_with_manifest_hash(out, options.manifest_hash, |out| {
+        let paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+        let order = order_paths(&paths, options.order, &options.priority, Some(graph));
+        write_project_summary(out, &build_tree(files), files, &options.format, &order)?;
+
+        if let Some(graph_options) = &options.graph {
+            out.write_all(b"Dependency graph:\n")?;
+            out.write_all(render_graph(graph, graph_options).as_bytes())?;
+        }
+        write_manifest(out, options.manifest.as_ref(), &order)
+    })
+}
+
+/// Builds the manifest of the files at `paths`, if asked for, then writes
+/// its section to `out` and its sidecar file as configured.
+pub(crate) fn write_manifest(
+    out: &mut dyn Write,
+    options: Option<&ManifestOptions>,
+    paths: &[String],
+) -> io::Result<()> {
+    let Some(options) = options.filter(|o| o.section || o.sidecar.is_some()) else {
+        return Ok(());
+    };
+    let manifest = Manifest::build(&options.roots, paths);
+    if options.section {
+        out.write_all(manifest.render().as_bytes())?;
+    }
+    if let Some(sidecar) = &options.sidecar {
+        fs::write(sidecar, manifest.to_json())?;
+    }
+    Ok(())
+}
+
+/// Runs `write`, followed by the manifest hash line if `enabled`.
+pub(crate) fn write_with_manifest_hash(
+    out: &mut dyn Write,
+    enabled: bool,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    if !enabled {
+        return write(out);
+    }
+    let mut hashing = HashingWriter {
+        inner: out,
+        hasher: Sha256::new(),
+    };
+    write(&mut hashing)?;
+    let hash = to_hex(&hashing.hasher.finalize());
+    writeln!(hashing.inner, "{}{}", MANIFEST_HASH_PREFIX, hash)
+}
+
+/// Checks the manifest hash line ending `bundle`: `None` if there is none,
+/// otherwise whether it matches the bytes before it.
+pub fn verify_manifest_hash(bundle: &str) -> Option<bool> {
+    let body = bundle.strip_suffix('\n').unwrap_or(bundle);
+    let start = body.rfind('\n').map_or(0, |i| i + 1);
+    let hash = body[start..].strip_prefix(MANIFEST_HASH_PREFIX)?;
+    Some(sha256_hex(&bundle.as_bytes()[..start]) == hash)
+}
+
+/// Passes writes through while hashing them.
+struct HashingWriter<'a> {
+    inner: &'a mut dyn Write,
+    hasher: Sha256,
+}
+
+impl Write for HashingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,267 @@
+use crate::scanner::{
+    canonical_root, file_from_bytes, oversize_file, slash_path, walk_files, LineSpan, ScannedFile,
+};
+use crate::utils::sha256_hex;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Bump when the on-disk layout or the scanner's output changes, so stale
+/// caches are discarded instead of misread.
+const CACHE_VERSION: u32 = 6;
+
+/// Everything known about a file as of its last scan. The content of text
+/// files is not kept: it is read again on every scan.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub size: u64,
+    /// Modification time as (seconds, nanoseconds) since the Unix epoch.
+    pub mtime: (u64, u32),
+    /// SHA-256 of the file bytes (`None` for files over the size limit,
+    /// which are never read).
+    pub hash: Option<String>,
+    pub is_binary: bool,
+    /// `utf-8`, `utf-8-lossy` (invalid sequences replaced) or `binary`.
+    pub encoding: String,
+    /// The description or size placeholder standing in for a binary or
+    /// oversize file.
+    pub summary: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    root: PathBuf,
+    max_file_size: u64,
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// Per-project scan cache stored as JSON in the user cache directory.
+///
+/// Entries are reused when a file's size and mtime are unchanged: binary and
+/// oversize files are then not read at all, text files only to get their
+/// content, which for valid UTF-8 is taken as it is. If only the mtime changed, the file is hashed and compared, so
+/// touching a file does not throw away its derived data.
+pub struct ScanCache {
+    file_path: PathBuf,
+    root: PathBuf,
+    max_file_size: u64,
+    entries: HashMap<String, CacheEntry>,
+    /// Files served from the cache during the last scan.
+    pub hits: usize,
+    /// Files (re)processed during the last scan.
+    pub misses: usize,
+}
+
+impl ScanCache {
+    /// `<user cache dir>/contextor`, if the platform has one.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("contextor"))
+    }
+
+    /// Opens the cache for `root` in the default cache directory.
+    pub fn open(root: &Path, max_file_size: u64) -> io::Result<Self> {
+        let dir = Self::default_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user cache directory"))?;
+        Ok(Self::open_in(&dir, root, max_file_size))
+    }
+
+    /// Opens the cache for `root` stored in `cache_dir`. A missing, corrupt
+    /// or incompatible cache file simply yields an empty cache.
+    pub fn open_in(cache_dir: &Path, root: &Path, max_file_size: u64) -> Self {
+        let root = canonical_root(&root.to_string_lossy());
+        let key = sha256_hex(root.to_string_lossy().as_bytes());
+        let file_path = cache_dir.join(format!("{}.json", &key[..16]));
+
+        let entries = fs::read(&file_path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<CacheFile>(&data).ok())
+            .filter(|cache| {
+                cache.version == CACHE_VERSION
+                    && cache.root == root
+                    && cache.max_file_size == max_file_size
+            })
+            .map(|cache| cache.entries)
+            .unwrap_or_default();
+
+        Self {
+            file_path,
+            root,
+            max_file_size,
+            entries,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Cached data for a relative path, as of the last scan.
+    pub fn entry(&self, path: &str) -> Option<&CacheEntry> {
+        self.entries.get(path)
+    }
+
+    /// Writes the cache to disk (via a temporary file, so readers never see
+    /// a partial write).
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.file_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let cache = CacheFile {
+            version: CACHE_VERSION,
+            root: self.root.clone(),
+            max_file_size: self.max_file_size,
+            entries: self.entries.clone(),
+        };
+        let data = serde_json::to_vec(&cache).map_err(io::Error::other)?;
+
+        let tmp_path = self.file_path.with_extension("json.tmp");
+        fs::write(&tmp_path, data)?;
+        fs::rename(&tmp_path, &self.file_path)
+    }
+
+    /// Scans the cache's root like [`crate::scanner::scan_project`], reading
+    /// only files that changed since the last scan. Entries of files that no
+    /// longer exist are dropped. Call [`ScanCache::save`] to persist.
+    pub fn scan(&mut self) -> Vec<ScannedFile> {
+        self.scan_with_ignores(&[])
+    }
+
+    /// Like [`ScanCache::scan`], additionally skipping paths matched by the
+    /// given gitignore-style patterns.
+    pub fn scan_with_ignores(&mut self, ignore_patterns: &[String]) -> Vec<ScannedFile> {
+        let root = self.root.clone();
+        let max_file_size = self.max_file_size;
+        let entries = &self.entries;
+
+        let results: Vec<(ScannedFile, CacheEntry, bool)> = walk_files(&root, ignore_patterns)
+            .par_iter()
+            .filter_map(|abs_path| {
+                let relative = abs_path.strip_prefix(&root).unwrap_or(abs_path);
+                let key = slash_path(relative);
+                scan_with_cache(abs_path, relative, entries.get(&key), max_file_size)
+            })
+            .collect();
+
+        self.entries.clear();
+        self.hits = 0;
+        self.misses = 0;
+
+        let mut files = Vec::with_capacity(results.len());
+        for (file, entry, hit) in results {
+            if hit {
+                self.hits += 1;
+            } else {
+                self.misses += 1;
+            }
+            self.entries.insert(file.path.clone(), entry);
+            files.push(file);
+        }
+        files
+    }
+}
+
+/// Returns the scanned file, its (new) cache entry, and whether the cached
+/// data could be reused.
+fn scan_with_cache(
+    abs_path: &Path,
+    relative: &Path,
+    cached: Option<&CacheEntry>,
+    max_file_size: u64,
+) -> Option<(ScannedFile, CacheEntry, bool)> {
+    let metadata = fs::metadata(abs_path).ok()?;
+    let size = metadata.len();
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| (d.as_secs(), d.subsec_nanos()))
+        .unwrap_or_default();
+    let path = slash_path(relative);
+
+    if let Some(entry) = cached.filter(|e| e.size == size && e.mtime == mtime) {
+        let file = match &entry.summary {
+            Some(summary) => ScannedFile {
+                path,
+                content: Some(summary.clone()),
+                is_binary: entry.is_binary,
+                // Text only keeps a summary when it is the size placeholder
+                line_origins: (!entry.is_binary).then(|| vec![LineSpan::Marker]),
+            },
+            None => cached_text(relative, fs::read(abs_path).ok()?, entry, max_file_size),
+        };
+        return Some((file, entry.clone(), true));
+    }
+
+    if size > max_file_size {
+        let file = oversize_file(abs_path, relative, size, max_file_size);
+        let entry = CacheEntry {
+            size,
+            mtime,
+            hash: None,
+            is_binary: file.is_binary,
+            encoding: "utf-8".to_string(),
+            summary: file.content.clone(),
+        };
+        return Some((file, entry, false));
+    }
+
+    let data = fs::read(abs_path).ok()?;
+    let hash = sha256_hex(&data);
+
+    // Touched but unchanged: keep the derived data, refresh the mtime
+    if let Some(entry) = cached.filter(|e| e.size == size && e.hash.as_ref() == Some(&hash)) {
+        let entry = CacheEntry {
+            mtime,
+            ..entry.clone()
+        };
+        return Some((file_from_bytes(relative, &data, max_file_size), entry, true));
+    }
+
+    let file = file_from_bytes(relative, &data, max_file_size);
+    let encoding = if file.is_binary {
+        "binary"
+    } else if std::str::from_utf8(&data).is_ok() {
+        "utf-8"
+    } else {
+        "utf-8-lossy"
+    };
+    let entry = CacheEntry {
+        size,
+        mtime,
+        hash: Some(hash),
+        is_binary: file.is_binary,
+        encoding: encoding.to_string(),
+        summary: file.content.clone().filter(|_| file.is_binary),
+    };
+    Some((file, entry, false))
+}
+
+/// A text file whose cache entry still matches. Binary detection is skipped,
+/// and so are the lossy conversion and the size cut for text that was valid
+/// UTF-8 (whose size is within the limit, or it would have a summary).
+fn cached_text(
+    relative: &Path,
+    data: Vec<u8>,
+    entry: &CacheEntry,
+    max_file_size: u64,
+) -> ScannedFile {
+    if entry.encoding != "utf-8" {
+        return file_from_bytes(relative, &data, max_file_size);
+    }
+    match String::from_utf8(data) {
+        Ok(text) => ScannedFile {
+            path: slash_path(relative),
+            content: Some(text),
+            is_binary: false,
+            line_origins: None,
+        },
+        Err(error) => file_from_bytes(relative, error.as_bytes(), max_file_size),
+    }
+}
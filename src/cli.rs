@@ -0,0 +1,675 @@
+use crate::apply::{apply_edits, default_backup_dir, parse_response, plan_edits, EditStatus};
+use crate::bundle::{select_files, verify_manifest_hash, write_bundle, BundleOptions};
+use crate::clipboard::copy_to_clipboard;
+use crate::filelist::{parse_file_list, scan_file_list};
+use crate::graph::{GraphExportOptions, GraphFormat};
+use crate::manifest::{Manifest, ManifestOptions};
+use crate::mcp::McpServer;
+use crate::order::FileOrder;
+use crate::relevance::Budget;
+use crate::roots::{scan_roots, DiskFiles, ProjectRoot};
+use crate::sample::SampleRule;
+use crate::scanner::ScannedFile;
+use crate::server::{BundleServer, ServerConfig};
+use crate::stream::{can_stream, write_roots_bundle};
+use crate::truncate::TruncateRule;
+use crate::unpack::unpack_bundle;
+use crate::verify::check_manifest;
+use crate::watch::{ProjectState, ProjectWatcher, DEFAULT_DEBOUNCE};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+Usage: contextor [OPTIONS] [FOLDER]...
+       contextor serve [--port <PORT>] [--allow <FOLDER>]... [--max-size <BYTES>]
+       contextor mcp [FOLDER] [--max-size <BYTES>]
+       contextor verify <BUNDLE>
+       contextor unpack <BUNDLE> <FOLDER> [--force]
+       contextor apply <REPLY> [FOLDER]... [--bundle <FILE>] [--yes] [--force]
+
+Without arguments the graphical interface is started. Several folders can be
+bundled together; each then appears under its alias (the folder name unless
+set with --alias). A FOLDER may also be a .zip, .tar, .tar.gz or .tgz archive.
+
+Options:
+      --alias <NAME>         Alias of the preceding FOLDER
+      --ignore <PATTERN>     Gitignore-style pattern to skip in the preceding FOLDER
+      --files-from <FILE>    Bundle only the paths listed in FILE (- for stdin),
+                             one per line, relative to FOLDER
+  -o, --output <FILE>        Write the bundle to FILE instead of stdout
+      --clipboard            Also copy the bundle to the clipboard
+      --max-size <BYTES>     Skip content of files larger than this (default 1000000)
+      --line-numbers         Prefix file content lines with line numbers
+      --truncate <RULE>      Keep only some lines of matching files, as PATTERN:SPEC
+                             with SPEC head=N, tail=N, head=N,tail=M or line
+                             ranges (e.g. '*.log:tail=100', 'src/app.rs:100-220')
+      --sample-data          Reduce CSV/TSV, JSON, YAML and log files to samples
+      --sample <RULE>        Sample matching files, as PATTERN:SPEC with SPEC
+                             table[=ROWS], outline or tail=N (e.g. '*.csv:table=5')
+      --notebook-outputs     Include text outputs of Jupyter notebook cells
+      --raw-notebooks        Keep notebooks as JSON instead of extracting cells
+      --query <TEXT>         Only include files relevant to TEXT
+      --budget-tokens <N>    Token budget for --query results
+      --budget-bytes <N>     Byte budget for --query results
+      --with-deps <FILE>     Only include FILE and everything it imports
+      --order <ORDER>        Order of file sections: alphabetical (default),
+                             dirs-first or dependencies
+      --dependency-order     Same as --order dependencies
+      --first <PATTERN>      Write files matching PATTERN first; repeat to rank
+                             several patterns
+      --manifest             List every included file with its size, line count
+                             and SHA-256 on disk, and the git commit of each folder
+      --manifest-file <FILE> Write that list as JSON to FILE
+      --manifest-hash        End the bundle with the SHA-256 of its content, so
+                             identical inputs can be checked to give identical bytes
+      --graph <dot|mermaid>  Append the dependency graph
+      --graph-collapse       Collapse the graph by directory
+      --graph-focus <FILE>   Restrict the graph to FILE and its imports
+  -w, --watch                Regenerate the bundle whenever files change
+      --archives             Also scan inside archives found in the folders
+      --no-cache             Re-read every file instead of using the scan cache
+  -h, --help                 Show this help
+
+The serve command answers bundle requests on 127.0.0.1 (port 7878 unless set
+with --port): POST /bundle with a JSON body such as
+{\"roots\": [\"backend\"], \"query\": \"auth\", \"budget_tokens\": 8000}, and GET /roots.
+Only the --allow folders (default: the current folder) and what lies inside
+them can be bundled; relative roots are resolved against the first one.
+
+The mcp command runs a Model Context Protocol server on stdin/stdout with the
+tools list_project_tree, read_files, search_files and build_bundle. They can
+only read inside FOLDER (default: the current folder).
+
+The verify command reads a bundle written with --manifest, or a --manifest-file
+(- for stdin), and lists the files changed, added or deleted since. Added files
+are only listed for bundles of every file in their folders. It exits with an
+error if any are.
+
+The unpack command recreates the files of a bundle (- for stdin) below FOLDER.
+Binary files, placeholders and truncated or sampled files cannot be restored
+and are reported; so are paths leading outside FOLDER and, without --force,
+files that already exist.
+
+The apply command reads a model's reply (- for stdin) with whole files in the
+bundle's `path:` format or unified diffs, and shows the diff of every file it
+would change in FOLDER (default: the folders of --bundle, or the current one).
+With --bundle, the bundle or --manifest-file it was given, files changed since
+are conflicts. --yes writes the changes, backing up every file first
+(--backup-dir); --force also writes conflicts.
+";
+
+pub enum Command {
+    Help,
+    Bundle(Box<BundleCommand>),
+    Serve(ServeCommand),
+    Mcp(McpCommand),
+    /// Check a bundle or manifest file (`-` is stdin) against the files.
+    Verify(PathBuf),
+    Unpack(UnpackCommand),
+    Apply(ApplyCommand),
+}
+
+/// Apply the file edits of a model's reply.
+#[derive(Debug, Default)]
+pub struct ApplyCommand {
+    /// The reply (`-` is stdin).
+    pub reply: PathBuf,
+    pub roots: Vec<ProjectRoot>,
+    /// Bundle or manifest the reply was made from, to detect conflicts.
+    pub bundle: Option<PathBuf>,
+    /// Write the changes instead of only showing them.
+    pub write: bool,
+    /// Also write files with conflicts.
+    pub force: bool,
+    pub backup_dir: Option<PathBuf>,
+}
+
+/// Recreate the files of a bundle in a folder.
+#[derive(Debug)]
+pub struct UnpackCommand {
+    /// Bundle file (`-` is stdin).
+    pub bundle: PathBuf,
+    pub target: PathBuf,
+    /// Replace files that already exist.
+    pub overwrite: bool,
+}
+
+/// Scan a folder and write its bundle.
+#[derive(Debug)]
+pub struct BundleCommand {
+    pub roots: Vec<ProjectRoot>,
+    pub max_file_size: u64,
+    /// List of paths to bundle instead of walking the folder (`-` is stdin).
+    pub files_from: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub clipboard: bool,
+    pub watch: bool,
+    pub use_cache: bool,
+    pub options: BundleOptions,
+}
+
+impl Default for BundleCommand {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            max_file_size: 1_000_000,
+            files_from: None,
+            output: None,
+            clipboard: false,
+            watch: false,
+            use_cache: true,
+            options: BundleOptions::default(),
+        }
+    }
+}
+
+/// Answer bundle requests over HTTP on localhost.
+#[derive(Debug)]
+pub struct ServeCommand {
+    pub port: u16,
+    pub config: ServerConfig,
+}
+
+impl Default for ServeCommand {
+    fn default() -> Self {
+        Self {
+            port: 7878,
+            config: ServerConfig::default(),
+        }
+    }
+}
+
+/// Serve MCP tools for one folder over stdio.
+#[derive(Debug)]
+pub struct McpCommand {
+    pub root: PathBuf,
+    pub max_file_size: u64,
+    pub use_cache: bool,
+}
+
+impl Default for McpCommand {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("."),
+            max_file_size: 1_000_000,
+            use_cache: true,
+        }
+    }
+}
+
+/// Parses command line arguments (without the program name).
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter().peekable();
+    match args.peek().map(String::as_str) {
+        Some("serve") => {
+            args.next();
+            return parse_serve_args(args);
+        }
+        Some("mcp") => {
+            args.next();
+            return parse_mcp_args(args);
+        }
+        Some("verify") => {
+            args.next();
+            return match (args.next(), args.next()) {
+                (Some(arg), _) if arg == "-h" || arg == "--help" => Ok(Command::Help),
+                (Some(file), None) => Ok(Command::Verify(PathBuf::from(file))),
+                _ => Err("verify takes one bundle or manifest file".to_string()),
+            };
+        }
+        Some("unpack") => {
+            args.next();
+            return parse_unpack_args(args);
+        }
+        Some("apply") => {
+            args.next();
+            return parse_apply_args(args);
+        }
+        _ => {}
+    }
+
+    let mut command = BundleCommand::default();
+    let mut graph: Option<GraphExportOptions> = None;
+    let mut expand_archives = false;
+    let mut manifest = ManifestOptions::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--files-from" => command.files_from = Some(PathBuf::from(value(&arg)?)),
+            "-o" | "--output" => command.output = Some(PathBuf::from(value(&arg)?)),
+            "--clipboard" => command.clipboard = true,
+            "--max-size" => command.max_file_size = parse_number(&arg, &value(&arg)?)?,
+            "--line-numbers" => command.options.format.line_numbers = true,
+            "--truncate" => command
+                .options
+                .truncation
+                .push(TruncateRule::parse(&value(&arg)?)?),
+            "--sample-data" => command.options.sampling.extend(SampleRule::defaults()),
+            "--sample" => command
+                .options
+                .sampling
+                .push(SampleRule::parse(&value(&arg)?)?),
+            "--notebook-outputs" => command.options.notebooks.include_outputs = true,
+            "--raw-notebooks" => command.options.notebooks.extract = false,
+            "--query" => command.options.query = Some(value(&arg)?),
+            "--budget-tokens" => {
+                command.options.budget = Some(Budget::Tokens(parse_number(&arg, &value(&arg)?)?))
+            }
+            "--budget-bytes" => {
+                command.options.budget = Some(Budget::Bytes(parse_number(&arg, &value(&arg)?)?))
+            }
+            "--with-deps" => command.options.include_with_deps = Some(value(&arg)?),
+            "--order" => command.options.order = FileOrder::parse(&value(&arg)?)?,
+            "--dependency-order" => command.options.order = FileOrder::Dependencies,
+            "--first" => command.options.priority.push(value(&arg)?),
+            "--manifest" => manifest.section = true,
+            "--manifest-file" => manifest.sidecar = Some(PathBuf::from(value(&arg)?)),
+            "--manifest-hash" => command.options.manifest_hash = true,
+            "--graph" => {
+                graph.get_or_insert_with(Default::default).format = match value(&arg)?.as_str() {
+                    "dot" => GraphFormat::Dot,
+                    "mermaid" => GraphFormat::Mermaid,
+                    other => return Err(format!("unknown graph format '{}'", other)),
+                }
+            }
+            "--graph-collapse" => {
+                graph
+                    .get_or_insert_with(Default::default)
+                    .collapse_by_directory = true
+            }
+            "--graph-focus" => {
+                graph.get_or_insert_with(Default::default).focus = Some(value(&arg)?)
+            }
+            "-w" | "--watch" => command.watch = true,
+            "--archives" => expand_archives = true,
+            "--no-cache" => command.use_cache = false,
+            "--alias" | "--ignore" => {
+                let value = value(&arg)?;
+                let root = command
+                    .roots
+                    .last_mut()
+                    .ok_or_else(|| format!("{} must follow a folder", arg))?;
+                if arg == "--alias" {
+                    root.alias = value;
+                } else {
+                    root.ignore_patterns.push(value);
+                }
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => command.roots.push(ProjectRoot::new(arg)),
+        }
+    }
+
+    if command.roots.is_empty() {
+        command.roots.push(ProjectRoot::new("."));
+    }
+    for root in &mut command.roots {
+        root.expand_archives = expand_archives;
+    }
+    if command.files_from.is_some() && (command.watch || command.roots.len() > 1) {
+        return Err("--files-from works with a single folder and without --watch".to_string());
+    }
+    command.options.graph = graph;
+    command.options.disk = Some(DiskFiles::new(&command.roots, command.max_file_size));
+    if manifest.section || manifest.sidecar.is_some() {
+        manifest.roots = command.roots.clone();
+        command.options.manifest = Some(manifest);
+    }
+    Ok(Command::Bundle(Box::new(command)))
+}
+
+fn parse_serve_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut command = ServeCommand::default();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--port" => command.port = parse_number(&arg, &value(&arg)?)?,
+            "--allow" => command
+                .config
+                .allowed_roots
+                .push(PathBuf::from(value(&arg)?)),
+            "--max-size" => command.config.max_file_size = parse_number(&arg, &value(&arg)?)?,
+            "--no-cache" => command.config.use_cache = false,
+            _ => return Err(format!("unknown serve option '{}'", arg)),
+        }
+    }
+
+    if command.config.allowed_roots.is_empty() {
+        command.config.allowed_roots.push(PathBuf::from("."));
+    }
+    Ok(Command::Serve(command))
+}
+
+fn parse_mcp_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut command = McpCommand::default();
+    let mut root = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--max-size" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for {}", arg))?;
+                command.max_file_size = parse_number(&arg, &value)?
+            }
+            "--no-cache" => command.use_cache = false,
+            _ if arg.starts_with('-') => return Err(format!("unknown mcp option '{}'", arg)),
+            _ if root.is_some() => return Err("mcp serves a single folder".to_string()),
+            _ => root = Some(PathBuf::from(arg)),
+        }
+    }
+    if let Some(root) = root {
+        command.root = root;
+    }
+    Ok(Command::Mcp(command))
+}
+
+fn parse_unpack_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut overwrite = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--force" => overwrite = true,
+            "-" => paths.push(PathBuf::from(arg)),
+            _ if arg.starts_with('-') => return Err(format!("unknown unpack option '{}'", arg)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [bundle, target] = <[PathBuf; 2]>::try_from(paths)
+        .map_err(|_| "unpack takes a bundle file and a target folder".to_string())?;
+    Ok(Command::Unpack(UnpackCommand {
+        bundle,
+        target,
+        overwrite,
+    }))
+}
+
+fn parse_apply_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut command = ApplyCommand::default();
+    let mut reply = None;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--bundle" => command.bundle = Some(PathBuf::from(value(&arg)?)),
+            "--backup-dir" => command.backup_dir = Some(PathBuf::from(value(&arg)?)),
+            "--yes" => command.write = true,
+            "--force" => command.force = true,
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown apply option '{}'", arg))
+            }
+            _ if reply.is_none() => reply = Some(PathBuf::from(arg)),
+            _ => command.roots.push(ProjectRoot::new(arg)),
+        }
+    }
+    command.reply = reply.ok_or("apply needs the file with the model's reply")?;
+    Ok(Command::Apply(command))
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number '{}' for {}", value, name))
+}
+
+pub fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Help => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Command::Serve(command) => run_serve(command),
+        Command::Mcp(command) => {
+            let server = McpServer::new(&command.root, command.max_file_size, command.use_cache)?;
+            server
+                .run(io::stdin().lock(), io::stdout().lock())
+                .map_err(|e| format!("mcp server stopped: {}", e))
+        }
+        Command::Verify(file) => run_verify(&file),
+        Command::Apply(command) => run_apply(command),
+        Command::Unpack(command) => {
+            let report = unpack_bundle(
+                &read_input(&command.bundle)?,
+                &command.target,
+                command.overwrite,
+            )?;
+            for entry in &report.skipped {
+                eprintln!("Skipping {}: {}", entry.path, entry.reason);
+            }
+            eprintln!(
+                "Wrote {} files to {}",
+                report.written.len(),
+                command.target.display()
+            );
+            Ok(())
+        }
+        Command::Bundle(command) if command.watch => run_watch(&command),
+        Command::Bundle(command) => match &command.files_from {
+            Some(list) => {
+                let files = scan_listed(&command.roots[0], list, command.max_file_size)?;
+                let files = select_files(files, &command.options);
+                emit(&command, |out| write_bundle(out, &files, &command.options))
+            }
+            // Scanned up front so an unreadable archive is reported as such
+            None if !can_stream(&command.roots, &command.options) => {
+                let files = scan_roots(&command.roots, command.max_file_size, command.use_cache)?;
+                let files = select_files(files, &command.options);
+                emit(&command, |out| write_bundle(out, &files, &command.options))
+            }
+            None => emit(&command, |out| {
+                write_roots_bundle(
+                    out,
+                    &command.roots,
+                    command.max_file_size,
+                    command.use_cache,
+                    &command.options,
+                )
+            }),
+        },
+    }
+}
+
+/// Scans the files named in `list`, reporting rejected entries on stderr.
+fn scan_listed(
+    root: &ProjectRoot,
+    list: &Path,
+    max_file_size: u64,
+) -> Result<Vec<ScannedFile>, String> {
+    let text = read_input(list)?;
+    let (files, rejected) = scan_file_list(&root.path, &parse_file_list(&text), max_file_size);
+    for entry in rejected {
+        eprintln!("Skipping {}: {}", entry.path, entry.reason);
+    }
+    Ok(files)
+}
+
+/// Reads a file, or stdin for `-`.
+fn read_input(path: &Path) -> Result<String, String> {
+    if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("failed to read stdin: {}", e))?;
+        return Ok(text);
+    }
+    fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))
+}
+
+fn run_verify(file: &Path) -> Result<(), String> {
+    let text = read_input(file)?;
+    let manifest = Manifest::parse(&text)?;
+    if verify_manifest_hash(&text) == Some(false) {
+        println!("The manifest hash does not match: the bundle was edited after it was written");
+    }
+    let report = check_manifest(&manifest);
+    print!("{}", report.render());
+    if report.is_current() {
+        Ok(())
+    } else {
+        Err("the bundle is out of date".to_string())
+    }
+}
+
+fn run_apply(command: ApplyCommand) -> Result<(), String> {
+    let edits = parse_response(&read_input(&command.reply)?);
+    if edits.is_empty() {
+        return Err("no file edits found in the reply".to_string());
+    }
+    let sent = match &command.bundle {
+        Some(bundle) => Some(Manifest::parse(&read_input(bundle)?)?),
+        None => None,
+    };
+    let mut roots = command.roots;
+    if roots.is_empty() {
+        roots = match &sent {
+            Some(manifest) => manifest
+                .roots
+                .iter()
+                .map(|root| ProjectRoot {
+                    alias: root.alias.clone(),
+                    ..ProjectRoot::new(&root.path)
+                })
+                .collect(),
+            None => vec![ProjectRoot::new(".")],
+        };
+    }
+
+    let planned = plan_edits(&roots, &edits, sent.as_ref());
+    for edit in &planned {
+        println!("{}: {}", edit.path, edit.status.label());
+        print!("{}", edit.diff());
+    }
+    if !command.write {
+        eprintln!("Nothing written; pass --yes to apply the changes");
+        return Ok(());
+    }
+
+    let mut accepted = Vec::new();
+    for edit in planned {
+        match &edit.status {
+            EditStatus::Ready => accepted.push(edit),
+            EditStatus::Conflict(_) if command.force => accepted.push(edit),
+            EditStatus::Conflict(reason) => {
+                eprintln!("Skipping {}: {} (--force writes it)", edit.path, reason)
+            }
+            _ => {}
+        }
+    }
+    let backup_dir = command
+        .backup_dir
+        .or_else(default_backup_dir)
+        .ok_or("no folder for backups; pass --backup-dir")?;
+    let report = apply_edits(&roots, &accepted, &backup_dir);
+    for entry in &report.skipped {
+        eprintln!("Skipping {}: {}", entry.path, entry.reason);
+    }
+    eprintln!(
+        "Wrote {} and deleted {} files",
+        report.written.len(),
+        report.deleted.len()
+    );
+    if let Some(dir) = &report.backup_dir {
+        eprintln!("Previous versions are in {}", dir.display());
+    }
+    Ok(())
+}
+
+fn run_serve(command: ServeCommand) -> Result<(), String> {
+    let server = BundleServer::bind(&format!("127.0.0.1:{}", command.port), command.config)?;
+    if let Some(address) = server.local_addr() {
+        eprintln!("Serving bundles on http://{} (Ctrl+C to stop)", address);
+    }
+    server.run();
+    Ok(())
+}
+
+fn run_watch(command: &BundleCommand) -> Result<(), String> {
+    let [root] = command.roots.as_slice() else {
+        return Err("watch mode supports a single folder".to_string());
+    };
+    if !root.path.is_dir() {
+        return Err(format!("{} is not a directory", root.path.display()));
+    }
+
+    let mut state = ProjectState::scan_with_ignores(
+        &root.path,
+        command.max_file_size,
+        root.ignore_patterns.clone(),
+    );
+    if let Some(output) = &command.output {
+        state.exclude(output);
+    }
+    let watcher = ProjectWatcher::new(state.root(), DEFAULT_DEBOUNCE)
+        .map_err(|e| format!("failed to watch {}: {}", root.path.display(), e))?;
+
+    let regenerate = |state: &ProjectState| {
+        let files = select_files(state.files().to_vec(), &command.options);
+        emit(command, |out| write_bundle(out, &files, &command.options))
+    };
+
+    regenerate(&state)?;
+    eprintln!("Watching {} (Ctrl+C to stop)", state.root().display());
+
+    while let Some(batch) = watcher.next_batch() {
+        if state.apply_changes(&batch) {
+            regenerate(&state)?;
+            eprintln!("Regenerated bundle ({} changed paths)", batch.len());
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the bundle produced by `write` to the output file or stdout as it
+/// is generated. Only a clipboard copy needs the whole text in memory.
+fn emit(
+    command: &BundleCommand,
+    write: impl Fn(&mut dyn Write) -> io::Result<()>,
+) -> Result<(), String> {
+    if !command.clipboard {
+        return write_output(command, &write);
+    }
+    let mut bundle = Vec::new();
+    write(&mut bundle).map_err(|e| format!("failed to render bundle: {}", e))?;
+    write_output(command, &|out| out.write_all(&bundle))?;
+    copy_to_clipboard(&String::from_utf8_lossy(&bundle));
+    Ok(())
+}
+
+fn write_output(
+    command: &BundleCommand,
+    write: &dyn Fn(&mut dyn Write) -> io::Result<()>,
+) -> Result<(), String> {
+    let write_buffered = |out: &mut dyn Write| {
+        let mut out = BufWriter::new(out);
+        write(&mut out)?;
+        out.flush()
+    };
+    match &command.output {
+        Some(path) => File::create(path)
+            .and_then(|mut file| write_buffered(&mut file))
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e)),
+        None => match write_buffered(&mut io::stdout().lock()) {
+            // The reader (e.g. `head`) has seen enough
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result.map_err(|e| format!("failed to write to stdout: {}", e)),
+        },
+    }
+}
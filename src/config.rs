@@ -0,0 +1,165 @@
+use crate::graph::GraphFormat;
+use crate::order::FileOrder;
+use crate::roots::ProjectRoot;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How many recently scanned folders are remembered.
+pub const MAX_RECENT_PROJECTS: usize = 10;
+
+/// Built-in instructions to send with a summary, by name.
+pub const PROMPT_PRESETS: [(&str, &str); 4] = [
+    (
+        "Review",
+        "Review the code above for bugs, unclear logic and missing error handling. \
+         Name the file and line of each finding.",
+    ),
+    (
+        "Explain",
+        "Explain how the project above is structured and how its main parts work together.",
+    ),
+    (
+        "Edit",
+        "Make the change described below. Reply with each changed file in full, \
+         as its path followed by a colon and a fenced code block, or as a unified diff.",
+    ),
+    (
+        "Tests",
+        "Write tests for the code above that cover its main behavior and edge cases, \
+         following the test layout the project already uses.",
+    ),
+];
+
+/// The summary followed by `prompt` after a blank line, or the summary alone
+/// if the prompt is blank.
+pub fn with_prompt(summary: &str, prompt: &str) -> String {
+    match prompt.trim() {
+        "" => summary.to_string(),
+        prompt => format!("{}\n\n{}\n", summary.trim_end(), prompt),
+    }
+}
+
+/// A named set of scan settings, restorable with one click.
+///
+/// Bundles have a single plain text format, so there is no format to pick;
+/// the options that shape that text (line numbers, order, priority, manifest,
+/// hash and graph) are all saved.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanProfile {
+    pub name: String,
+    /// Folders with their aliases and ignore patterns.
+    pub roots: Vec<ProjectRoot>,
+    /// Explicit file list; empty scans the whole folder.
+    pub files: Vec<String>,
+    pub max_file_size: u64,
+    pub line_numbers: bool,
+    pub query: String,
+    pub token_budget: Option<usize>,
+    pub include_with_deps: String,
+    pub order: FileOrder,
+    /// Patterns of files to write first.
+    pub priority: Vec<String>,
+    /// Append the file manifest.
+    pub manifest: bool,
+    pub manifest_hash: bool,
+    /// Format of the appended dependency graph, if any.
+    pub graph: Option<GraphFormat>,
+    pub graph_collapse: bool,
+    pub graph_focus: String,
+    /// Truncation rules as `PATTERN:SPEC`.
+    pub truncation: Vec<String>,
+    pub notebook_outputs: bool,
+    pub sample_data: bool,
+    /// Instructions copied after the summary.
+    pub prompt: String,
+}
+
+/// Settings kept between sessions, stored as JSON in the user config
+/// directory.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Most recent first.
+    pub recent_projects: Vec<PathBuf>,
+    /// Profiles by canonical project folder.
+    pub profiles: BTreeMap<String, Vec<ScanProfile>>,
+}
+
+impl AppConfig {
+    /// `<user config dir>/contextor/config.json`, if the platform has one.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("contextor").join("config.json"))
+    }
+
+    /// Reads the config at `path`. A missing or corrupt file yields the
+    /// default config.
+    pub fn load(path: &Path) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the config via a temporary file, so a crash never leaves a
+    /// partial one behind.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let data = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, data)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Moves `project` to the front of the recent list.
+    pub fn add_recent(&mut self, project: &Path) {
+        let project = canonical(project);
+        self.recent_projects.retain(|p| *p != project);
+        self.recent_projects.insert(0, project);
+        self.recent_projects.truncate(MAX_RECENT_PROJECTS);
+    }
+
+    /// Saved profiles of `project`, in the order they were created.
+    pub fn profiles(&self, project: &Path) -> &[ScanProfile] {
+        self.profiles
+            .get(&project_key(project))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Stores `profile` for `project`, replacing one of the same name.
+    pub fn save_profile(&mut self, project: &Path, profile: ScanProfile) {
+        let profiles = self.profiles.entry(project_key(project)).or_default();
+        match profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => profiles.push(profile),
+        }
+    }
+
+    /// Returns whether a profile was removed.
+    pub fn remove_profile(&mut self, project: &Path, name: &str) -> bool {
+        let key = project_key(project);
+        let Some(profiles) = self.profiles.get_mut(&key) else {
+            return false;
+        };
+        let before = profiles.len();
+        profiles.retain(|p| p.name != name);
+        let removed = profiles.len() != before;
+        if profiles.is_empty() {
+            self.profiles.remove(&key);
+        }
+        removed
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn project_key(project: &Path) -> String {
+    canonical(project).to_string_lossy().to_string()
+}
@@ -0,0 +1,655 @@
+use crate::scanner::ScannedFile;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// File-level dependency graph of a scanned project.
+///
+/// Edges point from a file to the project files it imports. Imports that do
+/// not resolve to a scanned file (the standard library, third-party packages)
+/// are dropped.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DependencyGraph {
+    edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn build(files: &[ScannedFile]) -> Self {
+        let index = ProjectIndex::new(files);
+        let mut edges = BTreeMap::new();
+
+        for file in files {
+            let mut deps = BTreeSet::new();
+            if let Some(content) = file.content.as_deref().filter(|_| !file.is_binary) {
+                for dep in index.resolve_imports(&file.path, content) {
+                    if dep != file.path {
+                        deps.insert(dep);
+                    }
+                }
+            }
+            edges.insert(file.path.clone(), deps);
+        }
+
+        Self { edges }
+    }
+
+    /// All files in the graph, sorted by path.
+    pub fn files(&self) -> impl Iterator<Item = &String> {
+        self.edges.keys()
+    }
+
+    /// Files directly imported by `path`.
+    pub fn dependencies(&self, path: &str) -> impl Iterator<Item = &String> {
+        self.edges.get(path).into_iter().flatten()
+    }
+
+    /// Files that directly import `path`.
+    pub fn dependents<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a String> {
+        self.edges
+            .iter()
+            .filter(move |(_, deps)| deps.contains(path))
+            .map(|(file, _)| file)
+    }
+
+    /// `(importer, imported)` pairs, sorted.
+    pub fn edges(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.edges
+            .iter()
+            .flat_map(|(file, deps)| deps.iter().map(move |dep| (file, dep)))
+    }
+
+    /// `start` plus every file it imports, directly or transitively.
+    pub fn transitive_dependencies(&self, start: &str) -> BTreeSet<String> {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![start.to_string()];
+
+        while let Some(path) = stack.pop() {
+            if !self.edges.contains_key(&path) || !seen.insert(path.clone()) {
+                continue;
+            }
+            stack.extend(self.dependencies(&path).cloned());
+        }
+
+        seen
+    }
+
+    /// Orders every file so that dependencies come before the files that
+    /// import them. Ties, and cycles, are broken alphabetically so the result
+    /// is deterministic.
+    pub fn topological_order(&self) -> Vec<String> {
+        // Unordered dependencies per file, and who imports each file
+        let mut remaining: BTreeMap<&String, usize> = BTreeMap::new();
+        let mut dependents: HashMap<&String, Vec<&String>> = HashMap::new();
+        for (file, deps) in &self.edges {
+            remaining.insert(file, deps.len());
+            for dep in deps {
+                dependents.entry(dep).or_default().push(file);
+            }
+        }
+        let mut ready: BTreeSet<&String> = remaining
+            .iter()
+            .filter(|(_, &count)| count == 0)
+            .map(|(file, _)| *file)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.edges.len());
+        // Cycle: fall back to the alphabetically first remaining file
+        while let Some(next) = ready
+            .pop_first()
+            .or_else(|| remaining.keys().next().copied())
+        {
+            remaining.remove(next);
+            for dependent in dependents.get(next).into_iter().flatten() {
+                if let Some(count) = remaining.get_mut(dependent) {
+                    *count = count.saturating_sub(1);
+                    if *count == 0 {
+                        ready.insert(dependent);
+                    }
+                }
+            }
+            order.push(next.clone());
+        }
+
+        order
+    }
+}
+
+/// Keeps `roots` and every file they transitively import, in scan order.
+pub fn select_with_dependencies(files: Vec<ScannedFile>, roots: &[&str]) -> Vec<ScannedFile> {
+    let graph = DependencyGraph::build(&files);
+    let keep: BTreeSet<String> = roots
+        .iter()
+        .flat_map(|root| graph.transitive_dependencies(root))
+        .collect();
+
+    files
+        .into_iter()
+        .filter(|f| keep.contains(&f.path))
+        .collect()
+}
+
+/// Lookup tables used to resolve import statements to project files.
+struct ProjectIndex<'a> {
+    paths: BTreeSet<&'a str>,
+    /// Crate name (as written in `use` paths) to its `src` directory.
+    rust_crates: HashMap<String, String>,
+    /// Go module path from `go.mod` to the directory containing it.
+    go_modules: Vec<(String, String)>,
+}
+
+impl<'a> ProjectIndex<'a> {
+    fn new(files: &'a [ScannedFile]) -> Self {
+        let paths = files.iter().map(|f| f.path.as_str()).collect();
+        let mut rust_crates = HashMap::new();
+        let mut go_modules = Vec::new();
+
+        for file in files {
+            let Some(content) = file.content.as_deref() else {
+                continue;
+            };
+            let (dir, name) = split_path(&file.path);
+            match name {
+                "Cargo.toml" => {
+                    if let Some(crate_name) = cargo_package_name(content) {
+                        rust_crates.insert(crate_name.replace('-', "_"), join(dir, "src"));
+                    }
+                }
+                "go.mod" => {
+                    if let Some(module) = content
+                        .lines()
+                        .find_map(|l| l.trim().strip_prefix("module "))
+                    {
+                        go_modules.push((module.trim().to_string(), dir.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            paths,
+            rust_crates,
+            go_modules,
+        }
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.paths.contains(path)
+    }
+
+    fn first_existing(&self, candidates: impl IntoIterator<Item = String>) -> Option<String> {
+        candidates.into_iter().find(|c| self.exists(c))
+    }
+
+    fn resolve_imports(&self, path: &str, content: &str) -> Vec<String> {
+        match extension(path) {
+            "rs" => self.resolve_rust(path, content),
+            "py" => self.resolve_python(path, content),
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => self.resolve_js(path, content),
+            "go" => self.resolve_go(path, content),
+            _ => Vec::new(),
+        }
+    }
+
+    // --- Rust ---
+
+    fn resolve_rust(&self, path: &str, content: &str) -> Vec<String> {
+        let (crate_root, module) = rust_module_path(path);
+        let mut resolved = Vec::new();
+
+        for child in rust_mod_declarations(content) {
+            let mut target = module.clone();
+            target.push(child);
+            resolved.extend(self.rust_module_file(&crate_root, &target));
+        }
+
+        for use_path in rust_use_paths(content) {
+            let segments: Vec<&str> = use_path.split("::").collect();
+            let (root, mut base, rest) = match segments.first().copied() {
+                Some("crate") => (crate_root.clone(), Vec::new(), &segments[1..]),
+                Some("self") => (crate_root.clone(), module.clone(), &segments[1..]),
+                Some("super") => {
+                    let mut base = module.clone();
+                    let mut rest = &segments[..];
+                    while rest.first() == Some(&"super") {
+                        base.pop();
+                        rest = &rest[1..];
+                    }
+                    (crate_root.clone(), base, rest)
+                }
+                Some(name) => match self.rust_crates.get(name) {
+                    Some(root) => (root.clone(), Vec::new(), &segments[1..]),
+                    None => continue,
+                },
+                None => continue,
+            };
+
+            // Longest prefix of the path that names a module file
+            let mut found = None;
+            for segment in rest {
+                base.push(segment.to_string());
+                if let Some(file) = self.rust_module_file(&root, &base) {
+                    found = Some(file);
+                }
+            }
+            resolved.extend(found);
+        }
+
+        resolved
+    }
+
+    fn rust_module_file(&self, crate_root: &str, module: &[String]) -> Option<String> {
+        if module.is_empty() {
+            return self.first_existing([join(crate_root, "lib.rs"), join(crate_root, "main.rs")]);
+        }
+        let base = join(crate_root, &module.join("/"));
+        self.first_existing([format!("{}.rs", base), format!("{}/mod.rs", base)])
+    }
+
+    // --- Python ---
+
+    fn resolve_python(&self, path: &str, content: &str) -> Vec<String> {
+        let (dir, _) = split_path(path);
+        let mut resolved = Vec::new();
+
+        for import in python_imports(content) {
+            let leading_dots = import.module.chars().take_while(|&c| c == '.').count();
+            let module_path = import.module[leading_dots..].replace('.', "/");
+
+            let bases: Vec<String> = if leading_dots > 0 {
+                let mut base = dir.to_string();
+                for _ in 1..leading_dots {
+                    base = split_path(&base).0.to_string();
+                }
+                vec![base]
+            } else {
+                // Absolute imports may be relative to the project root or to
+                // any directory on the way down to this file (e.g. `src/`)
+                let mut bases = vec![String::new()];
+                let mut acc = String::new();
+                for part in dir.split('/').filter(|p| !p.is_empty()) {
+                    acc = join(&acc, part);
+                    bases.push(acc.clone());
+                }
+                bases
+            };
+
+            for base in bases {
+                let module_base = join(&base, &module_path);
+                let mut found = Vec::new();
+
+                // `from pkg import submodule` imports the submodule file
+                for name in &import.names {
+                    found.extend(self.python_module_file(&join(&module_base, name)));
+                }
+                if found.is_empty() {
+                    found.extend(self.python_module_file(&module_base));
+                }
+                if !found.is_empty() {
+                    resolved.extend(found);
+                    break;
+                }
+            }
+        }
+
+        resolved
+    }
+
+    fn python_module_file(&self, base: &str) -> Option<String> {
+        if base.is_empty() {
+            return self.first_existing(["__init__.py".to_string()]);
+        }
+        self.first_existing([format!("{}.py", base), format!("{}/__init__.py", base)])
+    }
+
+    // --- JavaScript / TypeScript ---
+
+    fn resolve_js(&self, path: &str, content: &str) -> Vec<String> {
+        const SUFFIXES: &[&str] = &[
+            "",
+            ".ts",
+            ".tsx",
+            ".js",
+            ".jsx",
+            ".mjs",
+            ".cjs",
+            "/index.ts",
+            "/index.tsx",
+            "/index.js",
+            "/index.jsx",
+        ];
+        let (dir, _) = split_path(path);
+
+        js_import_specifiers(content)
+            .into_iter()
+            .filter(|spec| spec.starts_with('.'))
+            .filter_map(|spec| {
+                let base = normalize(&join(dir, &spec));
+                self.first_existing(SUFFIXES.iter().map(|s| format!("{}{}", base, s)))
+            })
+            .collect()
+    }
+
+    // --- Go ---
+
+    fn resolve_go(&self, path: &str, content: &str) -> Vec<String> {
+        let mut resolved = Vec::new();
+
+        for import in go_imports(content) {
+            let dir = self.go_modules.iter().find_map(|(module, dir)| {
+                if import == *module {
+                    Some(dir.clone())
+                } else {
+                    import
+                        .strip_prefix(module.as_str())
+                        .and_then(|rest| rest.strip_prefix('/'))
+                        .map(|rest| join(dir, rest))
+                }
+            });
+            let Some(dir) = dir else {
+                continue;
+            };
+
+            resolved.extend(
+                self.paths
+                    .iter()
+                    .filter(|p| {
+                        let (file_dir, name) = split_path(p);
+                        file_dir == dir
+                            && name.ends_with(".go")
+                            && !name.ends_with("_test.go")
+                            && **p != path
+                    })
+                    .map(|p| p.to_string()),
+            );
+        }
+
+        resolved
+    }
+}
+
+// --- Import statement extraction ---
+
+/// Names declared with `mod name;` (inline `mod name { .. }` blocks excluded).
+pub fn rust_mod_declarations(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = strip_rust_visibility(line.trim());
+            let rest = line.strip_prefix("mod ")?;
+            let name = rest.strip_suffix(';')?.trim();
+            is_identifier(name).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Paths named by `use` statements, with brace groups expanded
+/// (`use crate::{a, b::c};` gives `crate::a` and `crate::b::c`).
+pub fn rust_use_paths(content: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut statement: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if statement.is_none() {
+            if let Some(rest) = strip_rust_visibility(trimmed).strip_prefix("use ") {
+                statement = Some(rest.to_string());
+            } else {
+                continue;
+            }
+        } else if let Some(s) = statement.as_mut() {
+            s.push(' ');
+            s.push_str(trimmed);
+        }
+
+        if let Some(s) = &statement {
+            if let Some(end) = s.find(';') {
+                expand_use_tree("", &s[..end], &mut paths);
+                statement = None;
+            }
+        }
+    }
+
+    paths
+}
+
+fn strip_rust_visibility(line: &str) -> &str {
+    if let Some(rest) = line.strip_prefix("pub ") {
+        return rest;
+    }
+    if line.starts_with("pub(") {
+        if let Some(end) = line.find(')') {
+            return line[end + 1..].trim_start();
+        }
+    }
+    line
+}
+
+fn expand_use_tree(prefix: &str, tree: &str, out: &mut Vec<String>) {
+    let tree = tree.trim();
+    let Some(open) = tree.find('{') else {
+        let path = tree.split(" as ").next().unwrap_or(tree).trim();
+        let path = path.trim_end_matches("::*").trim_end_matches("::self");
+        if !path.is_empty() {
+            out.push(format!("{}{}", prefix, path));
+        }
+        return;
+    };
+
+    let head = tree[..open].trim();
+    let inner = tree[open + 1..]
+        .strip_suffix('}')
+        .unwrap_or(&tree[open + 1..]);
+    let new_prefix = format!("{}{}", prefix, head);
+
+    // Split on top-level commas only
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                expand_use_item(&new_prefix, &inner[start..i], out);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    expand_use_item(&new_prefix, &inner[start..], out);
+}
+
+fn expand_use_item(prefix: &str, item: &str, out: &mut Vec<String>) {
+    let item = item.trim();
+    if item.is_empty() {
+        return;
+    }
+    if item == "self" {
+        out.push(prefix.trim_end_matches("::").to_string());
+    } else {
+        expand_use_tree(prefix, item, out);
+    }
+}
+
+struct PythonImport {
+    module: String,
+    names: Vec<String>,
+}
+
+fn python_imports(content: &str) -> Vec<PythonImport> {
+    let mut imports = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("import ") {
+            for module in rest.split(',') {
+                let module = module.split_whitespace().next().unwrap_or("");
+                if !module.is_empty() {
+                    imports.push(PythonImport {
+                        module: module.to_string(),
+                        names: Vec::new(),
+                    });
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix("from ") {
+            let Some((module, names)) = rest.split_once(" import ") else {
+                continue;
+            };
+            let names = names
+                .trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace())
+                .split(',')
+                .filter_map(|n| n.split_whitespace().next())
+                .filter(|n| is_identifier(n))
+                .map(str::to_string)
+                .collect();
+            imports.push(PythonImport {
+                module: module.trim().to_string(),
+                names,
+            });
+        }
+    }
+
+    imports
+}
+
+/// Module specifiers from `import`/`export ... from`, `require()` and
+/// dynamic `import()`.
+pub fn js_import_specifiers(content: &str) -> Vec<String> {
+    let mut specs = Vec::new();
+
+    for keyword in ["from", "import", "require(", "import("] {
+        let mut search = content;
+        while let Some(pos) = search.find(keyword) {
+            let before = search[..pos].chars().next_back();
+            let after = &search[pos + keyword.len()..];
+            search = after;
+
+            if before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+                continue;
+            }
+            let rest = after.trim_start();
+            if let Some(spec) = quoted_prefix(rest) {
+                specs.push(spec.to_string());
+            }
+        }
+    }
+
+    specs
+}
+
+fn quoted_prefix(text: &str) -> Option<&str> {
+    let quote = text
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '\'' | '"' | '`'))?;
+    let rest = &text[1..];
+    rest.find(quote).map(|end| &rest[..end])
+}
+
+fn go_imports(content: &str) -> Vec<String> {
+    let mut imports = Vec::new();
+    let mut in_block = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if in_block {
+            if line.starts_with(')') {
+                in_block = false;
+            } else if let Some(spec) = line.split_whitespace().find_map(quoted_prefix) {
+                imports.push(spec.to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("import") {
+            let rest = rest.trim();
+            if rest.starts_with('(') {
+                in_block = true;
+            } else if let Some(spec) = rest.split_whitespace().find_map(quoted_prefix) {
+                imports.push(spec.to_string());
+            }
+        }
+    }
+
+    imports
+}
+
+fn cargo_package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == "name" {
+                    return Some(value.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+// --- Path helpers ---
+
+/// Crate source root and module path of a Rust file, e.g. `src/a/b.rs` gives
+/// `("src", ["a", "b"])`. Files outside a `src` directory (tests, examples)
+/// are treated as crate roots themselves.
+fn rust_module_path(path: &str) -> (String, Vec<String>) {
+    let parts: Vec<&str> = path.split('/').collect();
+    let Some(src) = parts.iter().rposition(|p| *p == "src") else {
+        let (dir, name) = split_path(path);
+        let stem = name.trim_end_matches(".rs");
+        let module = match stem {
+            "mod" | "main" | "lib" => Vec::new(),
+            _ if dir.ends_with("tests") || dir.ends_with("examples") => Vec::new(),
+            _ => vec![stem.to_string()],
+        };
+        return (dir.to_string(), module);
+    };
+
+    let crate_root = parts[..=src].join("/");
+    let mut module: Vec<String> = parts[src + 1..].iter().map(|p| p.to_string()).collect();
+    if let Some(last) = module.pop() {
+        let stem = last.trim_end_matches(".rs");
+        let is_root_file = module.is_empty() && (stem == "main" || stem == "lib");
+        if stem != "mod" && !is_root_file {
+            module.push(stem.to_string());
+        }
+    }
+    (crate_root, module)
+}
+
+fn split_path(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else if name.is_empty() {
+        dir.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn extension(path: &str) -> &str {
+    let (_, name) = split_path(path);
+    name.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("")
+}
+
+/// Resolves `.` and `..` segments.
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
@@ -0,0 +1,152 @@
+/// One line of a line-by-line comparison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Unchanged lines shown around each change by [`unified_diff`].
+const CONTEXT_LINES: usize = 3;
+
+/// Above this many line pairs the changed middle of two texts is shown as
+/// removed and re-added instead of being compared line by line.
+const MAX_COMPARED_PAIRS: usize = 4_000_000;
+
+/// Compares `old` and `new` line by line: a longest common subsequence of
+/// the part between their common first and last lines.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(|l| DiffLine::Same(l)).collect();
+    if old_middle.len() * new_middle.len() > MAX_COMPARED_PAIRS {
+        lines.extend(old_middle.iter().map(|l| DiffLine::Removed(l)));
+        lines.extend(new_middle.iter().map(|l| DiffLine::Added(l)));
+    } else {
+        lines.extend(common_subsequence_diff(old_middle, new_middle));
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|l| DiffLine::Same(l)));
+    lines
+}
+
+fn common_subsequence_diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // lengths[i][j]: longest common subsequence of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|l| DiffLine::Removed(l)));
+    lines.extend(new[j..].iter().map(|l| DiffLine::Added(l)));
+    lines
+}
+
+/// A unified diff of the file at `path`, `None` standing for a file that
+/// does not exist. Empty if nothing changed.
+pub fn unified_diff(path: &str, old: Option<&str>, new: Option<&str>) -> String {
+    let lines = diff_lines(old.unwrap_or(""), new.unwrap_or(""));
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() && old.is_some() == new.is_some() {
+        return String::new();
+    }
+
+    let mut output = format!(
+        "--- {}\n+++ {}\n",
+        old.map_or("/dev/null".to_string(), |_| format!("a/{}", path)),
+        new.map_or("/dev/null".to_string(), |_| format!("b/{}", path)),
+    );
+
+    // Group changes whose context would overlap into one hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + CONTEXT_LINES + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        // Line numbers where the hunk starts on each side
+        let old_before = count_side(&lines[..start], true);
+        let new_before = count_side(&lines[..start], false);
+        let old_count = count_side(&lines[start..end], true);
+        let new_count = count_side(&lines[start..end], false);
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_before, old_count),
+            hunk_range(new_before, new_count)
+        ));
+        for line in &lines[start..end] {
+            let (marker, text) = match line {
+                DiffLine::Same(text) => (' ', text),
+                DiffLine::Removed(text) => ('-', text),
+                DiffLine::Added(text) => ('+', text),
+            };
+            output.push(marker);
+            output.push_str(text);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+fn count_side(lines: &[DiffLine], old: bool) -> usize {
+    lines
+        .iter()
+        .filter(|line| match line {
+            DiffLine::Same(_) => true,
+            DiffLine::Removed(_) => old,
+            DiffLine::Added(_) => !old,
+        })
+        .count()
+}
+
+/// `start,count` as in a hunk header; an empty side starts at the line
+/// before it.
+fn hunk_range(before: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", before)
+    } else {
+        format!("{},{}", before + 1, count)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::scanner::{canonical_root, scan_file, ScannedFile};
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// An entry of a file list that could not be included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedPath {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Splits a file list into paths: one per line, blank lines and `#`
+/// comments skipped. Lines are kept as written; see [`scan_file_list`] for
+/// how `path:line:match` output from grep-like tools is handled.
+pub fn parse_file_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Scans exactly the listed files, skipping the directory walk but keeping the
+/// usual size limit and binary detection.
+///
+/// Paths may be absolute or relative to `root`. Each one must name an
+/// existing file inside `root`; anything else is returned as rejected. A line
+/// like `src/app.rs:42:fn main()` (grep/ripgrep output) is accepted as
+/// `src/app.rs` when the full line is not itself a file. Duplicates are
+/// included once.
+pub fn scan_file_list(
+    root: &Path,
+    paths: &[String],
+    max_file_size: u64,
+) -> (Vec<ScannedFile>, Vec<RejectedPath>) {
+    let root = canonical_root(&root.to_string_lossy());
+    let mut relatives = BTreeSet::new();
+    let mut rejected = Vec::new();
+
+    for path in paths {
+        match resolve(&root, path) {
+            Ok(relative) => {
+                relatives.insert(relative);
+            }
+            Err(reason) => rejected.push(RejectedPath {
+                path: path.clone(),
+                reason,
+            }),
+        }
+    }
+
+    let files = relatives
+        .par_iter()
+        .filter_map(|relative| scan_file(&root, relative, max_file_size))
+        .collect();
+
+    (files, rejected)
+}
+
+fn resolve(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let candidate = |p: &str| {
+        let full = root.join(p);
+        full.is_file().then_some(full)
+    };
+
+    let full = candidate(path)
+        .or_else(|| {
+            // grep-style "path:line:..." output
+            let (file, rest) = path.split_once(':')?;
+            rest.starts_with(|c: char| c.is_ascii_digit())
+                .then(|| candidate(file))
+                .flatten()
+        })
+        .ok_or_else(|| "not a file".to_string())?;
+
+    let canonical = full
+        .canonicalize()
+        .map_err(|e| format!("cannot resolve: {}", e))?;
+    canonical
+        .strip_prefix(root)
+        .map(Path::to_path_buf)
+        .map_err(|_| "outside of the root folder".to_string())
+}
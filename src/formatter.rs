@@ -1,5 +1,7 @@
-use crate::scanner::ScannedFile;
+use crate::scanner::{line_numbers, LineSpan, ScannedFile};
+use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Write};
 
 #[derive(Default)]
 struct Node {
@@ -8,13 +10,6 @@ struct Node {
 }
 
 impl Node {
-    fn new() -> Self {
-        Self {
-            children: BTreeMap::new(),
-            files: vec![],
-        }
-    }
-
     // Insert a path like ["src", "utils", "math", "helpers.rs"]
     fn insert_path(&mut self, parts: &[&str]) {
         if parts.is_empty() {
@@ -27,10 +22,7 @@ impl Node {
         } else {
             // It's a directory
             let dir = parts[0];
-            let node = self
-                .children
-                .entry(dir.to_string())
-                .or_insert_with(Node::new);
+            let node = self.children.entry(dir.to_string()).or_default();
             node.insert_path(&parts[1..]);
         }
     }
@@ -42,7 +34,7 @@ pub fn build_tree(files: &[ScannedFile]) -> Vec<(String, String)> {
     let mut top_dirs: BTreeSet<String> = BTreeSet::new();
 
     // Track files in "." if there's no directory
-    root_map.entry(".".to_string()).or_insert_with(Node::new);
+    root_map.entry(".".to_string()).or_default();
 
     // Identify top-level dirs and build placeholders
     for file in files {
@@ -58,7 +50,7 @@ pub fn build_tree(files: &[ScannedFile]) -> Vec<(String, String)> {
 
     // Ensure each top-level dir is in root_map
     for dir in top_dirs {
-        root_map.entry(dir).or_insert_with(Node::new);
+        root_map.entry(dir).or_default();
     }
 
     // Now insert each file path
@@ -187,7 +179,21 @@ pub fn build_tree(files: &[ScannedFile]) -> Vec<(String, String)> {
     output
 }
 
+/// Options controlling how file contents are rendered.
+#[derive(Clone, Debug, Default)]
+pub struct FormatOptions {
+    /// Prefix every content line with its line number in the original file.
+    pub line_numbers: bool,
+}
+
 pub fn format_file_contents(files: &[ScannedFile]) -> HashMap<String, String> {
+    format_file_contents_with_options(files, &FormatOptions::default())
+}
+
+pub fn format_file_contents_with_options(
+    files: &[ScannedFile],
+    options: &FormatOptions,
+) -> HashMap<String, String> {
     let mut formatted_output = HashMap::new();
 
     // Collect and sort file paths first
@@ -195,12 +201,7 @@ pub fn format_file_contents(files: &[ScannedFile]) -> HashMap<String, String> {
     sorted_files.sort_by(|a, b| a.path.cmp(&b.path));
 
     for file in sorted_files {
-        let formatted_entry = if file.is_binary {
-            "[Binary file]".to_string()
-        } else {
-            file.content.clone().unwrap_or("[Empty file]".to_string())
-        };
-
+        let formatted_entry = file_body(file, options);
         formatted_output.insert(
             file.path.clone(),
             format!("{}:\n{}", file.path, formatted_entry),
@@ -210,9 +211,113 @@ pub fn format_file_contents(files: &[ScannedFile]) -> HashMap<String, String> {
     formatted_output
 }
 
+/// What a file section shows below its `path:` header.
+fn file_body<'a>(file: &'a ScannedFile, options: &FormatOptions) -> Cow<'a, str> {
+    if file.is_binary {
+        // The scanner stores a description of the file instead of its bytes
+        return Cow::Borrowed(file.content.as_deref().unwrap_or("[Binary file]"));
+    }
+    match &file.content {
+        Some(content) if options.line_numbers => {
+            Cow::Owned(number_lines(content, file.line_origins.as_deref()))
+        }
+        Some(content) => Cow::Borrowed(content),
+        None => Cow::Borrowed("[Empty file]"),
+    }
+}
+
+/// Writes one file section followed by the blank line separating sections,
+/// exactly as [`format_project_summary`] lays it out.
+pub fn write_file_section(
+    out: &mut dyn Write,
+    file: &ScannedFile,
+    options: &FormatOptions,
+) -> io::Result<()> {
+    write!(out, "{}:\n{}\n\n", file.path, file_body(file, options))
+}
+
+/// Writes the tree lines and the blank line that ends them.
+pub fn write_tree(out: &mut dyn Write, tree: &[(String, String)]) -> io::Result<()> {
+    for (line, _) in tree {
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out)
+}
+
+/// Streaming counterpart of [`format_project_summary_in_order`]: writes the
+/// same bytes to `out`, formatting one file at a time instead of collecting
+/// every section first.
+pub fn write_project_summary(
+    out: &mut dyn Write,
+    tree: &[(String, String)],
+    files: &[ScannedFile],
+    options: &FormatOptions,
+    order: &[String],
+) -> io::Result<()> {
+    write_tree(out, tree)?;
+
+    // Later duplicates replace earlier ones, as in `format_file_contents`
+    let by_path: BTreeMap<&str, &ScannedFile> =
+        files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let mut written = BTreeSet::new();
+    for path in order {
+        if let Some(file) = by_path.get(path.as_str()) {
+            if written.insert(path.as_str()) {
+                write_file_section(out, file, options)?;
+            }
+        }
+    }
+    for (path, file) in &by_path {
+        if !written.contains(path) {
+            write_file_section(out, file, options)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prefixes each line with a fixed-width line number gutter.
+///
+/// `origins` tells which file line each line of `text` is (see
+/// [`ScannedFile::line_origins`]). Marker lines inserted by the scanner or a
+/// truncation rule (size placeholders, truncation and elision notes) are not
+/// part of the original file, so they get an empty gutter, and kept lines show
+/// their original numbers. Without `origins` the lines are numbered from 1.
+pub fn number_lines(text: &str, origins: Option<&[LineSpan]>) -> String {
+    let last = text
+        .lines()
+        .zip(line_numbers(origins))
+        .filter_map(|(_, n)| n)
+        .max();
+    let width = last.unwrap_or(1).to_string().len();
+
+    let mut output = String::with_capacity(text.len() + text.lines().count() * (width + 3));
+    for (line, number) in text.lines().zip(line_numbers(origins)) {
+        match number {
+            Some(n) => output.push_str(&format!("{:>width$} | {}\n", n, line)),
+            None => output.push_str(&format!("{:>width$} | {}\n", "", line)),
+        }
+    }
+
+    if !text.ends_with('\n') {
+        output.pop();
+    }
+    output
+}
+
 pub fn format_project_summary(
     tree: Vec<(String, String)>,
     file_contents: HashMap<String, String>,
+) -> String {
+    format_project_summary_in_order(tree, file_contents, &[])
+}
+
+/// Like [`format_project_summary`], but file sections listed in `order` are
+/// written first, in that order (e.g. a dependency order). Files missing from
+/// `order` follow alphabetically.
+pub fn format_project_summary_in_order(
+    tree: Vec<(String, String)>,
+    file_contents: HashMap<String, String>,
+    order: &[String],
 ) -> String {
     let mut output = String::new();
 
@@ -224,8 +329,17 @@ pub fn format_project_summary(
 
     output.push('\n'); // Space between tree and file contents
 
-    let mut sorted_files: Vec<_> = file_contents.keys().collect();
-    sorted_files.sort();
+    let mut sorted_files: Vec<_> = order
+        .iter()
+        .filter(|path| file_contents.contains_key(*path))
+        .collect();
+    let listed: BTreeSet<&String> = sorted_files.iter().copied().collect();
+    let mut rest: Vec<_> = file_contents
+        .keys()
+        .filter(|path| !listed.contains(path))
+        .collect();
+    rest.sort();
+    sorted_files.extend(rest);
 
     for file in sorted_files {
         if let Some(content) = file_contents.get(file) {
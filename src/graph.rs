@@ -0,0 +1,103 @@
+use crate::deps::DependencyGraph;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GraphFormat {
+    /// Graphviz DOT (`digraph { .. }`)
+    #[default]
+    Dot,
+    /// Mermaid flowchart (`graph LR`)
+    Mermaid,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GraphExportOptions {
+    pub format: GraphFormat,
+    /// Merge all files of a directory into one node.
+    pub collapse_by_directory: bool,
+    /// Only show this file and everything it transitively imports.
+    pub focus: Option<String>,
+    /// Only show files whose path starts with this prefix.
+    pub path_prefix: Option<String>,
+}
+
+/// Renders the dependency graph as DOT or Mermaid text. Nodes and edges are
+/// sorted so the same graph always renders identically.
+pub fn render_graph(graph: &DependencyGraph, options: &GraphExportOptions) -> String {
+    let focus = options
+        .focus
+        .as_deref()
+        .map(|path| graph.transitive_dependencies(path));
+    let included = |path: &str| {
+        focus.as_ref().is_none_or(|set| set.contains(path))
+            && options
+                .path_prefix
+                .as_deref()
+                .is_none_or(|prefix| path.starts_with(prefix))
+    };
+    let node_name = |path: &str| {
+        if options.collapse_by_directory {
+            match path.rsplit_once('/') {
+                Some((dir, _)) => dir.to_string(),
+                None => ".".to_string(),
+            }
+        } else {
+            path.to_string()
+        }
+    };
+
+    let nodes: BTreeSet<String> = graph
+        .files()
+        .filter(|path| included(path))
+        .map(|path| node_name(path))
+        .collect();
+    let edges: BTreeSet<(String, String)> = graph
+        .edges()
+        .filter(|(from, to)| included(from) && included(to))
+        .map(|(from, to)| (node_name(from), node_name(to)))
+        .filter(|(from, to)| from != to)
+        .collect();
+
+    match options.format {
+        GraphFormat::Dot => render_dot(&nodes, &edges),
+        GraphFormat::Mermaid => render_mermaid(&nodes, &edges),
+    }
+}
+
+fn render_dot(nodes: &BTreeSet<String>, edges: &BTreeSet<(String, String)>) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+
+    let mut output =
+        String::from("digraph dependencies {\n    rankdir=LR;\n    node [shape=box];\n");
+    for node in nodes {
+        output.push_str(&format!("    {};\n", quote(node)));
+    }
+    for (from, to) in edges {
+        output.push_str(&format!("    {} -> {};\n", quote(from), quote(to)));
+    }
+    output.push_str("}\n");
+    output
+}
+
+fn render_mermaid(nodes: &BTreeSet<String>, edges: &BTreeSet<(String, String)>) -> String {
+    // Mermaid ids must be plain identifiers, so paths only appear as labels
+    let ids: BTreeMap<&String, String> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node, format!("n{}", i)))
+        .collect();
+
+    let mut output = String::from("graph LR\n");
+    for (node, id) in &ids {
+        output.push_str(&format!(
+            "    {}[\"{}\"]\n",
+            id,
+            node.replace('"', "#quot;")
+        ));
+    }
+    for (from, to) in edges {
+        output.push_str(&format!("    {} --> {}\n", ids[from], ids[to]));
+    }
+    output
+}
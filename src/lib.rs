@@ -1,5 +1,30 @@
 pub mod app;
+pub mod apply;
+pub mod archive;
+pub mod binary;
+pub mod bundle;
+pub mod cache;
+pub mod cli;
 pub mod clipboard;
+pub mod config;
+pub mod deps;
+pub mod diff;
+pub mod filelist;
 pub mod formatter;
+pub mod graph;
+pub mod manifest;
+pub mod mcp;
+pub mod notebook;
+pub mod order;
+pub mod relevance;
+pub mod roots;
+pub mod sample;
 pub mod scanner;
+pub mod search;
+pub mod server;
+pub mod stream;
+pub mod truncate;
+pub mod unpack;
 pub mod utils;
+pub mod verify;
+pub mod watch;
@@ -1,11 +1,27 @@
 use contextor::app::ContextorApp;
+use contextor::cli;
 use eframe::NativeOptions;
 
 fn main() -> eframe::Result<()> {
+    // Any argument switches to the command line interface
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        let command = cli::parse_args(args).unwrap_or_else(|e| {
+            eprintln!("contextor: {}", e);
+            eprintln!("Try 'contextor --help' for more information.");
+            std::process::exit(2);
+        });
+        if let Err(e) = cli::run(command) {
+            eprintln!("contextor: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let options = NativeOptions::default();
     eframe::run_native(
         "Contextor",
         options,
-        Box::new(|_cc| Ok(Box::new(ContextorApp::default()))),
+        Box::new(|_cc| Ok(Box::new(ContextorApp::with_saved_config()))),
     )
 }
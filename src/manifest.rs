@@ -0,0 +1,322 @@
+use crate::roots::{unique_aliases, ProjectRoot};
+use crate::scanner::{slash_path, walk_files};
+use crate::utils::to_hex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// First line of the manifest section of a bundle.
+pub const MANIFEST_HEADER: &str = "Manifest:";
+
+/// Which files a bundle was made from, exactly as they were on disk, so the
+/// bundle can later be checked against the working tree.
+///
+/// In a bundle the manifest is a section of one line per root and per file:
+///
+/// ```text
+/// Manifest:
+/// root <alias> <commit or -> <clean, dirty or -> <folder>
+/// ignore <alias> <pattern>
+/// scope <all or selected>
+/// file <sha256> <bytes> <lines> <path>
+/// ```
+///
+/// The scope is `all` when the bundle had every file of its roots, and
+/// `selected` when a query, file list or other filter left some out.
+/// Spaces and `%` in an alias are written as `%20` and `%25`. As a sidecar
+/// file it is the same data as JSON. Either can be read back
+/// with [`Manifest::parse`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub roots: Vec<ManifestRoot>,
+    pub files: Vec<ManifestFile>,
+    /// Whether the bundle had every file of its roots, so files missing
+    /// from it later were added since.
+    #[serde(default)]
+    pub complete: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestRoot {
+    /// Prefix of this root's files when several roots are bundled.
+    pub alias: String,
+    pub path: PathBuf,
+    /// Extra gitignore-style patterns the root was scanned with.
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    /// `None` outside of a git repository (or without git).
+    pub git: Option<GitState>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitState {
+    pub commit: String,
+    /// Whether anything below the root differs from the commit.
+    pub dirty: bool,
+}
+
+/// A file as it is on disk, not as it was written into the bundle (which
+/// may be truncated or sampled).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Path as written in the bundle.
+    pub path: String,
+    pub size: u64,
+    pub lines: usize,
+    pub sha256: String,
+}
+
+/// What [`crate::bundle::write_bundle`] does with the manifest.
+#[derive(Clone, Debug, Default)]
+pub struct ManifestOptions {
+    /// The roots the bundled files were scanned from.
+    pub roots: Vec<ProjectRoot>,
+    /// Append the manifest section to the bundle.
+    pub section: bool,
+    /// Also write the manifest as JSON to this file.
+    pub sidecar: Option<PathBuf>,
+}
+
+impl Manifest {
+    /// Hashes the files behind the bundle `paths`. Files that cannot be read
+    /// from disk, like entries of archives, are left out.
+    pub fn build(roots: &[ProjectRoot], paths: &[String]) -> Self {
+        let mut roots = roots.to_vec();
+        unique_aliases(&mut roots);
+
+        let mut manifest = Self {
+            roots: roots
+                .iter()
+                .map(|root| {
+                    let path = root.path.canonicalize().unwrap_or(root.path.clone());
+                    ManifestRoot {
+                        alias: root.alias.clone(),
+                        git: git_state(&path),
+                        path,
+                        ignore_patterns: root.ignore_patterns.clone(),
+                    }
+                })
+                .collect(),
+            files: Vec::new(),
+            complete: false,
+        };
+        let bundled: BTreeSet<&str> = paths.iter().map(String::as_str).collect();
+        manifest.complete = manifest.roots.iter().all(|root| root.path.is_dir())
+            && manifest
+                .root_files()
+                .iter()
+                .all(|path| bundled.contains(path.as_str()));
+        manifest.files = paths
+            .iter()
+            .filter_map(|path| hash_file(path, &manifest.disk_path(path)?).ok())
+            .collect();
+        manifest
+    }
+
+    /// The file on disk behind a bundle path: relative to the only root, or
+    /// to the root whose alias is the first path component.
+    pub fn disk_path(&self, path: &str) -> Option<PathBuf> {
+        match self.roots.as_slice() {
+            [root] => Some(root.path.join(path)),
+            roots => {
+                let (alias, rest) = path.split_once('/')?;
+                let root = roots.iter().find(|root| root.alias == alias)?;
+                Some(root.path.join(rest))
+            }
+        }
+    }
+
+    /// Every file the roots would bundle now, by bundle path. Roots that are
+    /// not folders have none.
+    pub fn root_files(&self) -> BTreeSet<String> {
+        let prefix = self.roots.len() > 1;
+        let mut files = BTreeSet::new();
+        for root in self.roots.iter().filter(|root| root.path.is_dir()) {
+            for abs_path in walk_files(&root.path, &root.ignore_patterns) {
+                if let Ok(relative) = abs_path.strip_prefix(&root.path) {
+                    let relative = slash_path(relative);
+                    files.insert(if prefix {
+                        format!("{}/{}", root.alias, relative)
+                    } else {
+                        relative
+                    });
+                }
+            }
+        }
+        files
+    }
+
+    /// The manifest section, ending with a blank line.
+    pub fn render(&self) -> String {
+        let mut output = format!("{}\n", MANIFEST_HEADER);
+        for root in &self.roots {
+            let (commit, state) = match &root.git {
+                Some(git) => (
+                    git.commit.as_str(),
+                    if git.dirty { "dirty" } else { "clean" },
+                ),
+                None => ("-", "-"),
+            };
+            let alias = escape_alias(&root.alias);
+            output.push_str(&format!(
+                "root {} {} {} {}\n",
+                alias,
+                commit,
+                state,
+                root.path.display()
+            ));
+            for pattern in &root.ignore_patterns {
+                output.push_str(&format!("ignore {} {}\n", alias, pattern));
+            }
+        }
+        let scope = if self.complete { "all" } else { "selected" };
+        output.push_str(&format!("scope {}\n", scope));
+        for file in &self.files {
+            output.push_str(&format!(
+                "file {} {} {} {}\n",
+                file.sha256, file.size, file.lines, file.path
+            ));
+        }
+        output.push('\n');
+        output
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("manifest serializes")
+    }
+
+    /// Reads a manifest from a sidecar file's JSON or from the manifest
+    /// section of a bundle.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.trim_start().starts_with('{') {
+            return serde_json::from_str(text).map_err(|e| format!("invalid manifest: {}", e));
+        }
+        Self::parse_section(text).ok_or_else(|| "no manifest section found".to_string())
+    }
+
+    /// Reads the last manifest section in `bundle`, as written by
+    /// [`Manifest::render`].
+    fn parse_section(bundle: &str) -> Option<Self> {
+        let header = format!("{}\n", MANIFEST_HEADER);
+        let start = if bundle.starts_with(&header) {
+            0
+        } else {
+            bundle.rfind(&format!("\n{}", header))? + 1
+        };
+
+        let mut manifest = Self::default();
+        for line in bundle[start + header.len()..].lines() {
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match kind {
+                "root" => {
+                    let mut fields = rest.splitn(4, ' ');
+                    let (alias, commit, state, path) = (
+                        fields.next()?,
+                        fields.next()?,
+                        fields.next()?,
+                        fields.next()?,
+                    );
+                    manifest.roots.push(ManifestRoot {
+                        alias: unescape_alias(alias),
+                        path: PathBuf::from(path),
+                        ignore_patterns: Vec::new(),
+                        git: (commit != "-").then(|| GitState {
+                            commit: commit.to_string(),
+                            dirty: state == "dirty",
+                        }),
+                    });
+                }
+                "ignore" => {
+                    let (alias, pattern) = rest.split_once(' ')?;
+                    let alias = unescape_alias(alias);
+                    let root = manifest.roots.iter_mut().find(|r| r.alias == alias)?;
+                    root.ignore_patterns.push(pattern.to_string());
+                }
+                "scope" => manifest.complete = rest == "all",
+                "file" => {
+                    let mut fields = rest.splitn(4, ' ');
+                    let (sha256, size, lines, path) = (
+                        fields.next()?,
+                        fields.next()?,
+                        fields.next()?,
+                        fields.next()?,
+                    );
+                    manifest.files.push(ManifestFile {
+                        path: path.to_string(),
+                        size: size.parse().ok()?,
+                        lines: lines.parse().ok()?,
+                        sha256: sha256.to_string(),
+                    });
+                }
+                // The blank line ending the section
+                _ => break,
+            }
+        }
+        Some(manifest)
+    }
+}
+
+/// Hashes the file at `disk_path` as it is read, so large files are never
+/// held in memory.
+fn hash_file(path: &str, disk_path: &Path) -> io::Result<ManifestFile> {
+    let mut file = File::open(disk_path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let (mut size, mut newlines, mut last) = (0, 0, b'\n');
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buffer[..read];
+        hasher.update(chunk);
+        size += read as u64;
+        newlines += chunk.iter().filter(|&&b| b == b'\n').count();
+        last = chunk[read - 1];
+    }
+    Ok(ManifestFile {
+        path: path.to_string(),
+        size,
+        lines: newlines + usize::from(last != b'\n'),
+        sha256: to_hex(&hasher.finalize()),
+    })
+}
+
+/// An alias as one space-free field of a manifest line.
+fn escape_alias(alias: &str) -> String {
+    alias.replace('%', "%25").replace(' ', "%20")
+}
+
+fn unescape_alias(field: &str) -> String {
+    field.replace("%20", " ").replace("%25", "%")
+}
+
+/// The commit checked out at `dir` and whether anything below `dir` has
+/// uncommitted changes, or `None` if git is missing or `dir` is not in a
+/// repository.
+pub fn git_state(dir: &Path) -> Option<GitState> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let status = git(&["status", "--porcelain", "--", "."])?;
+    Some(GitState {
+        commit,
+        dirty: !status.is_empty(),
+    })
+}
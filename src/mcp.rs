@@ -0,0 +1,334 @@
+use crate::filelist::scan_file_list;
+use crate::formatter::{build_tree, format_file_contents_with_options, FormatOptions};
+use crate::roots::{scan_roots, ProjectRoot};
+use crate::scanner::{ScannedFile, SIZE_PLACEHOLDER_PREFIX};
+use crate::server::{build_bundle, BundleRequest, ServerConfig};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// MCP revision implemented by [`McpServer`].
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Matches returned by `search_files` unless the call asks for another limit.
+const DEFAULT_MAX_MATCHES: usize = 100;
+
+/// Longest matching line shown by `search_files`, in characters.
+const MAX_MATCH_CHARS: usize = 200;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A Model Context Protocol server over stdio: newline-delimited JSON-RPC
+/// 2.0 messages in, one response line per request out. Its tools read from
+/// a single root folder and nothing outside it.
+pub struct McpServer {
+    root: PathBuf,
+    config: ServerConfig,
+}
+
+impl McpServer {
+    /// Serves `root`, which must be an existing folder.
+    pub fn new(root: &Path, max_file_size: u64, use_cache: bool) -> Result<Self, String> {
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("cannot serve {}: {}", root.display(), e))?;
+        if !root.is_dir() {
+            return Err(format!("{} is not a directory", root.display()));
+        }
+        Ok(Self {
+            config: ServerConfig {
+                allowed_roots: vec![root.clone()],
+                max_file_size,
+                use_cache,
+            },
+            root,
+        })
+    }
+
+    /// Answers messages from `input` until it is closed.
+    pub fn run(&self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_message(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one JSON-RPC message. Notifications get no response.
+    pub fn handle_message(&self, message: &str) -> Option<String> {
+        let message: Value = match serde_json::from_str(message) {
+            Ok(message) => message,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                "missing method",
+            ));
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": {
+                    "name": "contextor",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+        };
+
+        // Requests without an id are notifications
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    /// Runs a tool. Failures of the tool itself are reported in the result
+    /// (`isError`) so the model can see them; only malformed calls are
+    /// protocol errors.
+    fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "missing tool name".to_string()))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        let output = match name {
+            "list_project_tree" => self.list_project_tree(&arguments),
+            "read_files" => self.read_files(&arguments),
+            "search_files" => self.search_files(&arguments),
+            "build_bundle" => self.build_bundle(arguments),
+            _ => return Err((INVALID_PARAMS, format!("unknown tool '{}'", name))),
+        };
+        let (text, is_error) = match output {
+            Ok(text) => (text, false),
+            Err(message) => (message, true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        }))
+    }
+
+    fn scan(&self) -> Result<Vec<ScannedFile>, String> {
+        scan_roots(
+            &[ProjectRoot::new(&self.root)],
+            self.config.max_file_size,
+            self.config.use_cache,
+        )
+    }
+
+    fn list_project_tree(&self, arguments: &Value) -> Result<String, String> {
+        let prefix = arguments
+            .get("path")
+            .and_then(Value::as_str)
+            .map(|path| path.trim_matches('/'))
+            .filter(|path| !path.is_empty() && *path != ".");
+        let files: Vec<ScannedFile> = self
+            .scan()?
+            .into_iter()
+            .filter(|file| match prefix {
+                Some(prefix) => Path::new(&file.path).starts_with(prefix),
+                None => true,
+            })
+            .collect();
+        if files.is_empty() {
+            return Err("no files found".to_string());
+        }
+        let lines: Vec<String> = build_tree(&files)
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    fn read_files(&self, arguments: &Value) -> Result<String, String> {
+        let paths: Vec<String> = arguments
+            .get("paths")
+            .and_then(Value::as_array)
+            .ok_or("'paths' must be a list of file paths")?
+            .iter()
+            .filter_map(|path| path.as_str().map(str::to_string))
+            .collect();
+        let options = FormatOptions {
+            line_numbers: arguments
+                .get("line_numbers")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        };
+
+        let (files, rejected) = scan_file_list(&self.root, &paths, self.config.max_file_size);
+        let contents = format_file_contents_with_options(&files, &options);
+        let mut sections: Vec<(&String, &String)> = contents.iter().collect();
+        sections.sort();
+
+        let mut output: Vec<String> = sections
+            .into_iter()
+            .map(|(_, section)| section.clone())
+            .collect();
+        output.extend(
+            rejected
+                .iter()
+                .map(|entry| format!("Skipped {}: {}", entry.path, entry.reason)),
+        );
+        if files.is_empty() && rejected.is_empty() {
+            return Err("no paths given".to_string());
+        }
+        if files.is_empty() {
+            return Err(output.join("\n"));
+        }
+        Ok(output.join("\n\n"))
+    }
+
+    fn search_files(&self, arguments: &Value) -> Result<String, String> {
+        let text = arguments
+            .get("text")
+            .and_then(Value::as_str)
+            .filter(|text| !text.is_empty())
+            .ok_or("'text' must be a non-empty string")?;
+        let case_sensitive = arguments
+            .get("case_sensitive")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let max_matches = arguments
+            .get("max_matches")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_MAX_MATCHES, |n| n as usize);
+
+        let needle = if case_sensitive {
+            text.to_string()
+        } else {
+            text.to_lowercase()
+        };
+        let mut files = self.scan()?;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut matches = Vec::new();
+        let mut total = 0;
+        for file in &files {
+            let Some(content) = file.content.as_deref().filter(|_| !file.is_binary) else {
+                continue;
+            };
+            if content.starts_with(SIZE_PLACEHOLDER_PREFIX) {
+                continue;
+            }
+            for (i, line) in content.lines().enumerate() {
+                let found = if case_sensitive {
+                    line.contains(&needle)
+                } else {
+                    line.to_lowercase().contains(&needle)
+                };
+                if !found {
+                    continue;
+                }
+                total += 1;
+                if matches.len() < max_matches {
+                    let shown: String = line.trim().chars().take(MAX_MATCH_CHARS).collect();
+                    matches.push(format!("{}:{}: {}", file.path, i + 1, shown));
+                }
+            }
+        }
+
+        if total == 0 {
+            return Ok(format!("No matches for '{}'", text));
+        }
+        if total > matches.len() {
+            matches.push(format!("[{} more matches omitted]", total - matches.len()));
+        }
+        Ok(matches.join("\n"))
+    }
+
+    fn build_bundle(&self, arguments: Value) -> Result<String, String> {
+        let request: BundleRequest =
+            serde_json::from_value(arguments).map_err(|e| format!("invalid arguments: {}", e))?;
+        build_bundle(&request, &self.config).map_err(|e| e.message)
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+    .to_string()
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "list_project_tree",
+            "description": "List the project's files as a directory tree. Ignored files (.gitignore) are left out.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Only list files under this folder" },
+                },
+            },
+        },
+        {
+            "name": "read_files",
+            "description": "Read files by their path relative to the project root.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "paths": { "type": "array", "items": { "type": "string" } },
+                    "line_numbers": { "type": "boolean", "description": "Prefix lines with their number" },
+                },
+                "required": ["paths"],
+            },
+        },
+        {
+            "name": "search_files",
+            "description": "Find lines containing a text, as path:line: text.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string" },
+                    "case_sensitive": { "type": "boolean" },
+                    "max_matches": { "type": "integer", "description": "Defaults to 100" },
+                },
+                "required": ["text"],
+            },
+        },
+        {
+            "name": "build_bundle",
+            "description": "Bundle the project (tree and file contents) into one text, optionally limited to files relevant to a query within a token budget.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "roots": { "type": "array", "items": { "type": "string" }, "description": "Sub-folders to bundle instead of the whole project" },
+                    "query": { "type": "string", "description": "Only include files relevant to this text" },
+                    "budget_tokens": { "type": "integer", "description": "Token budget for query results" },
+                    "with_deps": { "type": "string", "description": "Only include this file and what it imports" },
+                    "ignore": { "type": "array", "items": { "type": "string" }, "description": "Gitignore-style patterns to skip" },
+                    "line_numbers": { "type": "boolean" },
+                    "order": { "type": "string", "enum": ["alphabetical", "dirs-first", "dependencies"] },
+                    "truncate": { "type": "array", "items": { "type": "string" }, "description": "PATTERN:SPEC rules such as '*.log:tail=100'" },
+                    "sample_data": { "type": "boolean", "description": "Reduce CSV, JSON, YAML and log files to samples" },
+                    "manifest": { "type": "boolean", "description": "Append the size, line count and SHA-256 of every included file and the git commit" },
+                },
+            },
+        },
+    ])
+}
@@ -0,0 +1,141 @@
+use crate::scanner::ScannedFile;
+use serde_json::Value;
+
+/// How Jupyter notebooks (`.ipynb`) are rendered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotebookOptions {
+    /// Render cells instead of the raw notebook JSON.
+    pub extract: bool,
+    /// Include text outputs of code cells.
+    pub include_outputs: bool,
+    /// Lines kept per output before the rest is cut.
+    pub max_output_lines: usize,
+}
+
+impl Default for NotebookOptions {
+    fn default() -> Self {
+        Self {
+            extract: true,
+            include_outputs: false,
+            max_output_lines: 20,
+        }
+    }
+}
+
+/// Replaces the JSON of every notebook with its rendered cells. Notebooks
+/// that cannot be parsed are kept as they are.
+pub fn extract_notebooks(files: Vec<ScannedFile>, options: &NotebookOptions) -> Vec<ScannedFile> {
+    if !options.extract {
+        return files;
+    }
+    files
+        .into_iter()
+        .map(|mut file| {
+            if file.path.ends_with(".ipynb") {
+                if let Some(rendered) = file
+                    .content
+                    .as_deref()
+                    .and_then(|c| render_notebook(c, options))
+                {
+                    file.content = Some(rendered);
+                    file.line_origins = None;
+                }
+            }
+            file
+        })
+        .collect()
+}
+
+/// Renders the cells of an nbformat 4 notebook in order, each under a
+/// `[Cell N: type]` marker. Execution counts and metadata are dropped, and so
+/// are image outputs (noted by a marker when outputs are included).
+pub fn render_notebook(json: &str, options: &NotebookOptions) -> Option<String> {
+    let notebook: Value = serde_json::from_str(json).ok()?;
+    let cells = notebook.get("cells")?.as_array()?;
+    let language = notebook
+        .pointer("/metadata/kernelspec/language")
+        .or_else(|| notebook.pointer("/metadata/language_info/name"))
+        .and_then(Value::as_str);
+
+    let mut output = String::new();
+    for (i, cell) in cells.iter().enumerate() {
+        let cell_type = cell
+            .get("cell_type")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown");
+        match (cell_type, language) {
+            ("code", Some(language)) => {
+                output.push_str(&format!("[Cell {}: code, {}]\n", i + 1, language))
+            }
+            _ => output.push_str(&format!("[Cell {}: {}]\n", i + 1, cell_type)),
+        }
+        push_block(&mut output, &joined_text(cell.get("source")));
+
+        if cell_type == "code" && options.include_outputs {
+            for cell_output in cell
+                .get("outputs")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(text) = output_text(cell_output) {
+                    output.push_str("[Output]\n");
+                    push_block(&mut output, &limit_lines(&text, options.max_output_lines));
+                }
+            }
+        }
+        output.push('\n');
+    }
+
+    Some(output.trim_end().to_string())
+}
+
+fn push_block(output: &mut String, text: &str) {
+    if !text.is_empty() {
+        output.push_str(text.trim_end_matches('\n'));
+        output.push('\n');
+    }
+}
+
+/// Notebook text fields are either a string or a list of lines.
+fn joined_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn output_text(output: &Value) -> Option<String> {
+    match output.get("output_type")?.as_str()? {
+        "stream" => Some(joined_text(output.get("text"))),
+        "error" => {
+            let name = output
+                .get("ename")
+                .and_then(Value::as_str)
+                .unwrap_or("Error");
+            let value = output.get("evalue").and_then(Value::as_str).unwrap_or("");
+            Some(format!("{}: {}", name, value))
+        }
+        _ => {
+            let data = output.get("data")?.as_object()?;
+            // The text form of a plot is just its repr
+            if data.keys().any(|mime| mime.starts_with("image/")) {
+                Some("[Image output omitted]".to_string())
+            } else {
+                data.get("text/plain").map(|text| joined_text(Some(text)))
+            }
+        }
+    }
+}
+
+fn limit_lines(text: &str, max_lines: usize) -> String {
+    let total = text.lines().count();
+    if total <= max_lines {
+        return text.to_string();
+    }
+    let mut kept: Vec<&str> = text.lines().take(max_lines).collect();
+    let note = format!("[{} more output lines omitted]", total - max_lines);
+    kept.push(&note);
+    kept.join("\n")
+}
@@ -0,0 +1,118 @@
+use crate::deps::DependencyGraph;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The order of file sections in a bundle. Every order is total and only
+/// depends on the paths (and, for [`FileOrder::Dependencies`], the imports
+/// between files), so the same files always produce the same bundle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileOrder {
+    /// Byte-wise by path, so `Z.rs` comes before `a.rs`.
+    #[default]
+    Alphabetical,
+    /// In every directory, subdirectories before files, each alphabetically.
+    DirectoriesFirst,
+    /// Dependencies before the files that import them, ties broken
+    /// alphabetically (see [`DependencyGraph::topological_order`]).
+    Dependencies,
+}
+
+impl FileOrder {
+    pub const ALL: [FileOrder; 3] = [
+        FileOrder::Alphabetical,
+        FileOrder::DirectoriesFirst,
+        FileOrder::Dependencies,
+    ];
+
+    /// Parses `alphabetical`, `dirs-first` or `dependencies`.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|order| order.name() == name)
+            .ok_or_else(|| format!("unknown order '{}'", name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Alphabetical => "alphabetical",
+            Self::DirectoriesFirst => "dirs-first",
+            Self::Dependencies => "dependencies",
+        }
+    }
+}
+
+/// Orders `paths` for writing. Paths matching one of the gitignore-style
+/// `priority` patterns come first, grouped by the first pattern they match
+/// in list order; within each group, and for the rest, `order` applies.
+/// `graph` is only used for [`FileOrder::Dependencies`]; without it that
+/// order falls back to alphabetical. Duplicates are written once.
+pub fn order_paths(
+    paths: &[String],
+    order: FileOrder,
+    priority: &[String],
+    graph: Option<&DependencyGraph>,
+) -> Vec<String> {
+    let mut ordered: Vec<String> = paths
+        .iter()
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    match (order, graph) {
+        (FileOrder::DirectoriesFirst, _) => ordered.sort_by(|a, b| directories_first(a, b)),
+        (FileOrder::Dependencies, Some(graph)) => {
+            let rank: BTreeMap<String, usize> = graph
+                .topological_order()
+                .into_iter()
+                .enumerate()
+                .map(|(i, path)| (path, i))
+                .collect();
+            // Files unknown to the graph follow, alphabetically
+            ordered.sort_by_key(|path| rank.get(path).copied().unwrap_or(usize::MAX));
+        }
+        _ => {}
+    }
+
+    if !priority.is_empty() {
+        let matchers: Vec<Option<Gitignore>> = priority.iter().map(|p| matcher(p)).collect();
+        let group = |path: &str| {
+            matchers
+                .iter()
+                .position(|m| {
+                    m.as_ref()
+                        .is_some_and(|m| m.matched_path_or_any_parents(path, false).is_ignore())
+                })
+                .unwrap_or(matchers.len())
+        };
+        // The sort is stable, so each group keeps the order from above
+        ordered.sort_by_cached_key(|path| group(path));
+    }
+    ordered
+}
+
+fn matcher(pattern: &str) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new("");
+    builder.add_line(None, pattern).ok()?;
+    builder.build().ok()
+}
+
+/// Compares paths component by component; where they part, a directory
+/// (a component with more to follow) sorts before a file.
+fn directories_first(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('/').peekable();
+    let mut b_parts = b.split('/').peekable();
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (Some(x), Some(y)) if x == y => continue,
+            (Some(x), Some(y)) => {
+                let x_is_dir = a_parts.peek().is_some();
+                let y_is_dir = b_parts.peek().is_some();
+                return y_is_dir.cmp(&x_is_dir).then_with(|| x.cmp(y));
+            }
+            (x, y) => return x.is_some().cmp(&y.is_some()),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::deps::DependencyGraph;
+use crate::scanner::ScannedFile;
+use std::collections::{HashMap, HashSet};
+
+// BM25 tuning constants (the usual defaults)
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Path terms are counted this many times, since a file named `auth.rs` is a
+/// stronger signal than a passing mention of "auth" in a comment.
+const PATH_WEIGHT: usize = 3;
+
+/// Fraction of a matching file's score passed on to the files it references
+/// and to the files referencing it.
+const NEIGHBOUR_BOOST: f64 = 0.3;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "do", "does", "for", "from", "how", "in",
+    "is", "it", "of", "on", "or", "the", "this", "to", "what", "when", "where", "which", "who",
+    "why", "with", "work", "works",
+];
+
+/// Limit applied when selecting ranked files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Budget {
+    /// Total bytes of file content.
+    Bytes(usize),
+    /// Estimated tokens of file content (see [`estimate_tokens`]).
+    Tokens(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RankedFile {
+    pub path: String,
+    pub score: f64,
+}
+
+/// Rough token estimate used for budgeting (about four bytes per token).
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Splits text into lowercase search terms.
+///
+/// Identifiers are split on `snake_case` and `camelCase` boundaries, and the
+/// whole identifier is kept as well so exact matches still score.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        if word.is_empty() {
+            continue;
+        }
+
+        let parts = split_identifier(word);
+        if parts.len() > 1 {
+            push_term(&mut terms, &word.to_lowercase().replace('_', ""));
+        }
+        for part in parts {
+            push_term(&mut terms, &part);
+        }
+    }
+
+    terms
+}
+
+fn push_term(terms: &mut Vec<String>, term: &str) {
+    if term.len() < 2 || STOP_WORDS.contains(&term) || term.chars().all(|c| c.is_numeric()) {
+        return;
+    }
+    terms.push(stem(term));
+}
+
+fn split_identifier(word: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in word.chars() {
+        if c == '_' {
+            if !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            parts.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+/// Very small suffix stripper so "tokens"/"token" and "parsing"/"parse" meet.
+fn stem(term: &str) -> String {
+    for suffix in [
+        "ations", "ation", "ated", "ates", "ate", "ing", "ers", "er", "ed", "es", "s", "e",
+    ] {
+        if let Some(base) = term.strip_suffix(suffix) {
+            if base.len() >= 3 {
+                return base.to_string();
+            }
+        }
+    }
+    term.to_string()
+}
+
+struct Document {
+    terms: HashMap<String, usize>,
+    length: usize,
+}
+
+fn build_document(file: &ScannedFile) -> Document {
+    let mut terms: HashMap<String, usize> = HashMap::new();
+    let mut length = 0;
+
+    for term in tokenize(&file.path) {
+        *terms.entry(term).or_default() += PATH_WEIGHT;
+        length += PATH_WEIGHT;
+    }
+
+    if let Some(content) = file.content.as_deref().filter(|_| !file.is_binary) {
+        for term in tokenize(content) {
+            *terms.entry(term).or_default() += 1;
+            length += 1;
+        }
+    }
+
+    Document { terms, length }
+}
+
+/// Ranks files against a keyword or natural language query.
+///
+/// Files are scored with BM25 over their path components and identifiers,
+/// then files related to a match get a share of its score. Only files with a
+/// positive score are returned, best first.
+pub fn rank_files(files: &[ScannedFile], query: &str) -> Vec<RankedFile> {
+    let query_terms: HashSet<String> = tokenize(query).into_iter().collect();
+    if files.is_empty() || query_terms.is_empty() {
+        return Vec::new();
+    }
+
+    let documents: Vec<Document> = files.iter().map(build_document).collect();
+    let doc_count = documents.len() as f64;
+    let avg_length = documents.iter().map(|d| d.length).sum::<usize>() as f64 / doc_count;
+    let avg_length = avg_length.max(1.0);
+
+    let mut scores: Vec<f64> = vec![0.0; files.len()];
+    for term in &query_terms {
+        let doc_freq = documents
+            .iter()
+            .filter(|d| d.terms.contains_key(term))
+            .count() as f64;
+        if doc_freq == 0.0 {
+            continue;
+        }
+        let idf = ((doc_count - doc_freq + 0.5) / (doc_freq + 0.5) + 1.0).ln();
+
+        for (score, doc) in scores.iter_mut().zip(&documents) {
+            if let Some(&tf) = doc.terms.get(term) {
+                let tf = tf as f64;
+                let norm = K1 * (1.0 - B + B * doc.length as f64 / avg_length);
+                *score += idf * tf * (K1 + 1.0) / (tf + norm);
+            }
+        }
+    }
+
+    let boosted = boost_neighbours(files, &scores);
+
+    let mut ranked: Vec<RankedFile> = files
+        .iter()
+        .zip(boosted)
+        .filter(|(_, score)| *score > 0.0)
+        .map(|(file, score)| RankedFile {
+            path: file.path.clone(),
+            score,
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    ranked
+}
+
+/// Passes part of each matching file's score to the files it imports and the
+/// files that import it.
+fn boost_neighbours(files: &[ScannedFile], scores: &[f64]) -> Vec<f64> {
+    let graph = DependencyGraph::build(files);
+    let index: HashMap<&str, usize> = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.path.as_str(), i))
+        .collect();
+
+    let mut boosted = scores.to_vec();
+    for (importer, imported) in graph.edges() {
+        let (Some(&from), Some(&to)) = (index.get(importer.as_str()), index.get(imported.as_str()))
+        else {
+            continue;
+        };
+        boosted[to] += scores[from] * NEIGHBOUR_BOOST;
+        boosted[from] += scores[to] * NEIGHBOUR_BOOST;
+    }
+
+    boosted
+}
+
+/// Ranks `files` against `query` and keeps the best matches that fit within
+/// `budget`, in rank order. Files too large for the remaining budget are
+/// skipped so smaller relevant files can still be included.
+pub fn select_relevant(files: Vec<ScannedFile>, query: &str, budget: Budget) -> Vec<ScannedFile> {
+    let ranked = rank_files(&files, query);
+
+    let mut by_path: HashMap<String, ScannedFile> =
+        files.into_iter().map(|f| (f.path.clone(), f)).collect();
+
+    let mut selected = Vec::new();
+    let mut used: usize = 0;
+    for entry in ranked {
+        let Some(file) = by_path.remove(&entry.path) else {
+            continue;
+        };
+        let content = file.content.as_deref().unwrap_or("");
+        let cost = match budget {
+            Budget::Bytes(_) => content.len(),
+            Budget::Tokens(_) => estimate_tokens(content),
+        };
+        let limit = match budget {
+            Budget::Bytes(n) | Budget::Tokens(n) => n,
+        };
+
+        // Saturating, so an unbounded budget (`usize::MAX`) cannot overflow
+        let total = used.saturating_add(cost);
+        if total <= limit {
+            used = total;
+            selected.push(file);
+        }
+    }
+
+    selected
+}
@@ -0,0 +1,182 @@
+use crate::archive::{expand_archives, is_archive, scan_archive};
+use crate::cache::ScanCache;
+use crate::scanner::{canonical_root, scan_project_with_ignores, ScannedFile};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// One folder contributing files to a bundle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectRoot {
+    pub path: PathBuf,
+    /// Name shown as this root's top-level directory when several roots are
+    /// bundled together.
+    pub alias: String,
+    /// Extra gitignore-style patterns applied to this root only.
+    pub ignore_patterns: Vec<String>,
+    /// Scan the entries of zip and tar archives found in this root as if they
+    /// were directories.
+    #[serde(default)]
+    pub expand_archives: bool,
+}
+
+impl ProjectRoot {
+    /// A root aliased by its folder name.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let alias = default_alias(&path);
+        Self {
+            path,
+            alias,
+            ignore_patterns: Vec::new(),
+            expand_archives: false,
+        }
+    }
+}
+
+fn default_alias(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string())
+}
+
+/// Makes every alias a plain folder name (non-empty, slash-free and not `.`
+/// or `..`) and unique by appending `-2`, `-3`, … to repeats, so prefixed
+/// paths can never collide or leave the bundle's folder.
+pub fn unique_aliases(roots: &mut [ProjectRoot]) {
+    let mut seen = BTreeSet::new();
+    for root in roots.iter_mut() {
+        let mut base = root.alias.trim().replace(['/', '\\'], "_");
+        if matches!(base.as_str(), "" | "." | "..") {
+            base = default_alias(&root.path);
+        }
+
+        let mut alias = base.clone();
+        let mut n = 2;
+        while !seen.insert(alias.clone()) {
+            alias = format!("{}-{}", base, n);
+            n += 1;
+        }
+        root.alias = alias;
+    }
+}
+
+/// The root a bundle path belongs to and the path relative to it: the only
+/// root, or the one whose alias is the first component. Aliases must be
+/// unique (see [`unique_aliases`]).
+pub fn split_root_path<'a>(
+    roots: &'a [ProjectRoot],
+    path: &'a str,
+) -> Option<(&'a ProjectRoot, &'a str)> {
+    match roots {
+        [root] => Some((root, path)),
+        _ => {
+            let (alias, rest) = path.split_once('/')?;
+            Some((roots.iter().find(|root| root.alias == alias)?, rest))
+        }
+    }
+}
+
+/// Where the files behind bundle paths are on disk, so the parts of an
+/// oversize file that a sampling or truncation rule keeps can still be read.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiskFiles {
+    roots: Vec<ProjectRoot>,
+    max_file_size: u64,
+}
+
+impl DiskFiles {
+    /// Files scanned from `roots`, keeping at most `max_file_size` bytes of
+    /// any one file as the scan does.
+    pub fn new(roots: &[ProjectRoot], max_file_size: u64) -> Self {
+        let mut roots = roots.to_vec();
+        unique_aliases(&mut roots);
+        Self {
+            roots,
+            max_file_size,
+        }
+    }
+
+    /// Opens the file behind a bundle path. Entries of archives cannot be
+    /// opened.
+    pub fn open(&self, path: &str) -> Option<File> {
+        let (root, relative) = split_root_path(&self.roots, path)?;
+        File::open(root.path.join(relative)).ok()
+    }
+
+    /// Most bytes kept from one file.
+    pub fn limit(&self) -> usize {
+        usize::try_from(self.max_file_size).unwrap_or(usize::MAX)
+    }
+}
+
+/// Scans every root. A root may also be a zip or tar archive, which is read
+/// in memory. With a single root, paths stay relative to it as in
+/// [`crate::scanner::scan_project`]. With several, each path is prefixed by
+/// its root's alias (`backend/src/main.rs`), so `build_tree` shows one
+/// top-level section per root and content headers stay unambiguous. Fails if
+/// an archive root cannot be read.
+pub fn scan_roots(
+    roots: &[ProjectRoot],
+    max_file_size: u64,
+    use_cache: bool,
+) -> Result<Vec<ScannedFile>, String> {
+    let mut roots = roots.to_vec();
+    unique_aliases(&mut roots);
+    let prefix = roots.len() > 1;
+
+    let mut files = Vec::new();
+    for root in &roots {
+        let scanned = scan_root(root, max_file_size, use_cache)?;
+        files.extend(scanned.into_iter().map(|mut file| {
+            if prefix {
+                file.path = format!("{}/{}", root.alias, file.path);
+            }
+            file
+        }));
+    }
+    Ok(files)
+}
+
+fn scan_root(
+    root: &ProjectRoot,
+    max_file_size: u64,
+    use_cache: bool,
+) -> Result<Vec<ScannedFile>, String> {
+    if root.path.is_file() && is_archive(&root.path) {
+        return scan_archive(&root.path, max_file_size, &root.ignore_patterns)
+            .map_err(|e| format!("cannot read archive {}: {}", root.path.display(), e));
+    }
+
+    let files = scan_folder(root, max_file_size, use_cache);
+    if root.expand_archives {
+        let path = canonical_root(&root.path.to_string_lossy());
+        Ok(expand_archives(
+            &path,
+            files,
+            max_file_size,
+            &root.ignore_patterns,
+        ))
+    } else {
+        Ok(files)
+    }
+}
+
+fn scan_folder(root: &ProjectRoot, max_file_size: u64, use_cache: bool) -> Vec<ScannedFile> {
+    if use_cache {
+        if let Ok(mut cache) = ScanCache::open(&root.path, max_file_size) {
+            let files = cache.scan_with_ignores(&root.ignore_patterns);
+            // A cache that cannot be written only costs speed
+            let _ = cache.save();
+            return files;
+        }
+    }
+    scan_project_with_ignores(
+        &root.path.to_string_lossy(),
+        max_file_size,
+        &root.ignore_patterns,
+    )
+}
@@ -0,0 +1,538 @@
+use crate::roots::DiskFiles;
+use crate::scanner::{LineSpan, ScannedFile, SIZE_PLACEHOLDER_PREFIX, TRUNCATION_MARKER};
+use crate::truncate::{read_line_bounded, TruncateStrategy};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::Number;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+/// Documents smaller than this are kept whole by [`SampleStrategy::Outline`]:
+/// small config files read better as they are.
+pub const OUTLINE_MIN_BYTES: usize = 4096;
+
+/// Longest sample value shown in an outline, in characters.
+const SAMPLE_VALUE_CHARS: usize = 40;
+
+/// Deepest level of an outline before nested values are elided.
+const OUTLINE_MAX_DEPTH: usize = 8;
+
+/// How a data file is reduced to its shape.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SampleStrategy {
+    /// CSV/TSV: the header, the first N rows and the row count.
+    Table { rows: usize },
+    /// JSON: every key with its type and a sample value, one item per array.
+    /// YAML: the document with only the first item of each sequence.
+    Outline,
+    /// Logs: the last N lines.
+    Tail { lines: usize },
+}
+
+/// A sampled text and, if its lines are lines of the file, their origins.
+pub type Sample = (String, Option<Vec<LineSpan>>);
+
+impl SampleStrategy {
+    /// Parses `table`, `table=N`, `outline` or `tail=N`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let number = |text: &str| {
+            text.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid number '{}' in '{}'", text, spec))
+        };
+        match spec.trim().split_once('=') {
+            None if spec.trim() == "table" => Ok(Self::Table { rows: 10 }),
+            None if spec.trim() == "outline" => Ok(Self::Outline),
+            Some(("table", n)) => Ok(Self::Table { rows: number(n)? }),
+            Some(("tail", n)) => Ok(Self::Tail { lines: number(n)? }),
+            _ => Err(format!("unknown sampling '{}'", spec)),
+        }
+    }
+
+    /// Reduces `text` of the file at `path`, or returns `None` to keep it
+    /// (unparseable or small documents).
+    pub fn apply(&self, path: &str, text: &str) -> Option<String> {
+        self.apply_with_origins(path, text)
+            .map(|(sample, _)| sample)
+    }
+
+    /// Like [`Self::apply`], also returning where the sample's lines come
+    /// from when they are lines of the file (see
+    /// [`ScannedFile::line_origins`]).
+    pub fn apply_with_origins(&self, path: &str, text: &str) -> Option<Sample> {
+        let sample = match self {
+            Self::Table { rows } => sample_table(text, *rows),
+            Self::Outline if text.len() < OUTLINE_MIN_BYTES => return None,
+            Self::Outline if is_yaml(path) => {
+                outline_yaml(text.lines(), text.len() as u64, usize::MAX)
+            }
+            Self::Outline => outline_json(
+                &mut serde_json::Deserializer::from_str(text),
+                text.len() as u64,
+            )?,
+            Self::Tail { lines } => {
+                let (tail, origins) = TruncateStrategy::Tail(*lines).apply_with_origins(text);
+                return Some((tail, Some(origins)));
+            }
+        };
+        Some((sample, None))
+    }
+
+    /// Like [`Self::apply_with_origins`] for a file too large to load. The
+    /// file is read as a stream and at most `limit` bytes of it are held at a
+    /// time, except for JSON, whose outline is built while parsing.
+    pub fn apply_file(&self, path: &str, file: &mut File, limit: usize) -> Option<Sample> {
+        let sample = match self {
+            Self::Table { rows } => sample_table_file(file, *rows, limit).ok()??,
+            Self::Outline if is_yaml(path) => {
+                let size = file.metadata().ok()?.len();
+                let mut reader = BufReader::new(file);
+                let mut error = None;
+                let lines = std::iter::from_fn(|| {
+                    read_line_bounded(&mut reader, limit).unwrap_or_else(|e| {
+                        error = Some(e);
+                        None
+                    })
+                });
+                let outline = outline_yaml(lines, size, limit);
+                error.is_none().then_some(outline)?
+            }
+            Self::Outline => {
+                let size = file.metadata().ok()?.len();
+                outline_json(
+                    &mut serde_json::Deserializer::from_reader(BufReader::new(file)),
+                    size,
+                )?
+            }
+            Self::Tail { lines } => {
+                let (tail, origins) = TruncateStrategy::Tail(*lines)
+                    .apply_file(file, limit)
+                    .ok()?;
+                return Some((tail, Some(origins)));
+            }
+        };
+        Some((sample, None))
+    }
+}
+
+fn is_yaml(path: &str) -> bool {
+    path.ends_with(".yaml") || path.ends_with(".yml")
+}
+
+/// A sampling strategy for files matching a gitignore-style pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SampleRule {
+    pub pattern: String,
+    pub strategy: SampleStrategy,
+}
+
+impl SampleRule {
+    /// Parses `PATTERN:SPEC`, e.g. `*.csv:table=20` or `*.log:tail=200`.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let (pattern, spec) = rule
+            .trim()
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected PATTERN:SPEC, got '{}'", rule))?;
+        if pattern.is_empty() {
+            return Err(format!("missing file pattern in '{}'", rule));
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            strategy: SampleStrategy::parse(spec)?,
+        })
+    }
+
+    /// Tables, outlines and log tails for the common data file types.
+    pub fn defaults() -> Vec<Self> {
+        [
+            "*.csv:table=10",
+            "*.tsv:table=10",
+            "*.json:outline",
+            "*.yaml:outline",
+            "*.yml:outline",
+            "*.log:tail=100",
+        ]
+        .iter()
+        .filter_map(|rule| Self::parse(rule).ok())
+        .collect()
+    }
+
+    fn matcher(&self) -> Option<Gitignore> {
+        let mut builder = GitignoreBuilder::new("");
+        builder.add_line(None, &self.pattern).ok()?;
+        builder.build().ok()
+    }
+}
+
+/// Applies the last matching rule to each text file. Files that were too
+/// large to scan are sampled from `disk` when given.
+pub fn sample_data_files(
+    files: Vec<ScannedFile>,
+    rules: &[SampleRule],
+    disk: Option<&DiskFiles>,
+) -> Vec<ScannedFile> {
+    if rules.is_empty() {
+        return files;
+    }
+    let matchers: Vec<(Option<Gitignore>, &SampleStrategy)> = rules
+        .iter()
+        .map(|rule| (rule.matcher(), &rule.strategy))
+        .collect();
+
+    files
+        .into_iter()
+        .map(|mut file| {
+            let Some(content) = file.content.as_deref().filter(|_| !file.is_binary) else {
+                return file;
+            };
+            let Some(strategy) = matchers.iter().rev().find_map(|(matcher, strategy)| {
+                matcher
+                    .as_ref()?
+                    .matched(&file.path, false)
+                    .is_ignore()
+                    .then_some(*strategy)
+            }) else {
+                return file;
+            };
+            let sampled = if content.starts_with(SIZE_PLACEHOLDER_PREFIX) {
+                disk.and_then(|disk| {
+                    let mut opened = disk.open(&file.path)?;
+                    strategy.apply_file(&file.path, &mut opened, disk.limit())
+                })
+            } else {
+                strategy.apply_with_origins(&file.path, content)
+            };
+            if let Some((sampled, origins)) = sampled {
+                file.content = Some(sampled);
+                file.line_origins = origins;
+            }
+            file
+        })
+        .collect()
+}
+
+/// Splits CSV/TSV text into records, keeping newlines inside quoted fields.
+fn records(text: &str) -> Vec<&str> {
+    let mut records = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '\n' if !quoted => {
+                records.push(text[start..i].trim_end_matches('\r'));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < text.len() {
+        records.push(text[start..].trim_end_matches('\r'));
+    }
+    records.retain(|r| !r.is_empty());
+    records
+}
+
+fn column_count(record: &str, delimiter: char) -> usize {
+    let mut quoted = false;
+    1 + record
+        .chars()
+        .filter(|&c| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == delimiter && !quoted
+        })
+        .count()
+}
+
+/// Counts the records of CSV/TSV data as [`records`] splits them, reading
+/// it as a stream.
+fn count_records(reader: impl Read) -> io::Result<usize> {
+    let mut reader = BufReader::new(reader);
+    let mut count = 0;
+    let mut quoted = false;
+    let mut empty = true;
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        for &byte in buffer {
+            match byte {
+                b'"' => {
+                    quoted = !quoted;
+                    empty = false;
+                }
+                b'\n' if !quoted => {
+                    count += usize::from(!empty);
+                    empty = true;
+                }
+                b'\r' => {}
+                _ => empty = false,
+            }
+        }
+        let length = buffer.len();
+        reader.consume(length);
+    }
+    Ok(count + usize::from(!empty))
+}
+
+fn sample_table(text: &str, rows: usize) -> String {
+    let records = records(text);
+    match records.len() {
+        0 => text.to_string(),
+        count => table_sample(&records, count - 1, rows),
+    }
+}
+
+/// Samples the table from its first `limit` bytes, counting all of its rows.
+fn sample_table_file(file: &mut File, rows: usize, limit: usize) -> io::Result<Option<String>> {
+    let total = count_records(&mut *file)?;
+    file.seek(SeekFrom::Start(0))?;
+    let mut head = Vec::new();
+    (&mut *file).take(limit as u64 + 1).read_to_end(&mut head)?;
+    let complete = head.len() <= limit;
+    head.truncate(limit);
+
+    let head = String::from_utf8_lossy(&head);
+    let mut records = records(&head);
+    if !complete {
+        // The last record may have been cut off
+        records.pop();
+    }
+    Ok((!records.is_empty()).then(|| table_sample(&records, total.saturating_sub(1), rows)))
+}
+
+/// The table of `total` rows with its header and the first `rows` of
+/// `records`, which starts with the header.
+fn table_sample(records: &[&str], total: usize, rows: usize) -> String {
+    let header = records[0];
+    let delimiter = if header.matches('\t').count() > header.matches(',').count() {
+        '\t'
+    } else {
+        ','
+    };
+    let shown = total.min(rows).min(records.len() - 1);
+
+    let mut output = vec![format!(
+        "[Table: {} rows, {} columns; header and first {} rows shown]",
+        total,
+        column_count(header, delimiter),
+        shown
+    )];
+    output.extend(records.iter().take(shown + 1).map(|r| r.to_string()));
+    if total > shown {
+        output.push(format!("[{} more rows omitted]", total - shown));
+    }
+    output.join("\n")
+}
+
+fn outline_json<'de, R: serde_json::de::Read<'de>>(
+    deserializer: &mut serde_json::Deserializer<R>,
+    size: u64,
+) -> Option<String> {
+    let mut lines = vec![format!("[JSON outline of {} bytes]", size)];
+    Outline {
+        label: "$".to_string(),
+        depth: 0,
+        lines: &mut lines,
+    }
+    .deserialize(&mut *deserializer)
+    .ok()?;
+    deserializer.end().ok()?;
+    Some(lines.join("\n"))
+}
+
+/// Appends the outline of one JSON value to `lines` while it is parsed, so
+/// only the first item of each array is ever held.
+struct Outline<'a> {
+    label: String,
+    depth: usize,
+    lines: &'a mut Vec<String>,
+}
+
+impl Outline<'_> {
+    fn push(&mut self, description: String) {
+        let indent = "  ".repeat(self.depth);
+        self.lines
+            .push(format!("{}{}: {}", indent, self.label, description));
+    }
+
+    fn child<'b>(&self, label: String, lines: &'b mut Vec<String>) -> Outline<'b> {
+        Outline {
+            label,
+            depth: self.depth + 1,
+            lines,
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Outline<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Outline<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        if self.depth >= OUTLINE_MAX_DEPTH {
+            let mut keys = 0;
+            while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {
+                keys += 1;
+            }
+            self.push(format!("object ({} keys, not expanded)", keys));
+            return Ok(());
+        }
+        // Keys are listed sorted; a repeated key keeps its last value
+        let mut children = BTreeMap::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let mut lines = Vec::new();
+            map.next_value_seed(self.child(key.clone(), &mut lines))?;
+            children.insert(key, lines);
+        }
+        self.push(format!("object ({} keys)", children.len()));
+        self.lines.extend(children.into_values().flatten());
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        let mut items = 0;
+        let mut first = Vec::new();
+        if self.depth < OUTLINE_MAX_DEPTH
+            && seq
+                .next_element_seed(self.child("[0]".to_string(), &mut first))?
+                .is_some()
+        {
+            items += 1;
+        }
+        while seq.next_element::<IgnoredAny>()?.is_some() {
+            items += 1;
+        }
+        if self.depth >= OUTLINE_MAX_DEPTH {
+            self.push(format!("array ({} items, not expanded)", items));
+        } else {
+            self.push(format!("array ({} items)", items));
+            self.lines.extend(first);
+        }
+        Ok(())
+    }
+
+    fn visit_str<E>(mut self, text: &str) -> Result<(), E> {
+        let mut sample: String = text.chars().take(SAMPLE_VALUE_CHARS).collect();
+        if sample.len() < text.len() {
+            sample.push('…');
+        }
+        self.push(format!("string = {:?}", sample));
+        Ok(())
+    }
+
+    fn visit_u64<E>(mut self, n: u64) -> Result<(), E> {
+        self.push(format!("number = {}", n));
+        Ok(())
+    }
+
+    fn visit_i64<E>(mut self, n: i64) -> Result<(), E> {
+        self.push(format!("number = {}", n));
+        Ok(())
+    }
+
+    fn visit_f64<E>(mut self, n: f64) -> Result<(), E> {
+        let number = Number::from_f64(n).map_or_else(|| n.to_string(), |n| n.to_string());
+        self.push(format!("number = {}", number));
+        Ok(())
+    }
+
+    fn visit_bool<E>(mut self, b: bool) -> Result<(), E> {
+        self.push(format!("bool = {}", b));
+        Ok(())
+    }
+
+    fn visit_unit<E>(mut self) -> Result<(), E> {
+        self.push("null".to_string());
+        Ok(())
+    }
+}
+
+/// Keeps the structure of a block-style YAML document of `size` bytes but
+/// only the first item of every sequence, noting how many were left out. An
+/// outline longer than `limit` bytes ends with the truncation marker.
+fn outline_yaml<S: AsRef<str>>(
+    document: impl Iterator<Item = S>,
+    size: u64,
+    limit: usize,
+) -> String {
+    let mut lines = vec![format!("[YAML outline of {} bytes]", size)];
+    let (mut length, mut counted) = (0, 0);
+    let mut sequences: Vec<(usize, usize)> = Vec::new(); // (indent, items seen)
+    let mut skip_below: Option<usize> = None;
+
+    let close = |lines: &mut Vec<String>, (indent, items): (usize, usize)| {
+        if items > 1 {
+            lines.push(format!(
+                "{}[{} more items omitted]",
+                " ".repeat(indent),
+                items - 1
+            ));
+        }
+    };
+
+    for line in document {
+        let line = line.as_ref();
+        length += lines[counted..].iter().map(|l| l.len() + 1).sum::<usize>();
+        counted = lines.len();
+        if length > limit {
+            lines.push(TRUNCATION_MARKER.to_string());
+            return lines.join("\n");
+        }
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            if skip_below.is_none() {
+                lines.push(line.to_string());
+            }
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let is_item = trimmed == "-" || trimmed.starts_with("- ");
+
+        while let Some(&(seq_indent, items)) = sequences.last() {
+            if indent < seq_indent || (indent == seq_indent && !is_item) {
+                sequences.pop();
+                close(&mut lines, (seq_indent, items));
+            } else {
+                break;
+            }
+        }
+        match skip_below {
+            Some(skipped) if indent > skipped => continue,
+            _ => skip_below = None,
+        }
+
+        if is_item {
+            match sequences.last_mut() {
+                Some((seq_indent, items)) if *seq_indent == indent => {
+                    *items += 1;
+                    skip_below = Some(indent);
+                    continue;
+                }
+                _ => sequences.push((indent, 1)),
+            }
+        }
+        lines.push(line.to_string());
+    }
+    while let Some(sequence) = sequences.pop() {
+        close(&mut lines, sequence);
+    }
+
+    lines.join("\n")
+}
@@ -1,30 +1,138 @@
-use ignore::WalkBuilder;
+use crate::binary::{describe_binary, describe_binary_file, read_head};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
 use rayon::prelude::*;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Prefix of the placeholder stored instead of the content of oversized files.
+pub const SIZE_PLACEHOLDER_PREFIX: &str = "[File size > ";
+
+/// Line appended to text that was cut short by [`truncate_text`].
+pub const TRUNCATION_MARKER: &str = "[Truncated: File too large]";
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct ScannedFile {
     pub path: String, // Now stores relative path
     pub content: Option<String>,
     pub is_binary: bool,
+    /// Where the lines of `content` come from, in order, or `None` if they
+    /// are the file's lines from the first on. Line numbers follow this, not
+    /// the text, so a file line that reads like a marker is still numbered.
+    pub line_origins: Option<Vec<LineSpan>>,
+}
+
+impl ScannedFile {
+    /// Whether the scan left out the end of the file, as a size placeholder
+    /// or by cutting the text at the size limit: the content is the file's
+    /// first lines, if any, and a marker line.
+    pub fn is_cut(&self) -> bool {
+        matches!(
+            self.line_origins.as_deref(),
+            Some([LineSpan::Marker] | [LineSpan::Lines { first: 1, .. }, LineSpan::Marker])
+        )
+    }
+}
+
+/// A run of content lines and where they come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineSpan {
+    /// `count` lines of the file, the first of them line `first` (1-based).
+    Lines { first: usize, count: usize },
+    /// One line that is not in the file: a size placeholder, a truncation
+    /// marker or an elision marker.
+    Marker,
+}
+
+/// The file line number of each content line described by `origins`, `None`
+/// for marker lines. Lines past the described ones continue the count.
+pub fn line_numbers(origins: Option<&[LineSpan]>) -> impl Iterator<Item = Option<usize>> + '_ {
+    let mut spans = origins.unwrap_or_default().iter();
+    let (mut next, mut left) = (1, 0);
+    std::iter::from_fn(move || loop {
+        if left > 0 {
+            left -= 1;
+            next += 1;
+            return Some(Some(next - 1));
+        }
+        match spans.next() {
+            Some(&LineSpan::Lines { first, count }) => (next, left) = (first, count),
+            Some(LineSpan::Marker) => return Some(None),
+            None => left = usize::MAX,
+        }
+    })
+}
+
+/// Origins of content holding the first `kept` lines of a file followed by
+/// one marker line.
+pub(crate) fn cut_origins(kept: usize) -> Vec<LineSpan> {
+    let mut origins = Vec::new();
+    if kept > 0 {
+        origins.push(LineSpan::Lines {
+            first: 1,
+            count: kept,
+        });
+    }
+    origins.push(LineSpan::Marker);
+    origins
 }
 
 pub fn scan_project(folder_path: &str, max_file_size: u64) -> Vec<ScannedFile> {
-    // Convert the input folder to a canonical root path
-    // (in case of symlinks, different drive letters, etc.)
-    let root_path = match PathBuf::from(folder_path).canonicalize() {
+    scan_project_with_ignores(folder_path, max_file_size, &[])
+}
+
+/// Like [`scan_project`], additionally skipping paths matched by the given
+/// gitignore-style patterns (relative to the folder).
+pub fn scan_project_with_ignores(
+    folder_path: &str,
+    max_file_size: u64,
+    ignore_patterns: &[String],
+) -> Vec<ScannedFile> {
+    let root_path = canonical_root(folder_path);
+    let files = walk_files(&root_path, ignore_patterns);
+
+    // Process files in parallel (rayon)
+    files
+        .par_iter()
+        .filter_map(|abs_path| {
+            // Convert absolute path to relative (strip the root)
+            let relative = abs_path
+                .strip_prefix(&root_path)
+                .unwrap_or(abs_path) // fallback if strip_prefix fails
+                .to_path_buf();
+
+            process_file(&root_path, &relative, max_file_size)
+        })
+        .collect()
+}
+
+/// Converts the input folder to a canonical root path
+/// (in case of symlinks, different drive letters, etc.)
+pub(crate) fn canonical_root(folder_path: &str) -> PathBuf {
+    match PathBuf::from(folder_path).canonicalize() {
         Ok(p) => p,
         Err(_) => PathBuf::from(folder_path), // Fallback if canonicalize fails
-    };
+    }
+}
 
-    let walker = WalkBuilder::new(&root_path)
+/// Absolute paths of all files below `root_path` that are not ignored.
+pub(crate) fn walk_files(root_path: &Path, ignore_patterns: &[String]) -> Vec<PathBuf> {
+    let mut builder = WalkBuilder::new(root_path);
+    builder
         .git_ignore(true) // Respect .gitignore
         .hidden(false) // Show hidden files (set true if you want them excluded)
-        .parents(true) // Respect parent directory .gitignore
-        .build();
+        .parents(true); // Respect parent directory .gitignore
+
+    if let Some(matcher) = pattern_matcher(root_path, ignore_patterns) {
+        builder.filter_entry(move |e| {
+            let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+            !matcher.matched(e.path(), is_dir).is_ignore()
+        });
+    }
+    let walker = builder.build();
 
     // Collect paths (files only) before processing
-    let files: Vec<PathBuf> = walker
+    walker
         .filter_map(|entry| {
             if let Ok(e) = entry {
                 let path = e.path();
@@ -40,21 +148,89 @@ pub fn scan_project(folder_path: &str, max_file_size: u64) -> Vec<ScannedFile> {
             }
             None
         })
-        .collect();
+        .collect()
+}
 
-    // Process files in parallel (rayon)
-    files
-        .par_iter()
-        .filter_map(|abs_path| {
-            // Convert absolute path to relative (strip the root)
-            let relative = abs_path
-                .strip_prefix(&root_path)
-                .unwrap_or(abs_path) // fallback if strip_prefix fails
-                .to_path_buf();
+/// `relative` as stored in [`ScannedFile::path`]: components joined by `/`
+/// on every platform, so bundles do not depend on the OS they were made on.
+pub fn slash_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
 
-            process_file(&root_path, &relative, max_file_size)
-        })
-        .collect()
+/// Reads a single file the same way [`scan_project`] does.
+///
+/// Returns `None` if the file cannot be read (e.g. it has been deleted).
+pub fn scan_file(root_path: &Path, relative: &Path, max_file_size: u64) -> Option<ScannedFile> {
+    process_file(root_path, relative, max_file_size)
+}
+
+/// Matcher for extra gitignore-style patterns, `None` if there are none.
+fn pattern_matcher(root_path: &Path, patterns: &[String]) -> Option<Gitignore> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(root_path);
+    for pattern in patterns {
+        // Invalid patterns are skipped rather than failing the whole scan
+        let _ = builder.add_line(None, pattern);
+    }
+    builder.build().ok()
+}
+
+/// Whether `relative` would be skipped by [`scan_project_with_ignores`]:
+/// anything inside `.git`, matched by `ignore_patterns`, or matched by a
+/// `.gitignore` between `root_path` and the file.
+pub fn is_ignored(
+    root_path: &Path,
+    relative: &Path,
+    is_dir: bool,
+    ignore_patterns: &[String],
+) -> bool {
+    if relative.components().any(|c| c.as_os_str() == ".git") {
+        return true;
+    }
+    if let Some(matcher) = pattern_matcher(root_path, ignore_patterns) {
+        let path = root_path.join(relative);
+        if matcher
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+        {
+            return true;
+        }
+    }
+
+    // Collect the directories from the root down to the file's parent
+    let mut dirs = vec![root_path.to_path_buf()];
+    if let Some(parent) = relative.parent() {
+        for component in parent.components() {
+            let next = dirs[dirs.len() - 1].join(component);
+            dirs.push(next);
+        }
+    }
+
+    // Deeper .gitignore files take precedence over shallower ones
+    for dir in dirs.iter().rev() {
+        let gitignore_path = dir.join(".gitignore");
+        if !gitignore_path.is_file() {
+            continue;
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        builder.add(&gitignore_path);
+        let Ok(gitignore) = builder.build() else {
+            continue;
+        };
+        match gitignore.matched_path_or_any_parents(root_path.join(relative), is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+
+    false
 }
 
 /// This function expects the **root** path plus the **relative** path.
@@ -65,50 +241,110 @@ fn process_file(root_path: &Path, relative: &Path, max_file_size: u64) -> Option
     if let Ok(metadata) = fs::metadata(&full_path) {
         let file_size = metadata.len();
 
-        // If the file is too large, just store a placeholder
+        // If the file is too large, only look at its header
         if file_size > max_file_size {
-            return Some(ScannedFile {
-                path: relative.to_string_lossy().to_string(), // store relative
-                content: Some(format!(
-                    "[File size > {:.1}MB (max: {:.1}MB)]",
-                    file_size as f64 / 1_000_000.0,
-                    max_file_size as f64 / 1_000_000.0
-                )),
-                is_binary: false,
-            });
+            return Some(oversize_file(
+                &full_path,
+                relative,
+                file_size,
+                max_file_size,
+            ));
         }
 
         // Otherwise, read the file content (or detect if it's binary)
         let data = fs::read(&full_path).unwrap_or_default();
-        let is_bin = is_binary(&data);
-
-        let content = if is_bin {
-            None
-        } else {
-            Some(truncate_text(
-                String::from_utf8_lossy(&data).to_string(),
-                10_000_000,
-            ))
-        };
-
-        return Some(ScannedFile {
-            path: relative.to_string_lossy().to_string(),
-            content,
-            is_binary: is_bin,
-        });
+        return Some(file_from_bytes(relative, &data, max_file_size));
     }
 
     None
 }
 
-fn is_binary(data: &[u8]) -> bool {
-    data.iter().any(|&b| b == 0)
+/// A file over `max_file_size`: binaries are still described from a bounded
+/// read of their header, text gets the size placeholder.
+pub(crate) fn oversize_file(
+    full_path: &Path,
+    relative: &Path,
+    file_size: u64,
+    max_file_size: u64,
+) -> ScannedFile {
+    match read_head(full_path) {
+        Ok(head) if is_binary(&head) => {
+            let path = slash_path(relative);
+            ScannedFile {
+                content: Some(describe_binary_file(&path, full_path, &head, file_size)),
+                path,
+                is_binary: true,
+                line_origins: None,
+            }
+        }
+        _ => size_placeholder(relative, file_size, max_file_size),
+    }
+}
+
+pub(crate) fn size_placeholder(relative: &Path, file_size: u64, max_file_size: u64) -> ScannedFile {
+    ScannedFile {
+        path: slash_path(relative),
+        content: Some(format!(
+            "{}{:.1}MB (max: {:.1}MB)]",
+            SIZE_PLACEHOLDER_PREFIX,
+            file_size as f64 / 1_000_000.0,
+            max_file_size as f64 / 1_000_000.0
+        )),
+        is_binary: false,
+        line_origins: Some(vec![LineSpan::Marker]),
+    }
 }
 
-fn truncate_text(text: String, max_len: usize) -> String {
-    if text.len() > max_len {
-        format!("{}\n[Truncated: File too large]", &text[..max_len])
+/// Text is capped at `max_file_size` bytes too: a file may have grown since
+/// its size was checked, and invalid UTF-8 grows when replaced.
+pub(crate) fn file_from_bytes(relative: &Path, data: &[u8], max_file_size: u64) -> ScannedFile {
+    let is_bin = is_binary(data);
+
+    let (content, line_origins) = if is_bin {
+        (describe_binary(&slash_path(relative), data), None)
     } else {
-        text
+        let max_len = usize::try_from(max_file_size).unwrap_or(usize::MAX);
+        let text = String::from_utf8_lossy(data).to_string();
+        let cut = text.len() > max_len;
+        let text = truncate_text(text, max_len);
+        // All but the last line, the truncation marker, are from the file
+        let origins = cut.then(|| cut_origins(text.lines().count() - 1));
+        (text, origins)
+    };
+
+    ScannedFile {
+        path: slash_path(relative),
+        content: Some(content),
+        is_binary: is_bin,
+        line_origins,
+    }
+}
+
+pub(crate) fn is_binary(data: &[u8]) -> bool {
+    data.contains(&0)
+}
+
+/// Cuts `text` to at most `max_len` bytes (plus the [`TRUNCATION_MARKER`]
+/// line). The cut goes after the last complete line that fits, or, if not even
+/// the first line fits, at the last character boundary, so multi-byte
+/// characters are never split.
+pub fn truncate_text(mut text: String, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text;
+    }
+
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    if let Some(newline) = text[..end].rfind('\n') {
+        end = newline;
+    }
+
+    text.truncate(end);
+    if !text.is_empty() {
+        text.push('\n');
     }
+    text.push_str(TRUNCATION_MARKER);
+    text
 }
@@ -0,0 +1,74 @@
+use crate::unpack::section_spans;
+use regex::RegexBuilder;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Matches beyond this many are not collected, so a one-letter search in a
+/// large bundle stays responsive.
+pub const MAX_MATCHES: usize = 10_000;
+
+/// How [`find_matches`] treats the query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    /// Treat the query as a regular expression instead of plain text.
+    pub regex: bool,
+}
+
+/// Byte ranges of the non-empty matches of `query` in `text`, at most
+/// [`MAX_MATCHES`] of them. Fails if `query` is not a valid regular
+/// expression.
+pub fn find_matches(
+    text: &str,
+    query: &str,
+    options: SearchOptions,
+) -> Result<Vec<Range<usize>>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    Ok(regex
+        .find_iter(text)
+        .map(|m| m.range())
+        .filter(|range| !range.is_empty())
+        .take(MAX_MATCHES)
+        .collect())
+}
+
+/// One line of the file tree at the top of a bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeAnchor {
+    /// The tree line as drawn, e.g. `│   └── main.rs`.
+    pub line: String,
+    pub path: String,
+    /// Byte offset of the file's `path:` header in the bundle; `None` for
+    /// folders and files without a section.
+    pub offset: Option<usize>,
+}
+
+/// Pairs the entries returned by [`crate::formatter::build_tree`] with the
+/// position of their section in `bundle`, for jumping to a file.
+pub fn tree_anchors(bundle: &str, tree: &[(String, String)]) -> Vec<TreeAnchor> {
+    let offsets: HashMap<String, usize> = section_spans(bundle)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|span| (span.path, span.header))
+        .collect();
+
+    tree.iter()
+        .map(|(line, path)| TreeAnchor {
+            line: line.clone(),
+            path: path.clone(),
+            offset: offsets.get(path).copied(),
+        })
+        .collect()
+}
@@ -0,0 +1,474 @@
+use crate::archive::is_archive;
+use crate::bundle::{select_files, write_bundle, BundleOptions};
+use crate::graph::{GraphExportOptions, GraphFormat};
+use crate::manifest::ManifestOptions;
+use crate::order::FileOrder;
+use crate::relevance::Budget;
+use crate::roots::{scan_roots, DiskFiles, ProjectRoot};
+use crate::sample::SampleRule;
+use crate::scanner::ScannedFile;
+use crate::stream::{can_stream, write_roots_bundle};
+use crate::truncate::TruncateRule;
+use serde::Deserialize;
+use std::io::{self, BufWriter, Cursor, Read, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+/// Largest request body accepted, in bytes.
+const MAX_REQUEST_BYTES: u64 = 1_000_000;
+
+/// Size of the chunks a bundle is sent in, and how many may wait for a slow
+/// client before generation pauses.
+const CHUNK_BYTES: usize = 64 * 1024;
+const QUEUED_CHUNKS: usize = 16;
+
+/// Requests answered at the same time; further ones wait for a free worker.
+const WORKERS: usize = 8;
+
+/// Names a request may address the server by. A web page whose domain was
+/// rebound to 127.0.0.1 still sends its own name, so checking the `Host`
+/// (and `Origin`) keeps pages from reading bundles.
+const LOCAL_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+/// What a [`BundleServer`] may read and how.
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    /// Folders (and archives) requests may name, or anything inside them.
+    pub allowed_roots: Vec<PathBuf>,
+    /// Default for requests that do not set `max_size`, and its upper
+    /// limit.
+    pub max_file_size: u64,
+    pub use_cache: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            allowed_roots: Vec::new(),
+            max_file_size: 1_000_000,
+            use_cache: true,
+        }
+    }
+}
+
+/// Body of `POST /bundle`. Every field is optional; `roots` defaults to the
+/// first allowed root and relative roots are resolved against it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BundleRequest {
+    pub roots: Vec<String>,
+    /// Gitignore-style patterns applied to every root.
+    pub ignore: Vec<String>,
+    pub max_size: Option<u64>,
+    pub line_numbers: bool,
+    pub query: Option<String>,
+    pub budget_tokens: Option<usize>,
+    pub budget_bytes: Option<usize>,
+    pub with_deps: Option<String>,
+    pub dependency_order: bool,
+    /// `alphabetical`, `dirs-first` or `dependencies`.
+    pub order: Option<String>,
+    /// Patterns of files to write first.
+    pub priority: Vec<String>,
+    /// Append the file manifest section.
+    pub manifest: bool,
+    pub manifest_hash: bool,
+    /// `dot` or `mermaid`.
+    pub graph: Option<String>,
+    pub truncate: Vec<String>,
+    pub sample: Vec<String>,
+    pub sample_data: bool,
+    pub notebook_outputs: bool,
+    pub raw_notebooks: bool,
+    pub archives: bool,
+}
+
+/// A request that cannot be served, with the HTTP status to answer.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Serves bundles over HTTP:
+///
+/// - `GET /roots` lists the allowed roots as a JSON array.
+/// - `POST /bundle` takes a JSON [`BundleRequest`] and answers with the
+///   bundle text, sent with chunked transfer encoding while it is generated.
+///
+/// Roots outside the allow-list are refused with `403 Forbidden`; symlinks
+/// are resolved before the check. So are requests whose `Host` or `Origin`
+/// is not the local machine at the bound port.
+pub struct BundleServer {
+    server: Server,
+    config: Arc<ServerConfig>,
+}
+
+impl BundleServer {
+    /// Binds to `address` (`127.0.0.1:0` picks a free port). The allowed
+    /// roots must exist.
+    pub fn bind(address: &str, config: ServerConfig) -> Result<Self, String> {
+        if config.allowed_roots.is_empty() {
+            return Err("the server needs at least one allowed root".to_string());
+        }
+        let allowed_roots = config
+            .allowed_roots
+            .iter()
+            .map(|root| {
+                root.canonicalize()
+                    .map_err(|e| format!("cannot allow {}: {}", root.display(), e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let server =
+            Server::http(address).map_err(|e| format!("failed to listen on {}: {}", address, e))?;
+
+        Ok(Self {
+            server,
+            config: Arc::new(ServerConfig {
+                allowed_roots,
+                ..config
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Answers requests until the process ends, [`WORKERS`] at a time.
+    pub fn run(&self) {
+        let port = self.local_addr().map_or(0, |address| address.port());
+        thread::scope(|scope| {
+            for _ in 0..WORKERS {
+                scope.spawn(|| {
+                    for request in self.server.incoming_requests() {
+                        respond(request, &self.config, port);
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// The body of a response and its content type.
+type Reply = (&'static str, Box<dyn Read + Send>);
+
+fn respond(mut request: Request, config: &ServerConfig, port: u16) {
+    let result = check_host(&request, port).and_then(|()| route(&mut request, config));
+
+    let (status, content_type, body) = match result {
+        Ok((content_type, body)) => (200, content_type, body),
+        Err(error) => {
+            let message = format!("{}\n", error.message);
+            let body: Box<dyn Read + Send> = Box::new(Cursor::new(message.into_bytes()));
+            (error.status, "text/plain; charset=utf-8", body)
+        }
+    };
+    // No length is given, so tiny_http streams the body in chunks
+    let response = Response::new(
+        StatusCode(status),
+        vec![Header::from_bytes("Content-Type", content_type).unwrap()],
+        body,
+        None,
+        None,
+    );
+    // The client may already have gone away
+    let _ = request.respond(response);
+}
+
+/// Refuses requests not addressed to the local machine at `port`.
+fn check_host(request: &Request, port: u16) -> Result<(), ApiError> {
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str())
+    };
+    let host = header("Host").is_some_and(|host| is_local_host(host, port));
+    let origin = header("Origin").is_none_or(|origin| {
+        origin
+            .strip_prefix("http://")
+            .is_some_and(|host| is_local_host(host, port))
+    });
+    if host && origin {
+        Ok(())
+    } else {
+        Err(ApiError::new(403, "only requests to localhost are served"))
+    }
+}
+
+/// `localhost`, `127.0.0.1` or `[::1]`, with `port` or no port.
+fn is_local_host(host: &str, port: u16) -> bool {
+    let (name, host_port) = match host.rsplit_once(':') {
+        // The colons of a bare `[::1]` are not a port separator
+        Some((name, host_port)) if !host_port.contains(']') => (name, Some(host_port)),
+        _ => (host, None),
+    };
+    LOCAL_HOSTS.contains(&name) && host_port.is_none_or(|host_port| host_port == port.to_string())
+}
+
+fn route(request: &mut Request, config: &ServerConfig) -> Result<Reply, ApiError> {
+    match (request.method(), request.url()) {
+        (Method::Get, "/roots") => {
+            let roots: Vec<String> = config
+                .allowed_roots
+                .iter()
+                .map(|root| root.to_string_lossy().to_string())
+                .collect();
+            let json = serde_json::to_string(&roots).unwrap_or_default();
+            Ok(("application/json", Box::new(Cursor::new(json.into_bytes()))))
+        }
+        (Method::Post, "/bundle") => read_body(request)
+            .and_then(|body| {
+                serde_json::from_str::<BundleRequest>(&body)
+                    .map_err(|e| ApiError::new(400, format!("invalid request: {}", e)))
+            })
+            .and_then(|body| prepare_bundle(&body, config))
+            .map(|bundle| {
+                let (writer, reader) = pipe();
+                thread::spawn(move || {
+                    let mut out = BufWriter::with_capacity(CHUNK_BYTES, writer);
+                    // A failed write means the client went away
+                    let _ = bundle.write(&mut out).and_then(|_| out.flush());
+                });
+                let body: Box<dyn Read + Send> = Box::new(reader);
+                ("text/plain; charset=utf-8", body)
+            }),
+        (_, "/roots" | "/bundle") => Err(ApiError::new(405, "method not allowed")),
+        _ => Err(ApiError::new(404, "not found")),
+    }
+}
+
+/// A bounded in-memory pipe: the writer blocks while [`QUEUED_CHUNKS`]
+/// writes wait to be read, and the reader sees the end once the writer is
+/// dropped.
+fn pipe() -> (PipeWriter, PipeReader) {
+    let (sender, receiver) = sync_channel(QUEUED_CHUNKS);
+    (
+        PipeWriter(sender),
+        PipeReader {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        },
+    )
+}
+
+struct PipeWriter(SyncSender<Vec<u8>>);
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct PipeReader {
+    receiver: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+fn read_body(request: &mut Request) -> Result<String, ApiError> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_REQUEST_BYTES + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| ApiError::new(400, format!("failed to read request: {}", e)))?;
+    if body.len() as u64 > MAX_REQUEST_BYTES {
+        return Err(ApiError::new(413, "request too large"));
+    }
+    Ok(body)
+}
+
+/// A checked request, ready to be written. Bundles that cannot be streamed
+/// are scanned up front, so read errors are reported before the response
+/// starts and only the formatting is left.
+struct PreparedBundle {
+    roots: Vec<ProjectRoot>,
+    options: BundleOptions,
+    max_file_size: u64,
+    use_cache: bool,
+    files: Option<Vec<ScannedFile>>,
+}
+
+impl PreparedBundle {
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        match &self.files {
+            Some(files) => write_bundle(out, files, &self.options),
+            None => write_roots_bundle(
+                out,
+                &self.roots,
+                self.max_file_size,
+                self.use_cache,
+                &self.options,
+            ),
+        }
+    }
+}
+
+/// Checks the request against the allow-list and renders its bundle.
+pub(crate) fn build_bundle(
+    request: &BundleRequest,
+    config: &ServerConfig,
+) -> Result<String, ApiError> {
+    let bundle = prepare_bundle(request, config)?;
+    let mut output = Vec::new();
+    bundle
+        .write(&mut output)
+        .map_err(|e| ApiError::new(500, format!("failed to render bundle: {}", e)))?;
+    Ok(String::from_utf8_lossy(&output).to_string())
+}
+
+/// Checks the request against the allow-list and turns it into options.
+fn prepare_bundle(
+    request: &BundleRequest,
+    config: &ServerConfig,
+) -> Result<PreparedBundle, ApiError> {
+    let invalid = |message: String| ApiError::new(400, message);
+
+    let requested = if request.roots.is_empty() {
+        vec![".".to_string()]
+    } else {
+        request.roots.clone()
+    };
+    let mut roots = Vec::new();
+    for path in &requested {
+        let mut root = ProjectRoot::new(resolve_root(path, &config.allowed_roots)?);
+        root.ignore_patterns = request.ignore.clone();
+        root.expand_archives = request.archives;
+        roots.push(root);
+    }
+
+    let mut options = BundleOptions::default();
+    options.format.line_numbers = request.line_numbers;
+    options.query = request.query.clone();
+    options.budget = match (request.budget_tokens, request.budget_bytes) {
+        (Some(tokens), _) => Some(Budget::Tokens(tokens)),
+        (None, Some(bytes)) => Some(Budget::Bytes(bytes)),
+        (None, None) => None,
+    };
+    options.include_with_deps = request.with_deps.clone();
+    options.order = match request.order.as_deref() {
+        Some(order) => FileOrder::parse(order).map_err(invalid)?,
+        None if request.dependency_order => FileOrder::Dependencies,
+        None => FileOrder::Alphabetical,
+    };
+    options.priority = request.priority.clone();
+    options.manifest = request.manifest.then(|| ManifestOptions {
+        roots: roots.clone(),
+        section: true,
+        sidecar: None,
+    });
+    options.manifest_hash = request.manifest_hash;
+    options.graph = match request.graph.as_deref() {
+        None => None,
+        Some(format) => Some(GraphExportOptions {
+            format: match format {
+                "dot" => GraphFormat::Dot,
+                "mermaid" => GraphFormat::Mermaid,
+                other => return Err(invalid(format!("unknown graph format '{}'", other))),
+            },
+            ..Default::default()
+        }),
+    };
+    for rule in &request.truncate {
+        options
+            .truncation
+            .push(TruncateRule::parse(rule).map_err(invalid)?);
+    }
+    if request.sample_data {
+        options.sampling.extend(SampleRule::defaults());
+    }
+    for rule in &request.sample {
+        options
+            .sampling
+            .push(SampleRule::parse(rule).map_err(invalid)?);
+    }
+    options.notebooks.include_outputs = request.notebook_outputs;
+    options.notebooks.extract = !request.raw_notebooks;
+
+    let max_file_size = request
+        .max_size
+        .map_or(config.max_file_size, |size| size.min(config.max_file_size));
+    options.disk = Some(DiskFiles::new(&roots, max_file_size));
+
+    let files = if can_stream(&roots, &options) {
+        None
+    } else {
+        let files = scan_roots(&roots, max_file_size, config.use_cache)
+            .map_err(|message| ApiError::new(500, message))?;
+        Some(select_files(files, &options))
+    };
+    Ok(PreparedBundle {
+        roots,
+        options,
+        max_file_size,
+        use_cache: config.use_cache,
+        files,
+    })
+}
+
+/// Resolves `path` (relative to the first allowed root) and checks that it
+/// lies inside one of the `allowed` canonical roots.
+fn resolve_root(path: &str, allowed: &[PathBuf]) -> Result<PathBuf, ApiError> {
+    let joined = match allowed.first() {
+        Some(first) => first.join(path),
+        None => PathBuf::from(path),
+    };
+    let resolved = joined
+        .canonicalize()
+        .map_err(|_| ApiError::new(404, format!("no such folder: {}", path)))?;
+    if !allowed.iter().any(|root| resolved.starts_with(root)) {
+        return Err(ApiError::new(
+            403,
+            format!("{} is outside the allowed roots", path),
+        ));
+    }
+    if !resolved.is_dir() && !is_archive(&resolved) {
+        return Err(ApiError::new(
+            400,
+            format!("{} is not a folder or archive", path),
+        ));
+    }
+    Ok(resolved)
+}
@@ -0,0 +1,119 @@
+use crate::bundle::{
+    select_files, write_bundle, write_manifest, write_with_manifest_hash, BundleOptions,
+};
+use crate::formatter::{build_tree, write_file_section, write_tree};
+use crate::order::{order_paths, FileOrder};
+use crate::roots::{scan_roots, unique_aliases, ProjectRoot};
+use crate::scanner::{
+    canonical_root, file_from_bytes, scan_file, slash_path, walk_files, ScannedFile,
+};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Whether the bundle of `roots` can be written one file at a time: every
+/// root is a folder without archive expansion and no option needs to see all
+/// files at once (a query, a dependency filter, dependency order or the
+/// graph).
+pub fn can_stream(roots: &[ProjectRoot], options: &BundleOptions) -> bool {
+    roots
+        .iter()
+        .all(|root| root.path.is_dir() && !root.expand_archives)
+        && options.query.is_none()
+        && options.include_with_deps.is_none()
+        && options.order != FileOrder::Dependencies
+        && options.graph.is_none()
+}
+
+/// Scans `roots` and writes their bundle to `out`. When [`can_stream`]
+/// allows it, only the paths are collected up front and each file is read,
+/// transformed and written on its own, so memory stays bounded by the path
+/// list and the largest file. Otherwise the files are scanned and selected
+/// as usual before writing, and an archive root that cannot be read fails
+/// before anything is written. Either way the output is the same.
+pub fn write_roots_bundle(
+    out: &mut dyn Write,
+    roots: &[ProjectRoot],
+    max_file_size: u64,
+    use_cache: bool,
+    options: &BundleOptions,
+) -> io::Result<()> {
+    if can_stream(roots, options) {
+        return write_streamed_bundle(out, roots, max_file_size, options);
+    }
+    let files = scan_roots(roots, max_file_size, use_cache).map_err(io::Error::other)?;
+    let files = select_files(files, options);
+    write_bundle(out, &files, options)
+}
+
+/// One file to read: its bundle path, the root it lives in and its path
+/// relative to that root.
+struct PendingFile {
+    path: String,
+    root: PathBuf,
+    relative: PathBuf,
+}
+
+fn write_streamed_bundle(
+    out: &mut dyn Write,
+    roots: &[ProjectRoot],
+    max_file_size: u64,
+    options: &BundleOptions,
+) -> io::Result<()> {
+    let mut roots = roots.to_vec();
+    unique_aliases(&mut roots);
+    let prefix = roots.len() > 1;
+
+    let mut pending = BTreeMap::new();
+    for root in &roots {
+        let root_path = canonical_root(&root.path.to_string_lossy());
+        for abs_path in walk_files(&root_path, &root.ignore_patterns) {
+            let Ok(relative) = abs_path.strip_prefix(&root_path) else {
+                continue;
+            };
+            let path = if prefix {
+                format!("{}/{}", root.alias, slash_path(relative))
+            } else {
+                slash_path(relative)
+            };
+            pending.insert(
+                path.clone(),
+                PendingFile {
+                    path,
+                    root: root_path.clone(),
+                    relative: relative.to_path_buf(),
+                },
+            );
+        }
+    }
+    let paths: Vec<String> = pending.keys().cloned().collect();
+    let order = order_paths(&paths, options.order, &options.priority, None);
+
+    write_with_manifest_hash(out, options.manifest_hash, |out| {
+        let listed: Vec<ScannedFile> = paths
+            .into_iter()
+            .map(|path| ScannedFile {
+                path,
+                content: None,
+                is_binary: false,
+                line_origins: None,
+            })
+            .collect();
+        write_tree(out, &build_tree(&listed))?;
+        drop(listed);
+
+        for path in &order {
+            let file = &pending[path];
+            // The tree already lists a file deleted since the walk, so it is
+            // written empty, like a file the scan fails to read
+            let mut scanned = scan_file(&file.root, &file.relative, max_file_size)
+                .unwrap_or_else(|| file_from_bytes(&file.relative, &[], max_file_size));
+            scanned.path = file.path.clone();
+            // Only per-file transforms are left for `select_files` to apply
+            for selected in select_files(vec![scanned], options) {
+                write_file_section(out, &selected, &options.format)?;
+            }
+        }
+        write_manifest(out, options.manifest.as_ref(), &order)
+    })
+}
@@ -0,0 +1,357 @@
+use crate::roots::DiskFiles;
+use crate::scanner::{LineSpan, ScannedFile, TRUNCATION_MARKER};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+/// Start of the line that replaces lines left out by a [`TruncateStrategy`],
+/// e.g. `[Lines 41-180 of 500 omitted]`.
+pub const ELISION_MARKER_PREFIX: &str = "[Lines ";
+
+/// Which lines of a file to keep.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TruncateStrategy {
+    /// The first N lines.
+    Head(usize),
+    /// The last N lines.
+    Tail(usize),
+    /// The first `head` and the last `tail` lines.
+    HeadTail { head: usize, tail: usize },
+    /// Inclusive, 1-based line ranges.
+    Ranges(Vec<(usize, usize)>),
+}
+
+impl TruncateStrategy {
+    /// Parses `head=N`, `tail=N`, `head=N,tail=M` or line ranges such as
+    /// `100-220` and `1-30,100-220` (a single number keeps one line).
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let number = |text: &str| {
+            text.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid line count '{}' in '{}'", text, spec))
+        };
+
+        let mut head = None;
+        let mut tail = None;
+        let mut ranges = Vec::new();
+        for part in spec.split(',').map(str::trim) {
+            if let Some(n) = part.strip_prefix("head=") {
+                head = Some(number(n)?);
+            } else if let Some(n) = part.strip_prefix("tail=") {
+                tail = Some(number(n)?);
+            } else {
+                let (start, end) = part.split_once('-').unwrap_or((part, part));
+                let (start, end) = (number(start)?, number(end)?);
+                if start == 0 || end < start {
+                    return Err(format!("invalid line range '{}'", part));
+                }
+                ranges.push((start, end));
+            }
+        }
+
+        match (head, tail, ranges.is_empty()) {
+            (Some(head), None, true) => Ok(Self::Head(head)),
+            (None, Some(tail), true) => Ok(Self::Tail(tail)),
+            (Some(head), Some(tail), true) => Ok(Self::HeadTail { head, tail }),
+            (None, None, false) => Ok(Self::Ranges(ranges)),
+            _ => Err(format!(
+                "cannot mix head/tail and line ranges in '{}'",
+                spec
+            )),
+        }
+    }
+
+    /// The inclusive line ranges kept from a text of `total` lines, sorted
+    /// and merged.
+    fn kept_ranges(&self, total: usize) -> Vec<(usize, usize)> {
+        let mut ranges = match self {
+            Self::Head(n) => vec![(1, *n)],
+            Self::Tail(n) => vec![(total.saturating_sub(*n) + 1, total)],
+            Self::HeadTail { head, tail } => {
+                vec![(1, *head), (total.saturating_sub(*tail) + 1, total)]
+            }
+            Self::Ranges(ranges) => ranges.clone(),
+        };
+        ranges.retain(|&(start, end)| start <= end && start <= total);
+        ranges.sort();
+
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in ranges {
+            let end = end.min(total);
+            match merged.last_mut() {
+                Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// Keeps the selected lines of `text`. Every gap is replaced by one
+    /// elision marker stating the omitted range.
+    pub fn apply(&self, text: &str) -> String {
+        self.apply_with_origins(text).0
+    }
+
+    /// Like [`Self::apply`], also returning where each kept line comes from,
+    /// so line numbering can pick up at the right line after a gap.
+    pub fn apply_with_origins(&self, text: &str) -> (String, Vec<LineSpan>) {
+        let lines: Vec<&str> = text.lines().collect();
+        self.keep(lines.len(), text.ends_with('\n'), |n| {
+            Some(lines[n - 1].to_string())
+        })
+    }
+
+    /// Like [`Self::apply_with_origins`] for a file too large to load. The
+    /// file is read twice, once to count its lines, and at most `limit` bytes
+    /// of kept lines are held; the truncation marker stands in for the rest.
+    pub fn apply_file(&self, file: &mut File, limit: usize) -> io::Result<(String, Vec<LineSpan>)> {
+        let (total, ends_with_newline) = count_lines(&mut *file)?;
+        file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(file);
+        let mut read = 0;
+        let mut budget = limit;
+        let mut error = None;
+
+        let result = self.keep(total, ends_with_newline, |n| {
+            match read_nth_line(&mut reader, &mut read, n, budget.saturating_add(1)) {
+                Ok(Some(line)) if line.len() <= budget => {
+                    budget -= line.len();
+                    Some(line)
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    error = Some(e);
+                    None
+                }
+            }
+        });
+        error.map_or(Ok(result), Err)
+    }
+
+    /// Joins the kept lines of a text of `total` lines, taking each from
+    /// `line` by its 1-based number in ascending order. `line` returning
+    /// `None` ends the text with the truncation marker.
+    fn keep(
+        &self,
+        total: usize,
+        ends_with_newline: bool,
+        mut line: impl FnMut(usize) -> Option<String>,
+    ) -> (String, Vec<LineSpan>) {
+        let mut output = Vec::new();
+        let mut origins = Vec::new();
+        let mut next = 1;
+        for (start, end) in self.kept_ranges(total) {
+            if start > next {
+                output.push(elision_marker(next, start - 1, total));
+                origins.push(LineSpan::Marker);
+            }
+            origins.push(LineSpan::Lines {
+                first: start,
+                count: 0,
+            });
+            for n in start..=end {
+                let Some(line) = line(n) else {
+                    output.push(TRUNCATION_MARKER.to_string());
+                    origins.push(LineSpan::Marker);
+                    return (output.join("\n"), origins);
+                };
+                output.push(line);
+                if let Some(LineSpan::Lines { count, .. }) = origins.last_mut() {
+                    *count += 1;
+                }
+            }
+            next = end + 1;
+        }
+        if next <= total {
+            output.push(elision_marker(next, total, total));
+            origins.push(LineSpan::Marker);
+        }
+
+        let mut result = output.join("\n");
+        if ends_with_newline && next > total && total > 0 {
+            result.push('\n');
+        }
+        (result, origins)
+    }
+}
+
+/// The number of lines of `reader` as [`str::lines`] counts them, and whether
+/// the last one ends with a newline.
+fn count_lines(reader: impl Read) -> io::Result<(usize, bool)> {
+    let mut reader = BufReader::new(reader);
+    let mut total = 0;
+    let mut last = None;
+    loop {
+        let buffer = reader.fill_buf()?;
+        let Some(&byte) = buffer.last() else {
+            break;
+        };
+        total += buffer.iter().filter(|&&b| b == b'\n').count();
+        last = Some(byte);
+        let length = buffer.len();
+        reader.consume(length);
+    }
+    let ends_with_newline = last == Some(b'\n');
+    if last.is_some() && !ends_with_newline {
+        total += 1;
+    }
+    Ok((total, ends_with_newline))
+}
+
+/// Skips to line `n` (1-based) of `reader`, of which `read` lines were
+/// already read, and reads it with [`read_line_bounded`].
+fn read_nth_line(
+    reader: &mut impl BufRead,
+    read: &mut usize,
+    n: usize,
+    max: usize,
+) -> io::Result<Option<String>> {
+    while *read + 1 < n {
+        read_line_bounded(reader, 0)?;
+        *read += 1;
+    }
+    *read = n;
+    read_line_bounded(reader, max)
+}
+
+/// Reads the next line of `reader` without its line ending, keeping at most
+/// `max` bytes of it and skipping the rest. `None` at the end of the input.
+pub(crate) fn read_line_bounded(
+    reader: &mut impl BufRead,
+    max: usize,
+) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let mut found = false;
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        found = true;
+        let (chunk, used, done) = match buffer.iter().position(|&b| b == b'\n') {
+            Some(end) => (&buffer[..end], end + 1, true),
+            None => (buffer, buffer.len(), false),
+        };
+        let room = max.saturating_sub(line.len());
+        line.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        reader.consume(used);
+        if done {
+            break;
+        }
+    }
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+    Ok(found.then(|| String::from_utf8_lossy(&line).into_owned()))
+}
+
+fn elision_marker(start: usize, end: usize, total: usize) -> String {
+    format!(
+        "{}{}-{} of {} omitted]",
+        ELISION_MARKER_PREFIX, start, end, total
+    )
+}
+
+/// The last omitted line number if `line` is an elision marker.
+pub fn elided_range_end(line: &str) -> Option<usize> {
+    let rest = line.strip_prefix(ELISION_MARKER_PREFIX)?;
+    let (range, _) = rest.split_once(' ')?;
+    let (_, end) = range.split_once('-')?;
+    end.parse().ok()
+}
+
+/// A strategy applied to every file matching a gitignore-style pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruncateRule {
+    pub pattern: String,
+    pub strategy: TruncateStrategy,
+}
+
+impl TruncateRule {
+    /// Parses `PATTERN:SPEC`, e.g. `*.log:tail=200` or `src/app.rs:100-220`.
+    /// See [`TruncateStrategy::parse`] for the accepted specs.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let (pattern, spec) = rule
+            .trim()
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected PATTERN:SPEC, got '{}'", rule))?;
+        if pattern.is_empty() {
+            return Err(format!("missing file pattern in '{}'", rule));
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            strategy: TruncateStrategy::parse(spec)?,
+        })
+    }
+
+    fn matcher(&self) -> Option<Gitignore> {
+        let mut builder = GitignoreBuilder::new("");
+        builder.add_line(None, &self.pattern).ok()?;
+        builder.build().ok()
+    }
+}
+
+/// Applies the last matching rule to each text file. Binary and empty files
+/// are left as they are. Files the scan cut short are read from `disk` when
+/// given; otherwise a size placeholder is kept and cut text has the rule
+/// applied to the lines that were kept.
+pub fn apply_truncation(
+    files: Vec<ScannedFile>,
+    rules: &[TruncateRule],
+    disk: Option<&DiskFiles>,
+) -> Vec<ScannedFile> {
+    if rules.is_empty() {
+        return files;
+    }
+    let matchers: Vec<(Option<Gitignore>, &TruncateStrategy)> = rules
+        .iter()
+        .map(|rule| (rule.matcher(), &rule.strategy))
+        .collect();
+
+    files
+        .into_iter()
+        .map(|mut file| {
+            if file.is_binary {
+                return file;
+            }
+            let strategy = matchers.iter().rev().find_map(|(matcher, strategy)| {
+                matcher
+                    .as_ref()?
+                    .matched(&file.path, false)
+                    .is_ignore()
+                    .then_some(*strategy)
+            });
+            let (Some(strategy), Some(content)) = (strategy, &file.content) else {
+                return file;
+            };
+            if !file.is_cut() {
+                let (content, origins) = strategy.apply_with_origins(content);
+                file.content = Some(content);
+                file.line_origins = Some(origins);
+                return file;
+            }
+
+            // The rule is for the whole file, so read it again where possible
+            let from_disk = disk.and_then(|disk| {
+                let mut opened = disk.open(&file.path)?;
+                strategy.apply_file(&mut opened, disk.limit()).ok()
+            });
+            if let Some((content, origins)) = from_disk {
+                file.content = Some(content);
+                file.line_origins = Some(origins);
+            } else if let Some((kept, _)) = content.rsplit_once('\n') {
+                // Otherwise it applies to the lines kept before the scan's marker
+                let (mut content, mut origins) =
+                    strategy.apply_with_origins(&format!("{}\n", kept));
+                if !content.is_empty() && !content.ends_with('\n') {
+                    content.push('\n');
+                }
+                content.push_str(TRUNCATION_MARKER);
+                origins.push(LineSpan::Marker);
+                file.content = Some(content);
+                file.line_origins = Some(origins);
+            }
+            file
+        })
+        .collect()
+}
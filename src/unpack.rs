@@ -0,0 +1,375 @@
+use crate::archive::TRUNCATED_ARCHIVE_PREFIX;
+use crate::bundle::MANIFEST_HASH_PREFIX;
+use crate::filelist::RejectedPath;
+use crate::manifest::MANIFEST_HEADER;
+use crate::order::{order_paths, FileOrder};
+use crate::scanner::{SIZE_PLACEHOLDER_PREFIX, TRUNCATION_MARKER};
+use crate::truncate::elided_range_end;
+use crate::utils::resolve_inside;
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// Header of the dependency graph section that may follow the files.
+const GRAPH_HEADER: &str = "Dependency graph:\n";
+
+/// One file section of a bundle, its body as written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleSection {
+    pub path: String,
+    pub body: String,
+}
+
+/// Where one file section is in a bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionSpan {
+    pub path: String,
+    /// Byte offset of the `path:` header line.
+    pub header: usize,
+    /// The body, without the blank line ending the section.
+    pub body: Range<usize>,
+}
+
+/// What [`unpack_bundle`] did.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UnpackReport {
+    /// Paths written, relative to the target folder.
+    pub written: Vec<String>,
+    /// Sections that were not written, with the reason.
+    pub skipped: Vec<RejectedPath>,
+}
+
+/// Splits a bundle in contextor's text format into its file sections.
+pub fn parse_bundle(bundle: &str) -> Result<Vec<BundleSection>, String> {
+    Ok(section_spans(bundle)?
+        .into_iter()
+        .map(|span| BundleSection {
+            body: bundle[span.body].to_string(),
+            path: span.path,
+        })
+        .collect())
+}
+
+/// Finds the file sections of a bundle, in order.
+///
+/// The tree at the top tells which paths have a section, so a body line that
+/// merely looks like a `path:` header is not mistaken for one. When the
+/// sections follow an order the tree alone determines (see
+/// [`match_in_order`]), even a body line naming a file whose section comes
+/// later is told apart. The graph, manifest and manifest hash after the files
+/// are left out.
+pub fn section_spans(bundle: &str) -> Result<Vec<SectionSpan>, String> {
+    let (tree, _) = bundle
+        .split_once("\n\n")
+        .ok_or("not a contextor bundle: no file tree")?;
+    let paths = parse_tree(tree).ok_or("not a contextor bundle: no file tree")?;
+
+    let start = tree.len() + 2;
+    let end = start + sections_end(&bundle[start..]);
+    // Every line at a section start naming a listed file, with its body start
+    let mut candidates: BTreeMap<&str, Vec<(usize, usize)>> = paths
+        .iter()
+        .map(|path| (path.as_str(), Vec::new()))
+        .collect();
+    let mut offset = start;
+    for line in bundle[start..end].split_inclusive('\n') {
+        let at_section_start = offset == start || bundle[..offset].ends_with("\n\n");
+        if let Some(path) = line.strip_suffix(":\n").filter(|_| at_section_start) {
+            if let Some(found) = candidates.get_mut(path) {
+                found.push((offset, offset + line.len()));
+            }
+        }
+        offset += line.len();
+    }
+
+    let headers = FileOrder::ALL
+        .iter()
+        .find_map(|&order| {
+            let order = order_paths(&paths, order, &[], None);
+            match_in_order(&order, &candidates, start, end)
+        })
+        .unwrap_or_else(|| first_headers(&candidates));
+
+    Ok(headers
+        .iter()
+        .enumerate()
+        .map(|(i, &(header, body_start, path))| {
+            let body_end = headers.get(i + 1).map_or(end, |next| next.0);
+            let body = &bundle[body_start..body_end];
+            SectionSpan {
+                path: path.to_string(),
+                header,
+                body: body_start..body_start + body.strip_suffix("\n\n").unwrap_or(body).len(),
+            }
+        })
+        .collect())
+}
+
+/// Headers of sections written in `order`, one per file, or `None` if the
+/// sections between `start` and `end` do not follow it. They are matched from
+/// the back: each file takes its last candidate before the next file's
+/// header, so lines in earlier bodies that look like later headers are
+/// skipped.
+fn match_in_order<'a>(
+    order: &[String],
+    candidates: &BTreeMap<&'a str, Vec<(usize, usize)>>,
+    start: usize,
+    end: usize,
+) -> Option<Vec<(usize, usize, &'a str)>> {
+    let mut headers = Vec::with_capacity(order.len());
+    let mut limit = end;
+    for path in order.iter().rev() {
+        let (&path, found) = candidates.get_key_value(path.as_str())?;
+        let &(header, body_start) = found.iter().rev().find(|(header, _)| *header < limit)?;
+        headers.push((header, body_start, path));
+        limit = header;
+    }
+    headers.reverse();
+    (limit == start).then_some(headers)
+}
+
+/// Headers in an order the tree does not tell (priority patterns or
+/// dependency order): the first candidate of each file.
+fn first_headers<'a>(
+    candidates: &BTreeMap<&'a str, Vec<(usize, usize)>>,
+) -> Vec<(usize, usize, &'a str)> {
+    let mut headers: Vec<(usize, usize, &str)> = candidates
+        .iter()
+        .filter_map(|(&path, found)| found.first().map(|&(header, body)| (header, body, path)))
+        .collect();
+    headers.sort_unstable();
+    headers
+}
+
+/// Writes the files of `bundle` below `target`, recreating their folders.
+/// Sections that are not the whole file (binary and size placeholders,
+/// truncated, sampled or extracted files) are skipped and reported, and so
+/// are paths that would end up outside `target` and, unless `overwrite`,
+/// files that already exist. Line numbers are removed again if the bundle
+/// has them (see [`has_line_numbers`]).
+pub fn unpack_bundle(bundle: &str, target: &Path, overwrite: bool) -> Result<UnpackReport, String> {
+    let sections = parse_bundle(bundle)?;
+    let numbered = has_line_numbers(&sections);
+    fs::create_dir_all(target).map_err(|e| format!("cannot create {}: {}", target.display(), e))?;
+    let target = target
+        .canonicalize()
+        .map_err(|e| format!("cannot resolve {}: {}", target.display(), e))?;
+
+    let mut report = UnpackReport::default();
+    for section in sections {
+        let content = match remove_gutter(&section.body) {
+            Some((content, _)) if numbered => content,
+            _ => section.body,
+        };
+        let result = match unrestorable(&section.path, &content) {
+            Some(reason) => Err(reason.to_string()),
+            None => write_file(&target, &section.path, &content, overwrite),
+        };
+        match result {
+            Ok(()) => report.written.push(section.path),
+            Err(reason) => report.skipped.push(RejectedPath {
+                path: section.path,
+                reason,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// File paths listed by a tree as `build_tree` draws it. Files of the
+/// project root come first, one level indented; every later entry with more
+/// deeply indented entries after it is a folder.
+fn parse_tree(tree: &str) -> Option<Vec<String>> {
+    let entries: Vec<(usize, &str)> = tree.lines().map(parse_tree_line).collect::<Option<_>>()?;
+
+    let mut paths = Vec::new();
+    let mut folders: Vec<&str> = Vec::new();
+    let mut top_level = false;
+    for (i, &(depth, name)) in entries.iter().enumerate() {
+        top_level |= depth == 0;
+        if !top_level {
+            paths.push(name.to_string());
+            continue;
+        }
+        folders.truncate(depth);
+        if entries.get(i + 1).is_some_and(|&(next, _)| next > depth) {
+            folders.push(name);
+        } else {
+            paths.push(
+                folders
+                    .iter()
+                    .chain([&name])
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            );
+        }
+    }
+    Some(paths)
+}
+
+/// The depth and name of a tree line such as `│   └── main.rs`.
+fn parse_tree_line(mut line: &str) -> Option<(usize, &str)> {
+    let mut depth = 0;
+    loop {
+        if let Some(name) = line
+            .strip_prefix("├── ")
+            .or_else(|| line.strip_prefix("└── "))
+        {
+            return Some((depth, name));
+        }
+        line = line
+            .strip_prefix("│   ")
+            .or_else(|| line.strip_prefix("    "))?;
+        depth += 1;
+    }
+}
+
+/// Where the file sections end. The parts that may follow them are peeled
+/// off the end in reverse order (manifest hash line, manifest, graph), each
+/// only if it is really there, so a file containing one of their headers
+/// does not end the sections early.
+fn sections_end(text: &str) -> usize {
+    let mut end = text.len();
+
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    let last_line = body.rfind('\n').map_or(0, |i| i + 1);
+    if body[last_line..].starts_with(MANIFEST_HASH_PREFIX) {
+        end = last_line;
+    }
+
+    if let Some(manifest) = manifest_start(&text[..end]) {
+        end = manifest;
+    }
+
+    // The graph has no blank lines, unlike the section before it
+    let graph = text[..end].rfind("\n\n").map_or(0, |i| i + 2);
+    if text[graph..end].starts_with(GRAPH_HEADER) {
+        end = graph;
+    }
+    end
+}
+
+/// Start of the manifest section that `text` ends with, as written by
+/// [`crate::manifest::Manifest::render`]: the header, lines of known kinds
+/// including the scope, and a blank line.
+fn manifest_start(text: &str) -> Option<usize> {
+    let mut rest = text.strip_suffix("\n\n")?;
+    let mut scope = false;
+    loop {
+        let start = rest.rfind('\n').map_or(0, |i| i + 1);
+        let line = &rest[start..];
+        if line == MANIFEST_HEADER {
+            return scope.then_some(start);
+        }
+        match line.split_once(' ')?.0 {
+            "scope" => scope = true,
+            "root" | "ignore" | "file" => {}
+            _ => return None,
+        }
+        rest = rest.get(..start.checked_sub(1)?)?;
+    }
+}
+
+/// Whether the bundle was written with line numbers: every section has the
+/// gutter of [`crate::formatter::number_lines`] and some line in it has a
+/// number. Binary and empty file placeholders never get one, so they are
+/// left out.
+fn has_line_numbers(sections: &[BundleSection]) -> bool {
+    let mut numbered = false;
+    for section in sections {
+        let body = section.body.as_str();
+        if body.is_empty() || body == "[Empty file]" || body.starts_with("[Binary file") {
+            continue;
+        }
+        match remove_gutter(body) {
+            Some((_, has_number)) => numbered |= has_number,
+            None => return false,
+        }
+    }
+    numbered
+}
+
+/// The text without the gutter added by
+/// [`crate::formatter::number_lines`], or `None` if not every line has one.
+pub(crate) fn strip_line_numbers(text: &str) -> Option<String> {
+    remove_gutter(text)
+        .filter(|&(_, has_number)| has_number)
+        .map(|(text, _)| text)
+}
+
+/// The text without its gutter and whether any line in it has a number, or
+/// `None` if not every line has the gutter. A file of only marker lines has
+/// a gutter without numbers.
+fn remove_gutter(text: &str) -> Option<(String, bool)> {
+    let width = text.lines().next()?.find(" | ")?;
+    let mut numbered = false;
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let gutter = line.get(..width)?;
+        if !gutter.bytes().all(|b| b == b' ' || b.is_ascii_digit())
+            || line.get(width..width + 3) != Some(" | ")
+        {
+            return None;
+        }
+        numbered |= gutter.bytes().any(|b| b.is_ascii_digit());
+        lines.push(&line[width + 3..]);
+    }
+    let mut stripped = lines.join("\n");
+    if text.ends_with('\n') {
+        stripped.push('\n');
+    }
+    Some((stripped, numbered))
+}
+
+/// Why `content` is not the whole file at `path`, if it is not.
+pub(crate) fn unrestorable(path: &str, content: &str) -> Option<&'static str> {
+    let first_line = content.lines().next().unwrap_or("");
+    if first_line.starts_with("[Binary file") {
+        return Some("binary file");
+    }
+    if first_line.starts_with(SIZE_PLACEHOLDER_PREFIX) {
+        return Some("larger than the size limit");
+    }
+    if first_line.starts_with(TRUNCATED_ARCHIVE_PREFIX) {
+        return Some("archive cut off at its limits");
+    }
+    if path.ends_with(".ipynb") && first_line.starts_with("[Cell ") {
+        return Some("notebook reduced to its cells");
+    }
+    if first_line.starts_with("[JSON outline of ") || first_line.starts_with("[YAML outline of ") {
+        return Some("sampled");
+    }
+    for line in content.lines() {
+        if line == TRUNCATION_MARKER {
+            return Some("truncated");
+        }
+        if elided_range_end(line).is_some() {
+            return Some("lines omitted");
+        }
+        let line = line.trim_start();
+        if line.starts_with('[')
+            && (line.ends_with(" more rows omitted]") || line.ends_with(" more items omitted]"))
+        {
+            return Some("sampled");
+        }
+    }
+    None
+}
+
+fn write_file(target: &Path, path: &str, content: &str, overwrite: bool) -> Result<(), String> {
+    let destination = resolve_inside(target, path)?;
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if destination.exists() && !overwrite {
+        return Err("already exists".to_string());
+    }
+    let content = if content == "[Empty file]" {
+        ""
+    } else {
+        content
+    };
+    fs::write(&destination, content).map_err(|e| e.to_string())
+}
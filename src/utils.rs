@@ -1,4 +1,6 @@
 use eframe::egui;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
 
 /// Loads a custom monospaced font that supports box-drawing characters
 pub fn load_custom_font(ctx: &egui::Context) {
@@ -22,3 +24,47 @@ pub fn load_custom_font(ctx: &egui::Context) {
 
     ctx.set_fonts(fonts);
 }
+
+/// Lowercase hex SHA-256 digest of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    to_hex(&Sha256::digest(data))
+}
+
+/// Lowercase hex of `bytes`, e.g. a digest
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Joins the relative `path` to the canonical folder `target`, refusing
+/// paths that would lead outside it: absolute paths, `..` components,
+/// symlinked folders pointing elsewhere and symlinks as the file itself.
+/// Folders that do not exist yet are fine.
+pub fn resolve_inside(target: &Path, path: &str) -> Result<PathBuf, String> {
+    let outside = || "outside the target folder".to_string();
+    let relative = Path::new(path);
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(outside());
+    }
+    let destination = target.join(relative);
+    let existing = destination
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.exists())
+        .unwrap_or(target);
+    if !existing
+        .canonicalize()
+        .is_ok_and(|dir| dir.starts_with(target))
+    {
+        return Err(outside());
+    }
+    if fs::symlink_metadata(&destination).is_ok_and(|m| m.file_type().is_symlink()) {
+        return Err("is a symbolic link".to_string());
+    }
+    Ok(destination)
+}
@@ -0,0 +1,106 @@
+use crate::manifest::{git_state, Manifest};
+use crate::utils::sha256_hex;
+use std::collections::BTreeSet;
+use std::fs;
+
+/// How the working tree differs from a manifest, all paths as written in
+/// the bundle.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StaleReport {
+    /// Number of files the manifest lists.
+    pub checked: usize,
+    /// Listed files whose content is different now.
+    pub changed: Vec<String>,
+    /// Files in the manifest's roots that it does not list. Only reported
+    /// for bundles that had every file of their roots (see
+    /// [`Manifest::complete`]), as others leave files out on purpose.
+    pub added: Vec<String>,
+    /// Listed files that no longer exist.
+    pub deleted: Vec<String>,
+    /// Roots now at another commit: alias, commit then, commit now (`None`
+    /// if the root is no longer in a repository).
+    pub moved: Vec<(String, String, Option<String>)>,
+}
+
+impl StaleReport {
+    /// Whether the bundle still shows the files as they are.
+    pub fn is_current(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.deleted.is_empty()
+    }
+
+    /// One line per difference, then a summary line.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for (alias, then, now) in &self.moved {
+            output.push_str(&format!(
+                "{}: commit {} is now {}\n",
+                alias,
+                then,
+                now.as_deref().unwrap_or("no longer in a git repository")
+            ));
+        }
+        for (label, paths) in [
+            ("changed", &self.changed),
+            ("added", &self.added),
+            ("deleted", &self.deleted),
+        ] {
+            for path in paths {
+                output.push_str(&format!("{:<8} {}\n", label, path));
+            }
+        }
+        if self.is_current() {
+            output.push_str(&format!("All {} files are up to date\n", self.checked));
+        } else {
+            output.push_str(&format!(
+                "{} changed, {} added, {} deleted since the bundle of {} files\n",
+                self.changed.len(),
+                self.added.len(),
+                self.deleted.len(),
+                self.checked
+            ));
+        }
+        output
+    }
+}
+
+/// Compares the files listed in `manifest` with what is on disk now, and
+/// looks for files that appeared in its roots since if the bundle had all
+/// of them.
+pub fn check_manifest(manifest: &Manifest) -> StaleReport {
+    let mut report = StaleReport {
+        checked: manifest.files.len(),
+        ..StaleReport::default()
+    };
+    let mut listed = BTreeSet::new();
+    for file in &manifest.files {
+        listed.insert(file.path.as_str());
+        match manifest
+            .disk_path(&file.path)
+            .and_then(|path| fs::read(path).ok())
+        {
+            Some(data) if sha256_hex(&data) == file.sha256 => {}
+            Some(_) => report.changed.push(file.path.clone()),
+            None => report.deleted.push(file.path.clone()),
+        }
+    }
+    if manifest.complete {
+        report.added = manifest
+            .root_files()
+            .into_iter()
+            .filter(|path| !listed.contains(path.as_str()))
+            .collect();
+    }
+
+    for root in &manifest.roots {
+        let Some(then) = &root.git else {
+            continue;
+        };
+        let now = git_state(&root.path).map(|state| state.commit);
+        if now.as_ref() != Some(&then.commit) {
+            report
+                .moved
+                .push((root.alias.clone(), then.commit.clone(), now));
+        }
+    }
+    report
+}
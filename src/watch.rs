@@ -0,0 +1,230 @@
+use crate::scanner::{
+    is_ignored, scan_file, scan_project, scan_project_with_ignores, slash_path, ScannedFile,
+};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// Default quiet period before a burst of file events is reported.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Scanned files of a project, kept up to date incrementally while watching.
+pub struct ProjectState {
+    root: PathBuf,
+    max_file_size: u64,
+    files: Vec<ScannedFile>, // Sorted by path
+    ignore_patterns: Vec<String>,
+    excluded: BTreeSet<PathBuf>,
+}
+
+impl ProjectState {
+    /// Performs the initial full scan of `root`.
+    pub fn scan(root: &Path, max_file_size: u64) -> Self {
+        Self::scan_with_ignores(root, max_file_size, Vec::new())
+    }
+
+    /// Initial scan that also skips paths matched by `ignore_patterns`, now
+    /// and for every later change.
+    pub fn scan_with_ignores(
+        root: &Path,
+        max_file_size: u64,
+        ignore_patterns: Vec<String>,
+    ) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let mut files =
+            scan_project_with_ignores(&root.to_string_lossy(), max_file_size, &ignore_patterns);
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Self {
+            root,
+            max_file_size,
+            files,
+            ignore_patterns,
+            excluded: BTreeSet::new(),
+        }
+    }
+
+    /// Leaves `path` out of the project, e.g. the bundle being written into
+    /// the watched folder, which would otherwise retrigger itself.
+    pub fn exclude(&mut self, path: &Path) {
+        let path = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => parent
+                .canonicalize()
+                .map(|p| p.join(name))
+                .unwrap_or_else(|_| path.to_path_buf()),
+            _ => path.to_path_buf(),
+        };
+        if let Ok(relative) = path.strip_prefix(&self.root) {
+            let relative = slash_path(relative);
+            self.files.retain(|f| f.path != relative);
+        }
+        self.excluded.insert(path);
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn files(&self) -> &[ScannedFile] {
+        &self.files
+    }
+
+    /// Re-reads only the given absolute paths (files or directories),
+    /// removing those that no longer exist. Returns whether anything changed.
+    pub fn apply_changes(&mut self, paths: &[PathBuf]) -> bool {
+        let mut changed = false;
+
+        for path in paths {
+            if self.excluded.contains(path) {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&self.root) else {
+                continue;
+            };
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+            let relative_str = slash_path(relative);
+
+            if path.is_dir() {
+                if !is_ignored(&self.root, relative, true, &self.ignore_patterns) {
+                    changed |= self.rescan_dir(path, &relative_str);
+                }
+            } else if path.is_file()
+                && !is_ignored(&self.root, relative, false, &self.ignore_patterns)
+            {
+                if let Some(file) = scan_file(&self.root, relative, self.max_file_size) {
+                    changed |= self.upsert(file);
+                }
+            } else {
+                // Deleted (or now ignored): drop the file and anything below it
+                let dir_prefix = format!("{}/", relative_str);
+                let before = self.files.len();
+                self.files
+                    .retain(|f| f.path != relative_str && !f.path.starts_with(&dir_prefix));
+                changed |= self.files.len() != before;
+            }
+        }
+
+        changed
+    }
+
+    fn upsert(&mut self, file: ScannedFile) -> bool {
+        match self.files.binary_search_by(|f| f.path.cmp(&file.path)) {
+            Ok(i) if self.files[i] == file => false,
+            Ok(i) => {
+                self.files[i] = file;
+                true
+            }
+            Err(i) => {
+                self.files.insert(i, file);
+                true
+            }
+        }
+    }
+
+    /// Replaces everything below `relative_dir` with a fresh scan of `dir`,
+    /// so files that went away without an event of their own (e.g. a folder
+    /// moved out and back) are dropped too.
+    fn rescan_dir(&mut self, dir: &Path, relative_dir: &str) -> bool {
+        let dir_prefix = format!("{}/", relative_dir);
+        let scanned: Vec<ScannedFile> = scan_project(&dir.to_string_lossy(), self.max_file_size)
+            .into_iter()
+            .map(|mut file| {
+                file.path = format!("{}{}", dir_prefix, file.path);
+                file
+            })
+            .filter(|file| {
+                !self.excluded.contains(&self.root.join(&file.path))
+                    && !is_ignored(
+                        &self.root,
+                        Path::new(&file.path),
+                        false,
+                        &self.ignore_patterns,
+                    )
+            })
+            .collect();
+
+        let found: BTreeSet<&str> = scanned.iter().map(|f| f.path.as_str()).collect();
+        let before = self.files.len();
+        self.files
+            .retain(|f| !f.path.starts_with(&dir_prefix) || found.contains(f.path.as_str()));
+        let mut changed = self.files.len() != before;
+        for file in scanned {
+            changed |= self.upsert(file);
+        }
+        changed
+    }
+}
+
+/// Watches a directory tree and reports debounced batches of changed paths.
+pub struct ProjectWatcher {
+    _watcher: RecommendedWatcher, // Stops watching when dropped
+    batches: Receiver<Vec<PathBuf>>,
+}
+
+impl ProjectWatcher {
+    pub fn new(root: &Path, debounce: Duration) -> notify::Result<Self> {
+        let (event_tx, event_rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(event_tx)?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+
+        let (batch_tx, batches) = mpsc::channel();
+        thread::spawn(move || {
+            // Wait for the first event, then collect until things go quiet
+            while let Ok(first) = event_rx.recv() {
+                let mut batch = BTreeSet::new();
+                collect_paths(first, &mut batch);
+
+                let disconnected = loop {
+                    match event_rx.recv_timeout(debounce) {
+                        Ok(event) => collect_paths(event, &mut batch),
+                        Err(RecvTimeoutError::Timeout) => break false,
+                        Err(RecvTimeoutError::Disconnected) => break true,
+                    }
+                };
+
+                if !batch.is_empty() && batch_tx.send(batch.into_iter().collect()).is_err() {
+                    return;
+                }
+                if disconnected {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            _watcher: watcher,
+            batches,
+        })
+    }
+
+    /// Blocks until the next batch of changes arrives. Returns `None` once the
+    /// watcher has shut down.
+    pub fn next_batch(&self) -> Option<Vec<PathBuf>> {
+        self.batches.recv().ok()
+    }
+
+    /// Returns the next batch if one is ready, without blocking.
+    pub fn try_next_batch(&self) -> Option<Vec<PathBuf>> {
+        self.batches.try_recv().ok()
+    }
+}
+
+fn collect_paths(event: notify::Result<notify::Event>, batch: &mut BTreeSet<PathBuf>) {
+    let Ok(event) = event else {
+        return;
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+    batch.extend(
+        event
+            .paths
+            .into_iter()
+            .filter(|p| !p.components().any(|c| c.as_os_str() == ".git")),
+    );
+}
@@ -0,0 +1,298 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::apply::{
+    apply_edits, apply_patch, parse_response, plan_edits, EditAction, EditStatus, FileEdit,
+};
+use contextor::manifest::Manifest;
+use contextor::roots::ProjectRoot;
+use std::fs;
+
+fn patch_of(text: &str) -> FileEdit {
+    let mut edits = parse_response(text);
+    assert_eq!(edits.len(), 1, "{:?}", edits);
+    edits.remove(0)
+}
+
+fn patched(original: &str, diff: &str) -> Result<String, String> {
+    match patch_of(diff).action {
+        EditAction::Patch(hunks) => apply_patch(original, &hunks),
+        other => panic!("expected a patch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_response() {
+    let reply = "\
+Here is the fix. Note:
+the helper moved.
+
+**src/lib.rs**:
+```rust
+pub fn helper() {}
+```
+
+README.md:
+# Demo
+
+Plain text block.
+
+
+```diff
+diff --git a/src/main.rs b/src/main.rs
+index 1234567..89abcde 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,2 +1,2 @@
+ fn main() {
+-    old();
++    new();
+```
+
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+    let edits = parse_response(reply);
+    let summary: Vec<(&str, &str)> = edits
+        .iter()
+        .map(|edit| {
+            let kind = match &edit.action {
+                EditAction::Write(_) => "write",
+                EditAction::Patch(_) => "patch",
+                EditAction::Delete => "delete",
+            };
+            (edit.path.as_str(), kind)
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("src/lib.rs", "write"),
+            ("README.md", "write"),
+            ("src/main.rs", "patch"),
+            ("old.txt", "delete"),
+        ]
+    );
+    assert_eq!(
+        edits[0].action,
+        EditAction::Write("pub fn helper() {}\n".to_string())
+    );
+    assert_eq!(
+        edits[1].action,
+        EditAction::Write("# Demo\n\nPlain text block.\n".to_string())
+    );
+
+    // A header naming the file of the diff below it is not a file of its own
+    let edits = parse_response(
+        "src/a.rs:\n```diff\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1 +1 @@\n-x\n+y\n```\n",
+    );
+    assert_eq!(edits.len(), 1);
+    assert!(matches!(edits[0].action, EditAction::Patch(_)));
+
+    // Fences inside a Markdown file do not end its block
+    let readme = "# Demo\n\n```rust\nfn main() {}\n```\n\n```\nplain\n```\n";
+    let edits = parse_response(&format!(
+        "README.md:\n````markdown\n{}````\n\ndocs/a.md:\n```md\n{}```\nAfter the block.\n",
+        readme,
+        &readme[..readme.find("\n\n```\n").unwrap() + 1]
+    ));
+    assert_eq!(edits.len(), 2, "{:?}", edits);
+    assert_eq!(edits[0].action, EditAction::Write(readme.to_string()));
+    assert_eq!(
+        edits[1].action,
+        EditAction::Write("# Demo\n\n```rust\nfn main() {}\n```\n".to_string())
+    );
+}
+
+#[test]
+fn test_apply_patch() {
+    let original: String = (1..=10).map(|i| format!("line {}\n", i)).collect();
+
+    // The header is two lines off, and the context has trailing spaces
+    let diff = "--- a/f\n+++ b/f\n@@ -3,3 +3,3 @@\n line 5  \n-line 6\n+line six\n line 7\n";
+    let result = patched(&original, diff).unwrap();
+    assert_eq!(result, original.replace("line 6\n", "line six\n"));
+
+    let diff = "--- a/f\n+++ b/f\n@@ -10,0 +11,1 @@\n+line 11\n\\ No newline at end of file\n";
+    assert_eq!(
+        patched(&original, diff).unwrap(),
+        format!("{}line 11", original)
+    );
+
+    // Line endings of the file are kept
+    let crlf = original.replace('\n', "\r\n");
+    let diff = "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n line 1\n-line 2\n+line two\n";
+    assert_eq!(
+        patched(&crlf, diff).unwrap(),
+        crlf.replace("line 2\r\n", "line two\r\n")
+    );
+
+    let diff = "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n line 1\n-line 99\n+line 2\n";
+    assert_eq!(
+        patched(&original, diff),
+        Err("hunk 1 does not match the file".to_string())
+    );
+}
+
+#[test]
+fn test_plan_and_apply_edits() {
+    let test_dir = setup_test_dir("apply_edits");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::write(project.join("src/main.rs"), "fn main() {\n    old();\n}\n").unwrap();
+    fs::write(project.join("notes.txt"), "notes\n").unwrap();
+    fs::write(project.join("unsent.txt"), "not in the bundle\n").unwrap();
+    fs::write(project.join("remove.txt"), "bye\n").unwrap();
+
+    let roots = vec![ProjectRoot::new(&project)];
+    let sent = Manifest::build(
+        &roots,
+        &[
+            "src/main.rs".to_string(),
+            "notes.txt".to_string(),
+            "remove.txt".to_string(),
+        ],
+    );
+    // Changed after the bundle was made
+    fs::write(project.join("notes.txt"), "notes, edited\n").unwrap();
+
+    let reply = "\
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    old();
++    new();
+ }
+
+src/new.rs:
+```
+pub fn added() {}
+```
+
+notes.txt:
+```
+replaced
+```
+
+unsent.txt:
+```
+overwritten
+```
+
+--- a/remove.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+
+../escape.txt:
+```
+nope
+```
+";
+    let planned = plan_edits(&roots, &parse_response(reply), Some(&sent));
+    let statuses: Vec<(&str, String)> = planned
+        .iter()
+        .map(|edit| (edit.path.as_str(), edit.status.label()))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("src/main.rs", "ready".to_string()),
+            ("src/new.rs", "ready".to_string()),
+            (
+                "notes.txt",
+                "conflict: changed since the bundle was made".to_string()
+            ),
+            (
+                "unsent.txt",
+                "conflict: not in the bundle the edit was made for".to_string()
+            ),
+            ("remove.txt", "ready".to_string()),
+            (
+                "../escape.txt",
+                "failed: outside the target folder".to_string()
+            ),
+        ]
+    );
+    assert!(planned[0].diff().contains("-    old();\n+    new();\n"));
+
+    // Accept everything but the unsent file; one file changes after the preview
+    let accepted: Vec<_> = planned
+        .into_iter()
+        .filter(|edit| edit.path != "unsent.txt")
+        .collect();
+    fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+    let backups = test_dir.path.join("backups");
+    let report = apply_edits(&roots, &accepted, &backups);
+
+    assert_eq!(report.written, vec!["src/new.rs", "notes.txt"]);
+    assert_eq!(report.deleted, vec!["remove.txt"]);
+    let skipped: Vec<(&str, &str)> = report
+        .skipped
+        .iter()
+        .map(|s| (s.path.as_str(), s.reason.as_str()))
+        .collect();
+    assert_eq!(
+        skipped,
+        [
+            ("src/main.rs", "changed since the preview"),
+            ("../escape.txt", "failed: outside the target folder"),
+        ]
+    );
+    assert_eq!(report.backup_dir, Some(backups.clone()));
+
+    assert_eq!(
+        fs::read_to_string(project.join("src/new.rs")).unwrap(),
+        "pub fn added() {}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(project.join("notes.txt")).unwrap(),
+        "replaced\n"
+    );
+    assert_eq!(
+        fs::read_to_string(backups.join("notes.txt")).unwrap(),
+        "notes, edited\n"
+    );
+    assert_eq!(
+        fs::read_to_string(backups.join("remove.txt")).unwrap(),
+        "bye\n"
+    );
+    assert!(!project.join("remove.txt").exists());
+    assert!(!test_dir.path.join("escape.txt").exists());
+    assert!(matches!(
+        plan_edits(
+            &roots,
+            &parse_response("notes.txt:\n```\nreplaced\n```\n"),
+            None
+        )[0]
+        .status,
+        EditStatus::Unchanged
+    ));
+
+    // Sections echoed from the bundle are not the whole file
+    let echoed = "\
+notes.txt:
+```
+replaced
+[Truncated: File too large]
+```
+
+src/new.rs:
+1 | pub fn added() {}
+2 | 
+";
+    let statuses: Vec<String> = plan_edits(&roots, &parse_response(echoed), None)
+        .iter()
+        .map(|edit| edit.status.label())
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            "failed: not the whole file (truncated)",
+            "failed: not the whole file (has line numbers)",
+        ]
+    );
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::archive::{
+    is_archive, scan_archive, scan_archive_with_limits, ArchiveLimits, TRUNCATED_ARCHIVE_ENTRY,
+    TRUNCATED_ARCHIVE_PREFIX,
+};
+use contextor::formatter::build_tree;
+use contextor::roots::{scan_roots, ProjectRoot};
+use contextor::scanner::SIZE_PLACEHOLDER_PREFIX;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+    let mut writer = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, data) in entries {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+fn write_tar_gz(path: &Path, entries: &[(&str, &[u8])]) {
+    let encoder = GzEncoder::new(fs::File::create(path).unwrap(), Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for (name, data) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, *data).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+}
+
+#[test]
+fn test_is_archive() {
+    assert!(is_archive(Path::new("drop.zip")));
+    assert!(is_archive(Path::new("vendor/DROP.TAR.GZ")));
+    assert!(is_archive(Path::new("release.tgz")));
+    assert!(!is_archive(Path::new("notes.gz")));
+    assert!(!is_archive(Path::new("src/zip.rs")));
+}
+
+#[test]
+fn test_scan_zip_archive() {
+    let test_dir = setup_test_dir("zip_archive");
+    let archive = test_dir.path.join("drop.zip");
+    let large = vec![b'x'; 100];
+    write_zip(
+        &archive,
+        &[
+            (".gitignore", b"*.tmp\n"),
+            ("src/main.rs", b"fn main() {}"),
+            ("src/cache.tmp", b"scratch"),
+            ("lib/.gitignore", b"generated/\n"),
+            ("lib/generated/out.rs", b"// generated"),
+            ("lib/mod.rs", b"pub mod a;"),
+            ("assets/blob.bin", &[0, 1, 2]),
+            ("data/large.txt", &large),
+            ("../escape.txt", b"outside"),
+            (".git/config", b"[core]"),
+        ],
+    );
+
+    let mut files = scan_archive(&archive, 50, &["assets/".to_string()]).unwrap();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            ".gitignore",
+            "data/large.txt",
+            "lib/.gitignore",
+            "lib/mod.rs",
+            "src/main.rs"
+        ]
+    );
+    assert!(files[1]
+        .content
+        .as_deref()
+        .unwrap()
+        .starts_with(SIZE_PLACEHOLDER_PREFIX));
+    assert_eq!(files[4].content.as_deref(), Some("fn main() {}"));
+
+    // Binary detection applies to entries as well
+    let files = scan_archive(&archive, 50, &[]).unwrap();
+    let blob = files.iter().find(|f| f.path == "assets/blob.bin").unwrap();
+    assert!(blob.is_binary);
+}
+
+#[test]
+fn test_scan_archive_limits() {
+    let test_dir = setup_test_dir("archive_limits");
+    let zip = test_dir.path.join("many.zip");
+    let tar = test_dir.path.join("big.tar.gz");
+    let entries: [(&str, &[u8]); 4] = [
+        ("a.txt", b"0123456789"),
+        ("b.txt", b"0123456789"),
+        ("c.txt", b"0123456789"),
+        ("d.txt", b"0123456789"),
+    ];
+    write_zip(&zip, &entries);
+    write_tar_gz(&tar, &entries);
+
+    let limits = ArchiveLimits {
+        max_entries: 2,
+        ..ArchiveLimits::default()
+    };
+    let by_count = scan_archive_with_limits(&zip, 100, &[], limits).unwrap();
+    let limits = ArchiveLimits {
+        max_entries: 100,
+        max_bytes: 25,
+    };
+    let by_bytes = scan_archive_with_limits(&tar, 100, &[], limits).unwrap();
+    for files in [by_count, by_bytes] {
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt", "b.txt", TRUNCATED_ARCHIVE_ENTRY]);
+        let note = files[2].content.as_deref().unwrap();
+        assert!(note.starts_with(TRUNCATED_ARCHIVE_PREFIX), "{}", note);
+    }
+
+    // Entries over the size limit are not read, so they cost no bytes
+    let limits = ArchiveLimits {
+        max_entries: 100,
+        max_bytes: 0,
+    };
+    let files = scan_archive_with_limits(&zip, 5, &[], limits).unwrap();
+    assert_eq!(files.len(), 4);
+    assert!(scan_archive(&zip, 100, &[])
+        .unwrap()
+        .iter()
+        .all(|f| f.path != TRUNCATED_ARCHIVE_ENTRY));
+}
+
+#[test]
+fn test_scan_archive_roots() {
+    let test_dir = setup_test_dir("archive_roots");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(project.join("vendor")).unwrap();
+    fs::write(project.join("README.md"), "# Project").unwrap();
+    write_tar_gz(
+        &project.join("vendor/sdk.tar.gz"),
+        &[("include/sdk.h", b"int sdk(void);"), ("README", b"SDK")],
+    );
+
+    // Nested archives are only opened on request
+    let files = scan_roots(&[ProjectRoot::new(&project)], 1000, false).unwrap();
+    assert_eq!(files.len(), 2);
+    assert!(files
+        .iter()
+        .any(|f| f.path == "vendor/sdk.tar.gz" && f.is_binary));
+
+    let root = ProjectRoot {
+        expand_archives: true,
+        ..ProjectRoot::new(&project)
+    };
+    let mut files = scan_roots(&[root], 1000, false).unwrap();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "README.md",
+            "vendor/sdk.tar.gz/README",
+            "vendor/sdk.tar.gz/include/sdk.h"
+        ]
+    );
+    let tree: Vec<String> = build_tree(&files)
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect();
+    assert!(tree.contains(&"    └── sdk.tar.gz".to_string()));
+
+    // An archive can be the root itself
+    let files = scan_roots(
+        &[ProjectRoot::new(project.join("vendor/sdk.tar.gz"))],
+        1000,
+        false,
+    )
+    .unwrap();
+    let mut paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["README", "include/sdk.h"]);
+
+    // A corrupt archive root is an error, not an empty project
+    let broken = test_dir.path.join("broken.zip");
+    fs::write(&broken, "not a zip").unwrap();
+    let error = scan_roots(&[ProjectRoot::new(&broken)], 1000, false).unwrap_err();
+    assert!(error.starts_with("cannot read archive "), "{}", error);
+    assert!(error.contains("broken.zip"));
+}
@@ -0,0 +1,142 @@
+use contextor::binary::{describe_binary, format_size};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{self, Cursor, Read, Write};
+
+fn png_header(width: u32, height: u32, color: u8) -> Vec<u8> {
+    let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    data.extend(width.to_be_bytes());
+    data.extend(height.to_be_bytes());
+    data.extend([8, color, 0, 0, 0]);
+    data
+}
+
+#[test]
+fn test_format_size() {
+    assert_eq!(format_size(999), "999 bytes");
+    assert_eq!(format_size(12_345), "12.3 KB");
+    assert_eq!(format_size(4_000_000), "4.0 MB");
+}
+
+#[test]
+fn test_describe_images() {
+    assert_eq!(
+        describe_binary("logo.png", &png_header(640, 480, 6)),
+        "[Binary file: PNG image, 640x480, RGBA 8-bit, 29 bytes]"
+    );
+
+    let gif = [b"GIF89a".as_slice(), &[0x20, 0x00, 0x10, 0x00, 0, 0]].concat();
+    assert_eq!(
+        describe_binary("anim.gif", &gif),
+        "[Binary file: GIF image, 32x16, 12 bytes]"
+    );
+
+    // SOI, an APP0 segment, then a baseline frame header
+    let jpeg = [
+        0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0x00,
+        0x02, 0x00, 0x03, 0x00,
+    ];
+    assert_eq!(
+        describe_binary("photo.jpg", &jpeg),
+        "[Binary file: JPEG image, 512x256, YCbCr, 19 bytes]"
+    );
+}
+
+#[test]
+fn test_describe_archives() {
+    let mut zip_data = Vec::new();
+    {
+        let mut writer = zip::ZipWriter::new(Cursor::new(&mut zip_data));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        for name in ["README.md", "src/main.rs"] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(b"data").unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    let description = describe_binary("bundle.zip", &zip_data);
+    assert!(
+        description.starts_with("[Binary file: ZIP archive, 2 entries: README.md, src/main.rs, ")
+    );
+
+    let mut builder = tar::Builder::new(Vec::new());
+    for i in 0..22 {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, format!("file{}.txt", i), &b"x"[..])
+            .unwrap();
+    }
+    let tar_data = builder.into_inner().unwrap();
+    let description = describe_binary("files.tar", &tar_data);
+    assert!(description.starts_with("[Binary file: tar archive, 22 entries: file0.txt, file1.txt"));
+    assert!(description.contains("file19.txt, … (2 more), "));
+}
+
+#[test]
+fn test_describe_compiled_artifacts() {
+    let mut elf = b"\x7FELF\x02\x01\x01".to_vec();
+    elf.resize(16, 0);
+    elf.extend([0x03, 0x00, 0x3E, 0x00]);
+    assert_eq!(
+        describe_binary("libfoo.so", &elf),
+        "[Binary file: ELF 64-bit shared object, x86-64, 20 bytes]"
+    );
+
+    let mut pe = vec![0u8; 0x80];
+    pe[..2].copy_from_slice(b"MZ");
+    pe[0x3C] = 0x40;
+    pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+    pe[0x44..0x46].copy_from_slice(&0x8664u16.to_le_bytes());
+    pe[0x56..0x58].copy_from_slice(&0x2000u16.to_le_bytes());
+    pe[0x58..0x5A].copy_from_slice(&0x20Bu16.to_le_bytes());
+    assert_eq!(
+        describe_binary("app.dll", &pe),
+        "[Binary file: PE32+ DLL, x86-64, 128 bytes]"
+    );
+
+    let class = [0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D];
+    assert_eq!(
+        describe_binary("Main.class", &class),
+        "[Binary file: Java class file (Java 17), 8 bytes]"
+    );
+
+    assert_eq!(
+        describe_binary("module.wasm", b"\0asm\x01\0\0\0"),
+        "[Binary file: WebAssembly module, version 1, 8 bytes]"
+    );
+    assert_eq!(
+        describe_binary("blob.bin", &[0, 1, 2]),
+        "[Binary file: 3 bytes]"
+    );
+}
+
+#[test]
+fn test_describe_tar_gz_stops_at_limit() {
+    // 100 MB of zeros compress to about 100 KB, more than is listed
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+    let size = 100_000_000;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "zeros.bin", io::repeat(0).take(size))
+        .unwrap();
+    let mut header = tar::Header::new_gnu();
+    header.set_size(1);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "after.txt", &b"x"[..])
+        .unwrap();
+    let data = builder.into_inner().unwrap().finish().unwrap();
+
+    let description = describe_binary("bomb.tar.gz", &data);
+    assert!(
+        description
+            .starts_with("[Binary file: gzip-compressed tar archive, 1+ entries: zeros.bin, "),
+        "{}",
+        description
+    );
+}
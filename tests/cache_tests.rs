@@ -0,0 +1,156 @@
+mod common;
+
+use common::{setup_test_dir, TestDir};
+use contextor::cache::ScanCache;
+use contextor::scanner::scan_project;
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+/// A test folder with empty `project` and `cache` folders inside.
+fn setup_cache_test_dir(test_name: &str) -> TestDir {
+    let test_dir = setup_test_dir(test_name);
+    fs::create_dir_all(test_dir.path.join("project")).expect("Failed to create test directory");
+    fs::create_dir_all(test_dir.path.join("cache")).expect("Failed to create test directory");
+    test_dir
+}
+
+fn sorted_paths(files: &[contextor::scanner::ScannedFile]) -> Vec<String> {
+    let mut paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_cache_reuses_unchanged_files() {
+    let test_dir = setup_cache_test_dir("cache_reuse");
+    let project = test_dir.path.join("project");
+    let cache_dir = test_dir.path.join("cache");
+    fs::write(project.join("a.txt"), "alpha").unwrap();
+    fs::write(project.join("b.txt"), "beta").unwrap();
+
+    let mtime = fs::metadata(project.join("a.txt"))
+        .unwrap()
+        .modified()
+        .unwrap();
+
+    let mut cache = ScanCache::open_in(&cache_dir, &project, 1_000_000);
+    let first = cache.scan();
+    assert_eq!((cache.hits, cache.misses), (0, 2));
+    cache.save().unwrap();
+
+    // A fresh cache instance loads the saved entries
+    let mut cache = ScanCache::open_in(&cache_dir, &project, 1_000_000);
+    let second = cache.scan();
+    assert_eq!((cache.hits, cache.misses), (2, 0));
+    assert_eq!(sorted_paths(&first), sorted_paths(&second));
+
+    let entry = cache.entry("a.txt").unwrap();
+    assert_eq!(entry.size, 5);
+    assert_eq!(entry.encoding, "utf-8");
+    assert_eq!(
+        entry.hash.as_deref(),
+        Some("8ed3f6ad685b959ead7022518e1af76cd816f8e8ec7ccdda1ed4018e8f2223f8")
+    );
+    assert_eq!(entry.summary, None);
+
+    // Text content is read again instead of being stored in the cache
+    fs::write(project.join("a.txt"), "ALPHA").unwrap();
+    let file = fs::File::options()
+        .write(true)
+        .open(project.join("a.txt"))
+        .unwrap();
+    file.set_modified(mtime).unwrap();
+    let third = cache.scan();
+    assert_eq!(cache.hits, 2);
+    let a = third.iter().find(|f| f.path == "a.txt").unwrap();
+    assert_eq!(a.content.as_deref(), Some("ALPHA"));
+    for cache_file in fs::read_dir(&cache_dir).unwrap() {
+        let saved = fs::read_to_string(cache_file.unwrap().path()).unwrap();
+        assert!(!saved.contains("alpha") && !saved.contains("beta"));
+    }
+}
+
+#[test]
+fn test_cache_detects_changes() {
+    let test_dir = setup_cache_test_dir("cache_changes");
+    let project = test_dir.path.join("project");
+    let cache_dir = test_dir.path.join("cache");
+    fs::write(project.join("a.txt"), "alpha").unwrap();
+    fs::write(project.join("b.txt"), "beta").unwrap();
+    fs::write(project.join("c.txt"), "gamma").unwrap();
+
+    let mut cache = ScanCache::open_in(&cache_dir, &project, 1_000_000);
+    cache.scan();
+
+    // Same size, new content and mtime
+    fs::write(project.join("a.txt"), "ALPHA").unwrap();
+    let file = fs::File::options()
+        .write(true)
+        .open(project.join("a.txt"))
+        .unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    // Touched only: mtime changes, content does not
+    let file = fs::File::options()
+        .write(true)
+        .open(project.join("b.txt"))
+        .unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    fs::remove_file(project.join("c.txt")).unwrap();
+
+    let files = cache.scan();
+    assert_eq!(sorted_paths(&files), vec!["a.txt", "b.txt"]);
+    assert_eq!((cache.hits, cache.misses), (1, 1));
+    let a = files.iter().find(|f| f.path == "a.txt").unwrap();
+    assert_eq!(a.content.as_deref(), Some("ALPHA"));
+    assert!(cache.entry("c.txt").is_none());
+}
+
+#[test]
+fn test_cache_matches_uncached_scan() {
+    let test_dir = setup_cache_test_dir("cache_matches");
+    let project = test_dir.path.join("project");
+    let cache_dir = test_dir.path.join("cache");
+    fs::write(project.join("text.txt"), "hello").unwrap();
+    fs::write(project.join("bin.dat"), [0u8, 1, 2]).unwrap();
+    fs::write(project.join("big.txt"), "x".repeat(200)).unwrap();
+    // Replacing the invalid bytes takes it over the size limit
+    fs::write(project.join("lossy.txt"), [b'a', 0xff].repeat(40)).unwrap();
+
+    let mut cache = ScanCache::open_in(&cache_dir, &project, 100);
+    let mut uncached = scan_project(project.to_str().unwrap(), 100);
+    uncached.sort_by(|a, b| a.path.cmp(&b.path));
+    // Once filling the cache, once served from it
+    for hits in [0, 4] {
+        let mut cached = cache.scan();
+        cached.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(cached, uncached);
+        assert_eq!(cache.hits, hits);
+    }
+    assert!(uncached[2].is_cut());
+    assert_eq!(cache.entry("lossy.txt").unwrap().encoding, "utf-8-lossy");
+    assert_eq!(cache.entry("bin.dat").unwrap().encoding, "binary");
+    assert_eq!(cache.entry("big.txt").unwrap().hash, None);
+}
+
+#[test]
+fn test_cache_discarded_when_size_limit_changes() {
+    let test_dir = setup_cache_test_dir("cache_limit");
+    let project = test_dir.path.join("project");
+    let cache_dir = test_dir.path.join("cache");
+    fs::write(project.join("a.txt"), "alpha").unwrap();
+
+    let mut cache = ScanCache::open_in(&cache_dir, &project, 1_000_000);
+    cache.scan();
+    cache.save().unwrap();
+
+    let mut cache = ScanCache::open_in(&cache_dir, &project, 2);
+    let files = cache.scan();
+    assert_eq!(cache.misses, 1);
+    assert!(files[0]
+        .content
+        .as_deref()
+        .unwrap()
+        .starts_with("[File size > "));
+}
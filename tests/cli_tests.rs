@@ -0,0 +1,235 @@
+use contextor::cli::{parse_args, Command};
+use contextor::graph::GraphFormat;
+use contextor::order::FileOrder;
+use contextor::relevance::Budget;
+use std::path::PathBuf;
+
+fn parse(args: &[&str]) -> Result<Command, String> {
+    parse_args(args.iter().map(|a| a.to_string()))
+}
+
+#[test]
+fn test_parse_defaults() {
+    let Ok(Command::Bundle(command)) = parse(&["--watch"]) else {
+        panic!("expected bundle command");
+    };
+
+    assert_eq!(command.roots.len(), 1);
+    assert_eq!(command.roots[0].path, PathBuf::from("."));
+    assert_eq!(command.max_file_size, 1_000_000);
+    assert!(command.watch);
+    assert!(command.output.is_none());
+    assert!(command.options.graph.is_none());
+}
+
+#[test]
+fn test_parse_all_options() {
+    let Ok(Command::Bundle(command)) = parse(&[
+        "project",
+        "-o",
+        "out.txt",
+        "--clipboard",
+        "--max-size",
+        "500",
+        "--line-numbers",
+        "--query",
+        "auth flow",
+        "--budget-tokens",
+        "2000",
+        "--with-deps",
+        "src/main.rs",
+        "--dependency-order",
+        "--graph",
+        "mermaid",
+        "--graph-collapse",
+    ]) else {
+        panic!("expected bundle command");
+    };
+
+    assert_eq!(command.roots.len(), 1);
+    assert_eq!(command.roots[0].path, PathBuf::from("project"));
+    assert_eq!(command.output, Some(PathBuf::from("out.txt")));
+    assert!(command.clipboard);
+    assert_eq!(command.max_file_size, 500);
+    assert!(command.options.format.line_numbers);
+    assert_eq!(command.options.query.as_deref(), Some("auth flow"));
+    assert_eq!(command.options.budget, Some(Budget::Tokens(2000)));
+    assert_eq!(
+        command.options.include_with_deps.as_deref(),
+        Some("src/main.rs")
+    );
+    assert_eq!(command.options.order, FileOrder::Dependencies);
+    let graph = command.options.graph.unwrap();
+    assert_eq!(graph.format, GraphFormat::Mermaid);
+    assert!(graph.collapse_by_directory);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(parse(&["--bogus"]).is_err());
+    assert!(parse(&["--max-size"]).is_err());
+    assert!(parse(&["--max-size", "big"]).is_err());
+    assert!(parse(&["--graph", "svg"]).is_err());
+    assert!(parse(&["--alias", "x"]).is_err());
+    assert!(parse(&["a", "b", "--files-from", "-"]).is_err());
+    assert!(parse(&["--files-from", "list.txt", "--watch"]).is_err());
+    assert!(parse(&["--truncate", "*.log"]).is_err());
+    assert!(matches!(parse(&["-h"]), Ok(Command::Help)));
+}
+
+#[test]
+fn test_parse_ordering() {
+    let Ok(Command::Bundle(command)) = parse(&[
+        "--order",
+        "dirs-first",
+        "--first",
+        "README.md",
+        "--first",
+        "src/lib.rs",
+        "--manifest-hash",
+    ]) else {
+        panic!("expected bundle command");
+    };
+    assert_eq!(command.options.order, FileOrder::DirectoriesFirst);
+    assert_eq!(command.options.priority, vec!["README.md", "src/lib.rs"]);
+    assert!(command.options.manifest_hash);
+    assert!(command.options.manifest.is_none());
+
+    assert!(parse(&["--order", "random"]).is_err());
+}
+
+#[test]
+fn test_parse_manifest() {
+    let Ok(Command::Bundle(command)) = parse(&["src", "--manifest-file", "bundle.json"]) else {
+        panic!("expected bundle command");
+    };
+    let manifest = command.options.manifest.expect("manifest options");
+    assert!(!manifest.section);
+    assert_eq!(manifest.sidecar, Some(PathBuf::from("bundle.json")));
+    assert_eq!(manifest.roots, command.roots);
+
+    let Ok(Command::Bundle(command)) = parse(&["--manifest"]) else {
+        panic!("expected bundle command");
+    };
+    assert!(command.options.manifest.is_some_and(|m| m.section));
+}
+
+#[test]
+fn test_parse_serve() {
+    let Ok(Command::Serve(command)) = parse(&["serve"]) else {
+        panic!("expected serve command");
+    };
+    assert_eq!(command.port, 7878);
+    assert_eq!(command.config.allowed_roots, vec![PathBuf::from(".")]);
+
+    let Ok(Command::Serve(command)) = parse(&[
+        "serve",
+        "--port",
+        "9000",
+        "--allow",
+        "a",
+        "--allow",
+        "b",
+        "--no-cache",
+    ]) else {
+        panic!("expected serve command");
+    };
+    assert_eq!(command.port, 9000);
+    assert_eq!(
+        command.config.allowed_roots,
+        vec![PathBuf::from("a"), PathBuf::from("b")]
+    );
+    assert!(!command.config.use_cache);
+
+    assert!(parse(&["serve", "--port", "http"]).is_err());
+    assert!(parse(&["serve", "--watch"]).is_err());
+}
+
+#[test]
+fn test_parse_mcp() {
+    let Ok(Command::Mcp(command)) = parse(&["mcp"]) else {
+        panic!("expected mcp command");
+    };
+    assert_eq!(command.root, PathBuf::from("."));
+
+    let Ok(Command::Mcp(command)) = parse(&["mcp", "project", "--max-size", "500"]) else {
+        panic!("expected mcp command");
+    };
+    assert_eq!(command.root, PathBuf::from("project"));
+    assert_eq!(command.max_file_size, 500);
+
+    assert!(parse(&["mcp", "a", "b"]).is_err());
+    assert!(parse(&["mcp", "--query", "x"]).is_err());
+}
+
+#[test]
+fn test_parse_verify() {
+    let Ok(Command::Verify(file)) = parse(&["verify", "bundle.txt"]) else {
+        panic!("expected verify command");
+    };
+    assert_eq!(file, PathBuf::from("bundle.txt"));
+
+    assert!(parse(&["verify"]).is_err());
+    assert!(parse(&["verify", "a.txt", "b.txt"]).is_err());
+}
+
+#[test]
+fn test_parse_unpack() {
+    let Ok(Command::Unpack(command)) = parse(&["unpack", "-", "out", "--force"]) else {
+        panic!("expected unpack command");
+    };
+    assert_eq!(command.bundle, PathBuf::from("-"));
+    assert_eq!(command.target, PathBuf::from("out"));
+    assert!(command.overwrite);
+
+    assert!(parse(&["unpack", "bundle.txt"]).is_err());
+    assert!(parse(&["unpack", "bundle.txt", "out", "--query", "x"]).is_err());
+}
+
+#[test]
+fn test_parse_apply() {
+    let Ok(Command::Apply(command)) = parse(&[
+        "apply",
+        "reply.md",
+        "backend",
+        "--bundle",
+        "bundle.txt",
+        "--yes",
+    ]) else {
+        panic!("expected apply command");
+    };
+    assert_eq!(command.reply, PathBuf::from("reply.md"));
+    assert_eq!(command.roots.len(), 1);
+    assert_eq!(command.roots[0].path, PathBuf::from("backend"));
+    assert_eq!(command.bundle, Some(PathBuf::from("bundle.txt")));
+    assert!(command.write);
+    assert!(!command.force);
+
+    assert!(parse(&["apply"]).is_err());
+    assert!(parse(&["apply", "reply.md", "--bundle"]).is_err());
+}
+
+#[test]
+fn test_parse_multiple_roots() {
+    let Ok(Command::Bundle(command)) = parse(&[
+        "backend", "--alias", "api", "--ignore", "*.lock", "frontend", "--ignore", "dist/",
+    ]) else {
+        panic!("expected bundle command");
+    };
+
+    assert_eq!(command.roots.len(), 2);
+    assert_eq!(command.roots[0].path, PathBuf::from("backend"));
+    assert_eq!(command.roots[0].alias, "api");
+    assert_eq!(command.roots[0].ignore_patterns, vec!["*.lock"]);
+    assert_eq!(command.roots[1].alias, "frontend");
+    assert_eq!(command.roots[1].ignore_patterns, vec!["dist/"]);
+}
+
+#[test]
+fn test_parse_files_from() {
+    let Ok(Command::Bundle(command)) = parse(&["project", "--files-from", "-"]) else {
+        panic!("expected bundle command");
+    };
+    assert_eq!(command.files_from, Some(PathBuf::from("-")));
+    assert_eq!(command.roots[0].path, PathBuf::from("project"));
+}
@@ -0,0 +1,45 @@
+//! Fixtures shared by the integration tests.
+
+// Each test crate uses only some of these.
+#![allow(dead_code)]
+
+use contextor::scanner::ScannedFile;
+use std::fs;
+use std::path::PathBuf;
+
+/// A scratch folder below the working directory, removed when dropped.
+pub struct TestDir {
+    pub path: PathBuf,
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            fs::remove_dir_all(&self.path).expect("Failed to remove test directory");
+        }
+    }
+}
+
+/// Creates an empty `test_dir_<test_name>`, replacing one left behind by an
+/// earlier run.
+pub fn setup_test_dir(test_name: &str) -> TestDir {
+    let test_dir = PathBuf::from(format!("test_dir_{}", test_name));
+
+    if test_dir.exists() {
+        fs::remove_dir_all(&test_dir).expect("Failed to remove old test directory");
+    }
+
+    fs::create_dir_all(&test_dir).expect("Failed to create test directory");
+
+    TestDir { path: test_dir }
+}
+
+/// A scanned text file with `content`, as if read whole from disk.
+pub fn text_file(path: &str, content: &str) -> ScannedFile {
+    ScannedFile {
+        path: path.to_string(),
+        content: Some(content.to_string()),
+        is_binary: false,
+        line_origins: None,
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::config::{with_prompt, AppConfig, ScanProfile, MAX_RECENT_PROJECTS, PROMPT_PRESETS};
+use contextor::graph::GraphFormat;
+use contextor::roots::ProjectRoot;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_recent_projects() {
+    let mut config = AppConfig::default();
+    for i in 0..MAX_RECENT_PROJECTS + 2 {
+        config.add_recent(Path::new(&format!("/projects/p{}", i)));
+    }
+    config.add_recent(Path::new("/projects/p5"));
+
+    assert_eq!(config.recent_projects.len(), MAX_RECENT_PROJECTS);
+    assert_eq!(config.recent_projects[0], PathBuf::from("/projects/p5"));
+    assert_eq!(config.recent_projects[1], PathBuf::from("/projects/p11"));
+    assert_eq!(
+        config
+            .recent_projects
+            .iter()
+            .filter(|p| p.ends_with("p5"))
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn test_profiles_round_trip() {
+    let test_dir = setup_test_dir("config");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(&project).unwrap();
+    let config_path = test_dir.path.join("settings/config.json");
+
+    let mut config = AppConfig::default();
+    let profile = ScanProfile {
+        name: "backend".to_string(),
+        roots: vec![ProjectRoot {
+            ignore_patterns: vec!["*.lock".to_string()],
+            ..ProjectRoot::new(&project)
+        }],
+        files: vec!["src/main.rs".to_string()],
+        max_file_size: 5000,
+        token_budget: Some(2000),
+        graph: Some(GraphFormat::Mermaid),
+        prompt: PROMPT_PRESETS[0].1.to_string(),
+        ..ScanProfile::default()
+    };
+    config.save_profile(&project, profile.clone());
+    config.save_profile(
+        &project,
+        ScanProfile {
+            name: "docs".to_string(),
+            ..ScanProfile::default()
+        },
+    );
+    // Same name replaces, keeping the order
+    config.save_profile(
+        &project,
+        ScanProfile {
+            line_numbers: true,
+            ..profile.clone()
+        },
+    );
+    config.add_recent(&project);
+    config.save(&config_path).unwrap();
+
+    let loaded = AppConfig::load(&config_path);
+    assert_eq!(loaded, config);
+    // Relative and canonical spellings name the same project
+    let profiles = loaded.profiles(&project.canonicalize().unwrap());
+    let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["backend", "docs"]);
+    assert!(profiles[0].line_numbers);
+    assert_eq!(profiles[0].roots[0].ignore_patterns, vec!["*.lock"]);
+    assert_eq!(profiles[0].prompt, PROMPT_PRESETS[0].1);
+
+    let mut config = loaded;
+    assert!(config.remove_profile(&project, "docs"));
+    assert!(!config.remove_profile(&project, "docs"));
+    assert_eq!(config.profiles(&project).len(), 1);
+    assert!(config.profiles(Path::new("/elsewhere")).is_empty());
+}
+
+#[test]
+fn test_load_missing_or_corrupt_config() {
+    let test_dir = setup_test_dir("config_corrupt");
+    let path = test_dir.path.join("config.json");
+    assert_eq!(AppConfig::load(&path), AppConfig::default());
+
+    fs::write(&path, "{ not json").unwrap();
+    assert_eq!(AppConfig::load(&path), AppConfig::default());
+}
+
+#[test]
+fn test_with_prompt() {
+    let summary = "tree\n\nsrc/main.rs:\nfn main() {}\n\n";
+    assert_eq!(with_prompt(summary, "  \n"), summary);
+    assert_eq!(
+        with_prompt(summary, "Explain this.\n"),
+        "tree\n\nsrc/main.rs:\nfn main() {}\n\nExplain this.\n"
+    );
+    let names: Vec<&str> = PROMPT_PRESETS.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["Review", "Explain", "Edit", "Tests"]);
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::text_file;
+use contextor::deps::{
+    js_import_specifiers, rust_mod_declarations, rust_use_paths, select_with_dependencies,
+    DependencyGraph,
+};
+use contextor::formatter::format_project_summary_in_order;
+use contextor::scanner::ScannedFile;
+use std::collections::{BTreeSet, HashMap};
+
+fn deps_of(graph: &DependencyGraph, path: &str) -> Vec<String> {
+    graph.dependencies(path).cloned().collect()
+}
+
+fn rust_project() -> Vec<ScannedFile> {
+    vec![
+        text_file("Cargo.toml", "[package]\nname = \"my-app\"\n"),
+        text_file("src/main.rs", "use my_app::app::App;\nfn main() {}"),
+        text_file(
+            "src/lib.rs",
+            "pub mod app;\npub mod scanner;\npub mod utils;",
+        ),
+        text_file(
+            "src/app.rs",
+            "use crate::{scanner::scan, utils};\nuse std::path::Path;\npub struct App;",
+        ),
+        text_file(
+            "src/scanner.rs",
+            "use super::utils::helper;\npub fn scan() {}",
+        ),
+        text_file("src/utils.rs", "pub fn helper() {}"),
+    ]
+}
+
+#[test]
+fn test_rust_use_paths_expand_groups() {
+    let paths = rust_use_paths("use crate::{a, b::{c, d as e}};\npub(crate) use self::f::*;");
+    assert_eq!(
+        paths,
+        vec!["crate::a", "crate::b::c", "crate::b::d", "self::f"]
+    );
+}
+
+#[test]
+fn test_rust_mod_declarations() {
+    let mods = rust_mod_declarations("pub mod app;\nmod tests {\n}\npub(crate) mod util;");
+    assert_eq!(mods, vec!["app", "util"]);
+}
+
+#[test]
+fn test_rust_graph() {
+    let graph = DependencyGraph::build(&rust_project());
+
+    assert_eq!(deps_of(&graph, "src/main.rs"), vec!["src/app.rs"]);
+    assert_eq!(
+        deps_of(&graph, "src/lib.rs"),
+        vec!["src/app.rs", "src/scanner.rs", "src/utils.rs"]
+    );
+    assert_eq!(
+        deps_of(&graph, "src/app.rs"),
+        vec!["src/scanner.rs", "src/utils.rs"]
+    );
+    assert_eq!(deps_of(&graph, "src/scanner.rs"), vec!["src/utils.rs"]);
+}
+
+#[test]
+fn test_topological_order_puts_dependencies_first() {
+    let graph = DependencyGraph::build(&rust_project());
+    let order = graph.topological_order();
+    let pos = |p: &str| order.iter().position(|o| o == p).unwrap();
+
+    assert_eq!(order.len(), 6);
+    assert!(pos("src/utils.rs") < pos("src/scanner.rs"));
+    assert!(pos("src/scanner.rs") < pos("src/app.rs"));
+    assert!(pos("src/app.rs") < pos("src/main.rs"));
+    assert!(pos("src/app.rs") < pos("src/lib.rs"));
+}
+
+#[test]
+fn test_topological_order_handles_cycles() {
+    let files = vec![
+        text_file("a.py", "import b"),
+        text_file("b.py", "import a"),
+        text_file("c.py", "import a"),
+    ];
+    let order = DependencyGraph::build(&files).topological_order();
+    assert_eq!(order, vec!["a.py", "b.py", "c.py"]);
+}
+
+#[test]
+fn test_topological_order_of_long_chain() {
+    // m0 imports m1, which imports m2, …; the z files import nothing
+    let mut files: Vec<ScannedFile> = (0..5000)
+        .map(|i| text_file(&format!("m{}.py", i), &format!("import m{}", i + 1)))
+        .collect();
+    files.push(text_file("z2.py", ""));
+    files.push(text_file("z1.py", ""));
+
+    let order = DependencyGraph::build(&files).topological_order();
+    let mut expected: Vec<String> = (0..5000).rev().map(|i| format!("m{}.py", i)).collect();
+    expected.extend(["z1.py".to_string(), "z2.py".to_string()]);
+    assert_eq!(order, expected);
+}
+
+#[test]
+fn test_python_imports() {
+    let files = vec![
+        text_file("pkg/__init__.py", ""),
+        text_file("pkg/models.py", "import os\nfrom .db import connect"),
+        text_file("pkg/db.py", ""),
+        text_file(
+            "app.py",
+            "from pkg import models\nimport pkg.db as database",
+        ),
+    ];
+    let graph = DependencyGraph::build(&files);
+
+    assert_eq!(deps_of(&graph, "pkg/models.py"), vec!["pkg/db.py"]);
+    assert_eq!(
+        deps_of(&graph, "app.py"),
+        vec!["pkg/db.py", "pkg/models.py"]
+    );
+}
+
+#[test]
+fn test_js_imports() {
+    let source = "import React from 'react';\nimport { a } from \"./util\";\nconst b = require('../lib/b');\nexport * from './types';";
+    assert_eq!(
+        js_import_specifiers(source),
+        vec!["react", "./util", "./types", "../lib/b"]
+    );
+
+    let files = vec![
+        text_file("web/src/app.tsx", source),
+        text_file("web/src/util.ts", ""),
+        text_file("web/src/types/index.ts", ""),
+        text_file("web/lib/b.js", ""),
+    ];
+    let graph = DependencyGraph::build(&files);
+
+    assert_eq!(
+        deps_of(&graph, "web/src/app.tsx"),
+        vec!["web/lib/b.js", "web/src/types/index.ts", "web/src/util.ts"]
+    );
+}
+
+#[test]
+fn test_go_imports() {
+    let files = vec![
+        text_file("go.mod", "module example.com/shop\n\ngo 1.22\n"),
+        text_file(
+            "cmd/main.go",
+            "package main\n\nimport (\n\t\"fmt\"\n\tdb \"example.com/shop/internal/db\"\n)\n",
+        ),
+        text_file("internal/db/db.go", "package db"),
+        text_file("internal/db/query.go", "package db"),
+        text_file("internal/db/db_test.go", "package db"),
+    ];
+    let graph = DependencyGraph::build(&files);
+
+    assert_eq!(
+        deps_of(&graph, "cmd/main.go"),
+        vec!["internal/db/db.go", "internal/db/query.go"]
+    );
+}
+
+#[test]
+fn test_transitive_dependencies_and_dependents() {
+    let graph = DependencyGraph::build(&rust_project());
+
+    let deps = graph.transitive_dependencies("src/app.rs");
+    let expected: BTreeSet<String> = ["src/app.rs", "src/scanner.rs", "src/utils.rs"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(deps, expected);
+
+    let dependents: Vec<&String> = graph.dependents("src/utils.rs").collect();
+    assert_eq!(
+        dependents,
+        vec!["src/app.rs", "src/lib.rs", "src/scanner.rs"]
+    );
+}
+
+#[test]
+fn test_select_with_dependencies() {
+    let selected = select_with_dependencies(rust_project(), &["src/scanner.rs"]);
+    let paths: Vec<&str> = selected.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, vec!["src/scanner.rs", "src/utils.rs"]);
+}
+
+#[test]
+fn test_format_project_summary_in_order() {
+    let contents = HashMap::from([
+        ("a.rs".to_string(), "a.rs:\nA".to_string()),
+        ("b.rs".to_string(), "b.rs:\nB".to_string()),
+        ("c.rs".to_string(), "c.rs:\nC".to_string()),
+    ]);
+    let order = vec!["c.rs".to_string(), "missing.rs".to_string()];
+
+    let result = format_project_summary_in_order(vec![], contents, &order);
+    assert_eq!(result, "\nc.rs:\nC\n\na.rs:\nA\n\nb.rs:\nB\n\n");
+}
@@ -0,0 +1,60 @@
+use contextor::diff::{diff_lines, unified_diff, DiffLine};
+
+#[test]
+fn test_diff_lines() {
+    let old = "a\nb\nc\nd\n";
+    let new = "a\nc\nd\ne\n";
+    assert_eq!(
+        diff_lines(old, new),
+        vec![
+            DiffLine::Same("a"),
+            DiffLine::Removed("b"),
+            DiffLine::Same("c"),
+            DiffLine::Same("d"),
+            DiffLine::Added("e"),
+        ]
+    );
+    assert!(diff_lines(old, old)
+        .iter()
+        .all(|line| matches!(line, DiffLine::Same(_))));
+}
+
+#[test]
+fn test_unified_diff() {
+    let old: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+    let new = old
+        .replace("line 2\n", "line two\n")
+        .replace("line 18\n", "");
+
+    assert_eq!(
+        unified_diff("notes.txt", Some(&old), Some(&new)),
+        "\
+--- a/notes.txt
++++ b/notes.txt
+@@ -1,5 +1,5 @@
+ line 1
+-line 2
++line two
+ line 3
+ line 4
+ line 5
+@@ -15,6 +15,5 @@
+ line 15
+ line 16
+ line 17
+-line 18
+ line 19
+ line 20
+"
+    );
+
+    assert_eq!(unified_diff("same.txt", Some(&old), Some(&old)), "");
+    assert_eq!(
+        unified_diff("new.txt", None, Some("hello\n")),
+        "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,1 @@\n+hello\n"
+    );
+    assert_eq!(
+        unified_diff("gone.txt", Some("bye\n"), None),
+        "--- a/gone.txt\n+++ /dev/null\n@@ -1,1 +0,0 @@\n-bye\n"
+    );
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::filelist::{parse_file_list, scan_file_list};
+use contextor::scanner::SIZE_PLACEHOLDER_PREFIX;
+use std::fs;
+
+#[test]
+fn test_parse_file_list() {
+    let list = "src/main.rs\n\n  # failing tests\n  tests/a_tests.rs  \r\nREADME.md";
+    assert_eq!(
+        parse_file_list(list),
+        vec!["src/main.rs", "tests/a_tests.rs", "README.md"]
+    );
+}
+
+#[test]
+fn test_scan_file_list() {
+    let test_dir = setup_test_dir("file_list");
+    let root = test_dir.path.join("project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("src/lib.rs"), "pub mod app;").unwrap();
+    fs::write(root.join("big.txt"), "x".repeat(100)).unwrap();
+    fs::write(root.join("data.bin"), [0u8, 1, 2]).unwrap();
+    fs::write(root.join(".gitignore"), "src/lib.rs\n").unwrap();
+    fs::write(test_dir.path.join("outside.rs"), "secret").unwrap();
+
+    let absolute = root.join("src/main.rs").canonicalize().unwrap();
+    let listed = vec![
+        "src/lib.rs:1:pub mod app;".to_string(),
+        absolute.to_string_lossy().to_string(),
+        "src/main.rs".to_string(),
+        "big.txt".to_string(),
+        "data.bin".to_string(),
+        "../outside.rs".to_string(),
+        "missing.rs".to_string(),
+        "src".to_string(),
+    ];
+    let (mut files, rejected) = scan_file_list(&root, &listed, 50);
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    // Explicitly listed files are included even when gitignored, once each
+    let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec!["big.txt", "data.bin", "src/lib.rs", "src/main.rs"]
+    );
+    assert!(files[0]
+        .content
+        .as_deref()
+        .unwrap()
+        .starts_with(SIZE_PLACEHOLDER_PREFIX));
+    assert!(files[1].is_binary);
+    assert_eq!(files[3].content.as_deref(), Some("fn main() {}"));
+
+    let rejected: Vec<(&str, &str)> = rejected
+        .iter()
+        .map(|r| (r.path.as_str(), r.reason.as_str()))
+        .collect();
+    assert_eq!(
+        rejected,
+        vec![
+            ("../outside.rs", "outside of the root folder"),
+            ("missing.rs", "not a file"),
+            ("src", "not a file"),
+        ]
+    );
+}
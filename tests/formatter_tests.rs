@@ -1,5 +1,8 @@
-use contextor::formatter::{build_tree, format_file_contents, format_project_summary};
-use contextor::scanner::ScannedFile;
+use contextor::formatter::{
+    build_tree, format_file_contents, format_file_contents_with_options, format_project_summary,
+    FormatOptions,
+};
+use contextor::scanner::{LineSpan, ScannedFile};
 use std::collections::HashMap;
 
 #[test]
@@ -9,16 +12,19 @@ fn test_build_tree() {
             path: "src/main.rs".to_string(),
             content: Some("fn main() {}".to_string()),
             is_binary: false,
+            line_origins: None,
         },
         ScannedFile {
             path: "src/app.rs".to_string(),
             content: Some("pub struct App {}".to_string()),
             is_binary: false,
+            line_origins: None,
         },
         ScannedFile {
             path: "assets/logo.png".to_string(),
             content: None,
             is_binary: true,
+            line_origins: None,
         },
     ];
 
@@ -45,16 +51,19 @@ fn test_format_file_contents() {
             path: "src/main.rs".to_string(),
             content: Some("fn main() {}".to_string()),
             is_binary: false,
+            line_origins: None,
         },
         ScannedFile {
             path: "assets/logo.png".to_string(),
             content: None,
             is_binary: true,
+            line_origins: None,
         },
         ScannedFile {
             path: "README.md".to_string(),
             content: None,
             is_binary: false,
+            line_origins: None,
         },
     ];
 
@@ -139,6 +148,7 @@ fn test_deeply_nested_directories() {
         path: "src/utils/math/helpers.rs".to_string(),
         content: Some("fn helper() {}".to_string()),
         is_binary: false,
+        line_origins: None,
     }];
 
     let expected = vec![
@@ -174,11 +184,13 @@ fn test_all_binary_files() {
             path: "bin/image.png".to_string(),
             content: None,
             is_binary: true,
+            line_origins: None,
         },
         ScannedFile {
             path: "bin/sound.mp3".to_string(),
             content: None,
             is_binary: true,
+            line_origins: None,
         },
     ];
 
@@ -204,6 +216,7 @@ fn test_large_files_marked_correctly() {
         path: "large.txt".to_string(),
         content: Some("[File size > 1.0MB (max: 1.0MB)]".to_string()), // Simulate large file
         is_binary: false,
+        line_origins: None,
     }];
 
     let expected_content = HashMap::from([(
@@ -221,6 +234,7 @@ fn test_single_root_dir() {
         path: "src/main.rs".to_string(),
         content: None,
         is_binary: false,
+        line_origins: None,
     }];
 
     let result = build_tree(&files);
@@ -239,11 +253,13 @@ fn test_multiple_root_dirs() {
             path: "src/main.rs".to_string(),
             content: None,
             is_binary: false,
+            line_origins: None,
         },
         ScannedFile {
             path: "assets/logo.png".to_string(),
             content: None,
             is_binary: true,
+            line_origins: None,
         },
     ];
 
@@ -268,6 +284,7 @@ fn test_nested_dirs_standard_ascii() {
         path: "src/utils/math/helpers.rs".to_string(),
         content: None,
         is_binary: false,
+        line_origins: None,
     }];
 
     let result = build_tree(&files);
@@ -283,3 +300,52 @@ fn test_nested_dirs_standard_ascii() {
 
     assert_eq!(result, expected);
 }
+
+#[test]
+fn test_line_numbers() {
+    let files = vec![ScannedFile {
+        path: "src/main.rs".to_string(),
+        content: Some((1..=10).map(|i| format!("line {}\n", i)).collect()),
+        is_binary: false,
+        line_origins: None,
+    }];
+
+    let options = FormatOptions { line_numbers: true };
+    let result = format_file_contents_with_options(&files, &options);
+
+    let expected = "src/main.rs:\n 1 | line 1\n 2 | line 2\n 3 | line 3\n 4 | line 4\n 5 | line 5\n 6 | line 6\n 7 | line 7\n 8 | line 8\n 9 | line 9\n10 | line 10\n";
+    assert_eq!(result["src/main.rs"], expected);
+}
+
+#[test]
+fn test_line_numbers_skip_markers() {
+    let files = vec![
+        ScannedFile {
+            path: "big.txt".to_string(),
+            content: Some("first\nsecond\n[Truncated: File too large]".to_string()),
+            is_binary: false,
+            line_origins: Some(vec![
+                LineSpan::Lines { first: 1, count: 2 },
+                LineSpan::Marker,
+            ]),
+        },
+        ScannedFile {
+            path: "large.txt".to_string(),
+            content: Some("[File size > 1.0MB (max: 1.0MB)]".to_string()),
+            is_binary: false,
+            line_origins: Some(vec![LineSpan::Marker]),
+        },
+    ];
+
+    let options = FormatOptions { line_numbers: true };
+    let result = format_file_contents_with_options(&files, &options);
+
+    assert_eq!(
+        result["big.txt"],
+        "big.txt:\n1 | first\n2 | second\n  | [Truncated: File too large]"
+    );
+    assert_eq!(
+        result["large.txt"],
+        "large.txt:\n  | [File size > 1.0MB (max: 1.0MB)]"
+    );
+}
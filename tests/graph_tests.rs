@@ -0,0 +1,69 @@
+mod common;
+
+use common::text_file;
+use contextor::deps::DependencyGraph;
+use contextor::graph::{render_graph, GraphExportOptions, GraphFormat};
+
+fn sample_graph() -> DependencyGraph {
+    DependencyGraph::build(&[
+        text_file("src/main.rs", "mod app;\nmod util;"),
+        text_file("src/app.rs", "use crate::util;\nuse crate::net::client;"),
+        text_file("src/util.rs", ""),
+        text_file("src/net/mod.rs", "pub mod client;"),
+        text_file("src/net/client.rs", "use crate::util;"),
+    ])
+}
+
+#[test]
+fn test_render_dot() {
+    let options = GraphExportOptions {
+        focus: Some("src/app.rs".to_string()),
+        ..Default::default()
+    };
+    let expected = "\
+digraph dependencies {
+    rankdir=LR;
+    node [shape=box];
+    \"src/app.rs\";
+    \"src/net/client.rs\";
+    \"src/util.rs\";
+    \"src/app.rs\" -> \"src/net/client.rs\";
+    \"src/app.rs\" -> \"src/util.rs\";
+    \"src/net/client.rs\" -> \"src/util.rs\";
+}
+";
+    assert_eq!(render_graph(&sample_graph(), &options), expected);
+}
+
+#[test]
+fn test_render_mermaid_collapsed() {
+    let options = GraphExportOptions {
+        format: GraphFormat::Mermaid,
+        collapse_by_directory: true,
+        ..Default::default()
+    };
+    let expected = "\
+graph LR
+    n0[\"src\"]
+    n1[\"src/net\"]
+    n0 --> n1
+    n1 --> n0
+";
+    assert_eq!(render_graph(&sample_graph(), &options), expected);
+}
+
+#[test]
+fn test_render_path_prefix() {
+    let options = GraphExportOptions {
+        format: GraphFormat::Mermaid,
+        path_prefix: Some("src/net/".to_string()),
+        ..Default::default()
+    };
+    let expected = "\
+graph LR
+    n0[\"src/net/client.rs\"]
+    n1[\"src/net/mod.rs\"]
+    n1 --> n0
+";
+    assert_eq!(render_graph(&sample_graph(), &options), expected);
+}
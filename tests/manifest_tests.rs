@@ -0,0 +1,165 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::bundle::{render_bundle, select_files, BundleOptions};
+use contextor::manifest::{git_state, Manifest, ManifestOptions, MANIFEST_HEADER};
+use contextor::roots::{scan_roots, ProjectRoot};
+use contextor::stream::write_roots_bundle;
+use contextor::utils::sha256_hex;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn manifest_options(roots: &[ProjectRoot]) -> BundleOptions {
+    BundleOptions {
+        manifest: Some(ManifestOptions {
+            roots: roots.to_vec(),
+            section: true,
+            sidecar: None,
+        }),
+        ..BundleOptions::default()
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[test]
+fn test_manifest_section() {
+    let test_dir = setup_test_dir("manifest_section");
+    let root = &test_dir.path;
+    fs::create_dir_all(root.join("src")).unwrap();
+    let main = "fn main() {\n    println!(\"hi\");\n}\n";
+    fs::write(root.join("src/main.rs"), main).unwrap();
+    fs::write(root.join("notes.txt"), "one\ntwo").unwrap();
+    let log: String = (1..=50).map(|i| format!("line {}\n", i)).collect();
+    fs::write(root.join("app.log"), &log).unwrap();
+
+    let roots = vec![ProjectRoot::new(root)];
+    let mut options = manifest_options(&roots);
+    options.truncation = vec![contextor::truncate::TruncateRule::parse("*.log:tail=5").unwrap()];
+
+    let mut streamed = Vec::new();
+    write_roots_bundle(&mut streamed, &roots, 1_000_000, false, &options).unwrap();
+    let streamed = String::from_utf8(streamed).unwrap();
+    let files = select_files(scan_roots(&roots, 1_000_000, false).unwrap(), &options);
+    assert_eq!(streamed, render_bundle(&files, &options));
+
+    let section = &streamed[streamed.find(MANIFEST_HEADER).expect("manifest section")..];
+    let lines: Vec<&str> = section.lines().collect();
+    assert!(lines[1].starts_with("root "));
+    assert!(lines[1].ends_with(&fs::canonicalize(root).unwrap().display().to_string()));
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[2], "scope all");
+    assert_eq!(
+        &lines[3..6],
+        [
+            format!(
+                "file {} {} 50 app.log",
+                sha256_hex(log.as_bytes()),
+                log.len()
+            ),
+            format!("file {} 7 2 notes.txt", sha256_hex(b"one\ntwo")),
+            format!(
+                "file {} {} 3 src/main.rs",
+                sha256_hex(main.as_bytes()),
+                main.len()
+            ),
+        ]
+    );
+    // The truncated log is hashed as it is on disk
+    assert!(!streamed.contains("line 1\n"));
+}
+
+#[test]
+fn test_manifest_sidecar_multiple_roots() {
+    let test_dir = setup_test_dir("manifest_sidecar");
+    let backend = test_dir.path.join("backend");
+    let frontend = test_dir.path.join("frontend");
+    fs::create_dir_all(&backend).unwrap();
+    fs::create_dir_all(&frontend).unwrap();
+    fs::write(backend.join("server.py"), "print('up')\n").unwrap();
+    fs::write(frontend.join("index.js"), "").unwrap();
+
+    let roots = vec![ProjectRoot::new(&backend), ProjectRoot::new(&frontend)];
+    let sidecar = test_dir.path.join("manifest.json");
+    let options = BundleOptions {
+        manifest: Some(ManifestOptions {
+            roots: roots.clone(),
+            section: false,
+            sidecar: Some(sidecar.clone()),
+        }),
+        ..BundleOptions::default()
+    };
+
+    let mut output = Vec::new();
+    write_roots_bundle(&mut output, &roots, 1_000_000, false, &options).unwrap();
+    assert!(!String::from_utf8(output).unwrap().contains(MANIFEST_HEADER));
+
+    let manifest: Manifest = serde_json::from_str(&fs::read_to_string(&sidecar).unwrap()).unwrap();
+    let aliases: Vec<&str> = manifest.roots.iter().map(|r| r.alias.as_str()).collect();
+    assert_eq!(aliases, ["backend", "frontend"]);
+    let files: Vec<(&str, u64, usize)> = manifest
+        .files
+        .iter()
+        .map(|f| (f.path.as_str(), f.size, f.lines))
+        .collect();
+    assert_eq!(
+        files,
+        [("backend/server.py", 12, 1), ("frontend/index.js", 0, 0)]
+    );
+    assert_eq!(manifest.files[1].sha256, sha256_hex(b""));
+
+    // Paths that are not on disk are left out
+    let built = Manifest::build(&roots, &["backend/missing.py".to_string()]);
+    assert!(built.files.is_empty());
+
+    // Aliases with spaces survive the section
+    let mut roots = roots;
+    roots[0].alias = "My Project".to_string();
+    roots[0].ignore_patterns = vec!["*.lock".to_string()];
+    let built = Manifest::build(&roots, &["My Project/server.py".to_string()]);
+    assert_eq!(built.files.len(), 1);
+    assert!(built.render().contains("\nroot My%20Project "));
+    let parsed = Manifest::parse(&built.render()).unwrap();
+    assert_eq!(parsed, built);
+    assert_eq!(
+        parsed.disk_path("My Project/server.py"),
+        Some(backend.canonicalize().unwrap().join("server.py"))
+    );
+}
+
+#[test]
+fn test_manifest_git_state() {
+    let test_dir = setup_test_dir("manifest_git");
+    let root = &test_dir.path;
+    fs::write(root.join("a.txt"), "a\n").unwrap();
+    if !git(root, &["init", "-q"]) {
+        // Without git there is no state to report
+        return;
+    }
+    assert!(git(root, &["add", "."]));
+    assert!(git(root, &["commit", "-q", "-m", "initial"]));
+
+    let state = git_state(root).expect("git state");
+    assert_eq!(state.commit.len(), 40);
+    assert!(!state.dirty);
+
+    fs::write(root.join("a.txt"), "changed\n").unwrap();
+    let dirty = git_state(root).expect("git state");
+    assert_eq!(dirty.commit, state.commit);
+    assert!(dirty.dirty);
+
+    let manifest = Manifest::build(&[ProjectRoot::new(root)], &["a.txt".to_string()]);
+    let alias = &manifest.roots[0].alias;
+    assert!(manifest
+        .render()
+        .contains(&format!("root {} {} dirty ", alias, state.commit)));
+}
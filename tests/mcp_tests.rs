@@ -0,0 +1,124 @@
+mod common;
+
+use common::{setup_test_dir, TestDir};
+use contextor::mcp::McpServer;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Cursor;
+
+fn setup_project(test_dir: &TestDir) -> McpServer {
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::write(project.join("src/main.rs"), "mod auth;\nfn main() {}\n").unwrap();
+    fs::write(project.join("src/auth.rs"), "pub fn login() {}\n").unwrap();
+    fs::write(test_dir.path.join("secret.txt"), "hunter2").unwrap();
+    McpServer::new(&project, 1000, false).unwrap()
+}
+
+fn call_tool(server: &McpServer, name: &str, arguments: Value) -> (String, bool) {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 7,
+        "method": "tools/call",
+        "params": { "name": name, "arguments": arguments },
+    });
+    let response: Value =
+        serde_json::from_str(&server.handle_message(&request.to_string()).unwrap()).unwrap();
+    assert_eq!(response["id"], 7);
+    let result = &response["result"];
+    (
+        result["content"][0]["text"].as_str().unwrap().to_string(),
+        result["isError"].as_bool().unwrap(),
+    )
+}
+
+#[test]
+fn test_mcp_session() {
+    let test_dir = setup_test_dir("mcp_session");
+    let server = setup_project(&test_dir);
+
+    let input = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05","capabilities":{},"clientInfo":{"name":"test","version":"1"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#,
+        "not json",
+        r#"{"jsonrpc":"2.0","id":3,"method":"resources/list"}"#,
+    ]
+    .join("\n");
+    let mut output = Vec::new();
+    server.run(Cursor::new(input), &mut output).unwrap();
+
+    let responses: Vec<Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    // The notification gets no response
+    assert_eq!(responses.len(), 4);
+    assert_eq!(responses[0]["result"]["serverInfo"]["name"], "contextor");
+    let tools: Vec<&str> = responses[1]["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        tools,
+        vec![
+            "list_project_tree",
+            "read_files",
+            "search_files",
+            "build_bundle"
+        ]
+    );
+    assert_eq!(responses[2]["error"]["code"], -32700);
+    assert_eq!(responses[3]["error"]["code"], -32601);
+}
+
+#[test]
+fn test_mcp_tools() {
+    let test_dir = setup_test_dir("mcp_tools");
+    let server = setup_project(&test_dir);
+
+    let (tree, is_error) = call_tool(&server, "list_project_tree", json!({}));
+    assert!(!is_error);
+    assert!(tree.contains("auth.rs"));
+    assert!(tree.contains("main.rs"));
+
+    let (text, is_error) = call_tool(
+        &server,
+        "read_files",
+        json!({ "paths": ["src/auth.rs"], "line_numbers": true }),
+    );
+    assert!(!is_error);
+    assert_eq!(text, "src/auth.rs:\n1 | pub fn login() {}\n");
+
+    let (text, is_error) = call_tool(&server, "search_files", json!({ "text": "LOGIN" }));
+    assert!(!is_error);
+    assert_eq!(text, "src/auth.rs:1: pub fn login() {}");
+
+    let (text, is_error) = call_tool(
+        &server,
+        "build_bundle",
+        json!({ "query": "login", "budget_tokens": 1000 }),
+    );
+    assert!(!is_error);
+    assert!(text.contains("pub fn login() {}"));
+}
+
+#[test]
+fn test_mcp_sandbox() {
+    let test_dir = setup_test_dir("mcp_sandbox");
+    let server = setup_project(&test_dir);
+
+    let (text, is_error) = call_tool(&server, "read_files", json!({ "paths": ["../secret.txt"] }));
+    assert!(is_error);
+    assert!(!text.contains("hunter2"));
+
+    let (text, is_error) = call_tool(&server, "build_bundle", json!({ "roots": [".."] }));
+    assert!(is_error);
+    assert!(!text.contains("hunter2"));
+
+    let (_, is_error) = call_tool(&server, "search_files", json!({ "text": "" }));
+    assert!(is_error);
+}
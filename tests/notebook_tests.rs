@@ -0,0 +1,84 @@
+use contextor::notebook::{extract_notebooks, render_notebook, NotebookOptions};
+use contextor::scanner::ScannedFile;
+
+const NOTEBOOK: &str = r##"{
+  "metadata": {"kernelspec": {"name": "python3", "language": "python"}},
+  "nbformat": 4,
+  "cells": [
+    {"cell_type": "markdown", "metadata": {}, "source": ["# Analysis\n", "Loading data."]},
+    {
+      "cell_type": "code",
+      "execution_count": 3,
+      "metadata": {"scrolled": true},
+      "source": "import pandas as pd\ndf = pd.read_csv('data.csv')\ndf.head()",
+      "outputs": [
+        {"output_type": "stream", "name": "stdout", "text": ["1\n", "2\n", "3\n", "4\n"]},
+        {"output_type": "execute_result", "execution_count": 3, "metadata": {},
+         "data": {"text/plain": ["   a  b\n", "0  1  2"]}},
+        {"output_type": "display_data", "metadata": {},
+         "data": {"image/png": "iVBORw0KGgoAAAANSUhEUg==", "text/plain": ["<Figure size 640x480>"]}},
+        {"output_type": "error", "ename": "KeyError", "evalue": "'c'", "traceback": ["\u001b[0;31m..."]}
+      ]
+    }
+  ]
+}"##;
+
+#[test]
+fn test_render_notebook_without_outputs() {
+    let rendered = render_notebook(NOTEBOOK, &NotebookOptions::default()).unwrap();
+    assert_eq!(
+        rendered,
+        "[Cell 1: markdown]\n# Analysis\nLoading data.\n\n\
+         [Cell 2: code, python]\nimport pandas as pd\ndf = pd.read_csv('data.csv')\ndf.head()"
+    );
+}
+
+#[test]
+fn test_render_notebook_with_outputs() {
+    let options = NotebookOptions {
+        include_outputs: true,
+        max_output_lines: 2,
+        ..NotebookOptions::default()
+    };
+    let rendered = render_notebook(NOTEBOOK, &options).unwrap();
+
+    assert!(rendered.ends_with(
+        "df.head()\n\
+         [Output]\n1\n2\n[2 more output lines omitted]\n\
+         [Output]\n   a  b\n0  1  2\n\
+         [Output]\n[Image output omitted]\n\
+         [Output]\nKeyError: 'c'"
+    ));
+    assert!(!rendered.contains("iVBOR"));
+    assert!(!rendered.contains("execution_count"));
+}
+
+#[test]
+fn test_extract_notebooks() {
+    let file = |path: &str, content: &str| ScannedFile {
+        path: path.to_string(),
+        content: Some(content.to_string()),
+        is_binary: false,
+        line_origins: None,
+    };
+    let files = vec![
+        file("analysis.ipynb", NOTEBOOK),
+        file("broken.ipynb", "{ not json"),
+        file("data.json", NOTEBOOK),
+    ];
+
+    let extracted = extract_notebooks(files.clone(), &NotebookOptions::default());
+    assert!(extracted[0]
+        .content
+        .as_deref()
+        .unwrap()
+        .starts_with("[Cell 1: markdown]"));
+    assert_eq!(extracted[1], files[1]);
+    assert_eq!(extracted[2], files[2]);
+
+    let raw = NotebookOptions {
+        extract: false,
+        ..NotebookOptions::default()
+    };
+    assert_eq!(extract_notebooks(files.clone(), &raw), files);
+}
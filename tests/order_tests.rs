@@ -0,0 +1,165 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::bundle::{render_bundle, verify_manifest_hash, BundleOptions, MANIFEST_HASH_PREFIX};
+use contextor::deps::DependencyGraph;
+use contextor::order::{order_paths, FileOrder};
+use contextor::roots::ProjectRoot;
+use contextor::scanner::ScannedFile;
+use contextor::stream::write_roots_bundle;
+use std::fs;
+
+fn file(path: &str, content: &str) -> ScannedFile {
+    ScannedFile {
+        path: path.to_string(),
+        content: Some(content.to_string()),
+        is_binary: false,
+        line_origins: None,
+    }
+}
+
+fn paths(list: &[&str]) -> Vec<String> {
+    list.iter().map(|p| p.to_string()).collect()
+}
+
+#[test]
+fn test_order_paths() {
+    let input = paths(&[
+        "src/main.rs",
+        "README.md",
+        "src/ui/app.rs",
+        "Cargo.toml",
+        "src/lib.rs",
+    ]);
+
+    assert_eq!(
+        order_paths(&input, FileOrder::Alphabetical, &[], None),
+        paths(&[
+            "Cargo.toml",
+            "README.md",
+            "src/lib.rs",
+            "src/main.rs",
+            "src/ui/app.rs"
+        ])
+    );
+    assert_eq!(
+        order_paths(&input, FileOrder::DirectoriesFirst, &[], None),
+        paths(&[
+            "src/ui/app.rs",
+            "src/lib.rs",
+            "src/main.rs",
+            "Cargo.toml",
+            "README.md"
+        ])
+    );
+
+    // Priority groups follow the pattern list; each group keeps the order
+    let priority = paths(&["README.md", "src/ui/", "*.toml"]);
+    assert_eq!(
+        order_paths(&input, FileOrder::Alphabetical, &priority, None),
+        paths(&[
+            "README.md",
+            "src/ui/app.rs",
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/main.rs"
+        ])
+    );
+
+    // Duplicates are listed once
+    assert_eq!(
+        order_paths(&paths(&["b", "a", "b"]), FileOrder::Alphabetical, &[], None),
+        paths(&["a", "b"])
+    );
+}
+
+#[test]
+fn test_dependency_order() {
+    let files = vec![
+        file("main.rs", "mod util;\nfn main() {}"),
+        file("util.rs", "pub fn help() {}"),
+    ];
+    let graph = DependencyGraph::build(&files);
+    let input = paths(&["main.rs", "util.rs"]);
+
+    assert_eq!(
+        order_paths(&input, FileOrder::Dependencies, &[], Some(&graph)),
+        paths(&["util.rs", "main.rs"])
+    );
+    // Without a graph the order is alphabetical
+    assert_eq!(
+        order_paths(&input, FileOrder::Dependencies, &[], None),
+        paths(&["main.rs", "util.rs"])
+    );
+}
+
+#[test]
+fn test_parse_order() {
+    assert_eq!(
+        FileOrder::parse("dirs-first"),
+        Ok(FileOrder::DirectoriesFirst)
+    );
+    assert_eq!(
+        FileOrder::parse("dependencies"),
+        Ok(FileOrder::Dependencies)
+    );
+    assert!(FileOrder::parse("random").is_err());
+}
+
+#[test]
+fn test_reproducible_bundle_with_manifest_hash() {
+    let files = vec![
+        file("src/main.rs", "fn main() {}"),
+        file("README.md", "# Demo"),
+    ];
+    let options = BundleOptions {
+        manifest_hash: true,
+        order: FileOrder::DirectoriesFirst,
+        ..BundleOptions::default()
+    };
+
+    // Input order does not matter
+    let mut reversed = files.clone();
+    reversed.reverse();
+    let bundle = render_bundle(&files, &options);
+    assert_eq!(bundle, render_bundle(&reversed, &options));
+
+    let last_line = bundle.lines().last().unwrap();
+    assert!(last_line.starts_with(MANIFEST_HASH_PREFIX));
+    assert_eq!(verify_manifest_hash(&bundle), Some(true));
+    assert!(bundle.find("src/main.rs:").unwrap() < bundle.find("README.md:").unwrap());
+
+    let tampered = bundle.replace("# Demo", "# Demo!");
+    assert_eq!(verify_manifest_hash(&tampered), Some(false));
+    assert_eq!(
+        verify_manifest_hash(&render_bundle(&files, &BundleOptions::default())),
+        None
+    );
+}
+
+#[test]
+fn test_streamed_bundle_has_same_order_and_hash() {
+    let test_dir = setup_test_dir("order_streamed");
+    fs::create_dir_all(test_dir.path.join("src")).unwrap();
+    fs::write(test_dir.path.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(test_dir.path.join("README.md"), "# Demo").unwrap();
+    fs::write(test_dir.path.join("Cargo.toml"), "[package]").unwrap();
+
+    let roots = vec![ProjectRoot::new(&test_dir.path)];
+    let options = BundleOptions {
+        manifest_hash: true,
+        order: FileOrder::DirectoriesFirst,
+        priority: paths(&["Cargo.toml"]),
+        ..BundleOptions::default()
+    };
+    let mut streamed = Vec::new();
+    write_roots_bundle(&mut streamed, &roots, 1000, false, &options).unwrap();
+    let streamed = String::from_utf8(streamed).unwrap();
+
+    let files = contextor::roots::scan_roots(&roots, 1000, false).unwrap();
+    assert_eq!(streamed, render_bundle(&files, &options));
+    let cargo = streamed.find("Cargo.toml:").unwrap();
+    assert!(cargo < streamed.find("src/main.rs:").unwrap());
+    assert!(streamed.find("src/main.rs:").unwrap() < streamed.find("README.md:").unwrap());
+    assert_eq!(verify_manifest_hash(&streamed), Some(true));
+}
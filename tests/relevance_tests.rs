@@ -0,0 +1,92 @@
+mod common;
+
+use common::text_file;
+use contextor::relevance::{estimate_tokens, rank_files, select_relevant, tokenize, Budget};
+use contextor::scanner::ScannedFile;
+
+fn sample_project() -> Vec<ScannedFile> {
+    vec![
+        text_file(
+            "src/auth.rs",
+            "pub fn authenticate(user: &User, password: &str) -> bool { check_password(password) }",
+        ),
+        text_file(
+            "src/login_handler.rs",
+            "use crate::auth;\nfn handle() { auth::authenticate(&user, pw); }",
+        ),
+        text_file("src/render.rs", "pub fn draw_frame() { paint_pixels(); }"),
+        text_file("README.md", "A small demo application."),
+    ]
+}
+
+#[test]
+fn test_tokenize_splits_identifiers() {
+    let terms = tokenize("parseHttpRequest snake_case_name");
+    assert!(terms.contains(&"pars".to_string()));
+    assert!(terms.contains(&"http".to_string()));
+    assert!(terms.contains(&"request".to_string()));
+    assert!(terms.contains(&"snak".to_string()));
+    assert!(terms.contains(&"nam".to_string()));
+}
+
+#[test]
+fn test_tokenize_drops_stop_words() {
+    assert_eq!(
+        tokenize("how does the login work"),
+        vec!["login".to_string()]
+    );
+}
+
+#[test]
+fn test_rank_files_prefers_matches() {
+    let files = sample_project();
+    let ranked = rank_files(&files, "how does authentication work");
+
+    assert_eq!(ranked[0].path, "src/auth.rs");
+    assert!(ranked.iter().any(|r| r.path == "src/login_handler.rs"));
+    assert!(!ranked.iter().any(|r| r.path == "src/render.rs"));
+}
+
+#[test]
+fn test_rank_files_boosts_importers() {
+    let files = vec![
+        text_file("src/auth.rs", "pub fn verify_token() {}"),
+        text_file("src/server.rs", "use crate::auth;\nfn start() {}"),
+        text_file("src/render.rs", "fn draw() {}"),
+    ];
+
+    let ranked = rank_files(&files, "verify token");
+
+    assert_eq!(ranked[0].path, "src/auth.rs");
+    assert_eq!(ranked[1].path, "src/server.rs");
+    assert_eq!(ranked.len(), 2);
+}
+
+#[test]
+fn test_rank_files_empty_query() {
+    assert!(rank_files(&sample_project(), "the of and").is_empty());
+}
+
+#[test]
+fn test_select_relevant_respects_budget() {
+    let files = sample_project();
+    let auth_tokens = estimate_tokens(files[0].content.as_ref().unwrap());
+
+    let selected = select_relevant(files, "authentication", Budget::Tokens(auth_tokens));
+
+    assert_eq!(selected.len(), 1);
+    assert_eq!(selected[0].path, "src/auth.rs");
+}
+
+#[test]
+fn test_select_relevant_byte_budget_skips_oversized() {
+    let files = vec![
+        text_file("src/auth.rs", &"authenticate ".repeat(100)),
+        text_file("src/session.rs", "fn authenticate_session() {}"),
+    ];
+
+    let selected = select_relevant(files, "authenticate", Budget::Bytes(100));
+
+    assert_eq!(selected.len(), 1);
+    assert_eq!(selected[0].path, "src/session.rs");
+}
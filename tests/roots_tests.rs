@@ -0,0 +1,90 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::formatter::build_tree;
+use contextor::roots::{scan_roots, unique_aliases, ProjectRoot};
+use std::fs;
+
+#[test]
+fn test_unique_aliases() {
+    let mut roots = vec![
+        ProjectRoot::new("a/shared"),
+        ProjectRoot::new("b/shared"),
+        ProjectRoot {
+            alias: "web/app".to_string(),
+            ..ProjectRoot::new("c")
+        },
+        ProjectRoot {
+            alias: ".".to_string(),
+            ..ProjectRoot::new("d")
+        },
+        ProjectRoot {
+            alias: " .. ".to_string(),
+            ..ProjectRoot::new("e")
+        },
+        ProjectRoot {
+            alias: String::new(),
+            ..ProjectRoot::new("f")
+        },
+    ];
+    unique_aliases(&mut roots);
+
+    let aliases: Vec<&str> = roots.iter().map(|r| r.alias.as_str()).collect();
+    assert_eq!(
+        aliases,
+        vec!["shared", "shared-2", "web_app", "d", "e", "f"]
+    );
+}
+
+#[test]
+fn test_scan_multiple_roots() {
+    let test_dir = setup_test_dir("multi_roots");
+    let backend = test_dir.path.join("backend");
+    let frontend = test_dir.path.join("frontend");
+    fs::create_dir_all(backend.join("src")).unwrap();
+    fs::create_dir_all(frontend.join("dist")).unwrap();
+    fs::write(backend.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(backend.join("Cargo.lock"), "lock").unwrap();
+    fs::write(frontend.join("index.ts"), "export {}").unwrap();
+    fs::write(frontend.join("dist/bundle.js"), "built").unwrap();
+
+    let roots = vec![
+        ProjectRoot {
+            alias: "api".to_string(),
+            ignore_patterns: vec!["*.lock".to_string()],
+            ..ProjectRoot::new(&backend)
+        },
+        ProjectRoot {
+            ignore_patterns: vec!["dist/".to_string()],
+            ..ProjectRoot::new(&frontend)
+        },
+    ];
+
+    let mut files = scan_roots(&roots, 1_000_000, false).unwrap();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, vec!["api/src/main.rs", "frontend/index.ts"]);
+
+    let tree = build_tree(&files);
+    let lines: Vec<&str> = tree.iter().map(|(line, _)| line.as_str()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "├── api",
+            "│   └── src",
+            "│       └── main.rs",
+            "└── frontend",
+            "    └── index.ts",
+        ]
+    );
+}
+
+#[test]
+fn test_single_root_is_not_prefixed() {
+    let test_dir = setup_test_dir("single_root");
+    fs::write(test_dir.path.join("a.txt"), "a").unwrap();
+
+    let files = scan_roots(&[ProjectRoot::new(&test_dir.path)], 1_000_000, false).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "a.txt");
+}
@@ -0,0 +1,211 @@
+mod common;
+
+use common::{setup_test_dir, text_file};
+use contextor::roots::{scan_roots, DiskFiles, ProjectRoot};
+use contextor::sample::{sample_data_files, SampleRule, SampleStrategy, OUTLINE_MIN_BYTES};
+use contextor::scanner::SIZE_PLACEHOLDER_PREFIX;
+use std::fs;
+
+#[test]
+fn test_parse_sample_rules() {
+    assert_eq!(
+        SampleStrategy::parse("table"),
+        Ok(SampleStrategy::Table { rows: 10 })
+    );
+    assert_eq!(
+        SampleStrategy::parse("table=3"),
+        Ok(SampleStrategy::Table { rows: 3 })
+    );
+    assert_eq!(
+        SampleStrategy::parse("outline"),
+        Ok(SampleStrategy::Outline)
+    );
+    assert_eq!(
+        SampleStrategy::parse("tail=50"),
+        Ok(SampleStrategy::Tail { lines: 50 })
+    );
+    assert!(SampleStrategy::parse("head=5").is_err());
+    assert!(SampleStrategy::parse("tail=x").is_err());
+
+    let rule = SampleRule::parse("data/*.csv:table=5").unwrap();
+    assert_eq!(rule.pattern, "data/*.csv");
+    assert_eq!(SampleRule::defaults().len(), 6);
+}
+
+#[test]
+fn test_sample_table() {
+    let csv = "id,name,notes\n1,a,\"multi\nline\"\n2,b,x\n3,c,\"x, y\"\n";
+    let sampled = SampleStrategy::Table { rows: 1 }
+        .apply("data.csv", csv)
+        .unwrap();
+    assert_eq!(
+        sampled,
+        "[Table: 3 rows, 3 columns; header and first 1 rows shown]\n\
+         id,name,notes\n1,a,\"multi\nline\"\n[2 more rows omitted]"
+    );
+
+    let tsv = "a\tb\r\n1\t2\r\n";
+    assert_eq!(
+        SampleStrategy::Table { rows: 10 }
+            .apply("data.tsv", tsv)
+            .unwrap(),
+        "[Table: 1 rows, 2 columns; header and first 1 rows shown]\na\tb\n1\t2"
+    );
+}
+
+#[test]
+fn test_outline_json() {
+    let items: Vec<String> = (0..200)
+        .map(|i| format!(r#"{{"id": {}, "tags": ["t"], "active": true}}"#, i))
+        .collect();
+    let json = format!(
+        r#"{{"name": "{}", "version": null, "items": [{}]}}"#,
+        "x".repeat(60),
+        items.join(", ")
+    );
+    assert!(json.len() >= OUTLINE_MIN_BYTES);
+
+    let outline = SampleStrategy::Outline.apply("data.json", &json).unwrap();
+    let expected = format!(
+        "[JSON outline of {} bytes]\n\
+         $: object (3 keys)\n  \
+           items: array (200 items)\n    \
+             [0]: object (3 keys)\n      \
+               active: bool = true\n      \
+               id: number = 0\n      \
+               tags: array (1 items)\n        \
+                 [0]: string = \"t\"\n  \
+           name: string = \"{}…\"\n  \
+           version: null",
+        json.len(),
+        "x".repeat(40)
+    );
+    assert_eq!(outline, expected);
+
+    // Small or invalid documents are kept
+    assert_eq!(SampleStrategy::Outline.apply("package.json", "{}"), None);
+    let invalid = "{".repeat(OUTLINE_MIN_BYTES);
+    assert_eq!(SampleStrategy::Outline.apply("broken.json", &invalid), None);
+}
+
+#[test]
+fn test_outline_yaml() {
+    let mut yaml = String::from("# services\nservices:\n");
+    for i in 0..300 {
+        yaml.push_str(&format!(
+            "  - name: svc{}\n    ports:\n      - 80\n      - 443\n",
+            i
+        ));
+    }
+    yaml.push_str("version: 3\n");
+
+    let outline = SampleStrategy::Outline.apply("compose.yml", &yaml).unwrap();
+    assert_eq!(
+        outline,
+        format!(
+            "[YAML outline of {} bytes]\n# services\nservices:\n  - name: svc0\n    ports:\n      - 80\n      \
+             [1 more items omitted]\n  [299 more items omitted]\nversion: 3",
+            yaml.len()
+        )
+    );
+}
+
+#[test]
+fn test_sample_data_files() {
+    let log: String = (1..=5).map(|i| format!("event {}\n", i)).collect();
+    let files = vec![
+        text_file("logs/app.log", &log),
+        text_file("src/main.rs", "fn main() {}"),
+        text_file("big.csv", "[File size > 2.0MB (max: 1.0MB)]"),
+    ];
+    let rules = vec![
+        SampleRule::parse("*.log:tail=10").unwrap(),
+        SampleRule::parse("logs/*.log:tail=2").unwrap(),
+        SampleRule::parse("*.csv:table").unwrap(),
+    ];
+
+    let sampled = sample_data_files(files.clone(), &rules, None);
+    assert_eq!(
+        sampled[0].content.as_deref(),
+        Some("[Lines 1-3 of 5 omitted]\nevent 4\nevent 5\n")
+    );
+    assert_eq!(sampled[1], files[1]);
+    assert_eq!(sampled[2], files[2]);
+}
+
+#[test]
+fn test_sample_oversize_files() {
+    let test_dir = setup_test_dir("sample_oversize");
+    let csv: String = std::iter::once("id,name\n".to_string())
+        .chain((1..=5000).map(|i| format!("{},row {}\n", i, i)))
+        .collect();
+    let log: String = (1..=5000).map(|i| format!("event {}\n", i)).collect();
+    let json = format!(
+        "[{}]",
+        (0..1000)
+            .map(|i| format!("{{\"name\": \"item {}\", \"id\": {}}}", i, i))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let yaml: String = std::iter::once("items:\n".to_string())
+        .chain((0..1000).map(|i| format!("  - name: item {}\n", i)))
+        .collect();
+    fs::write(test_dir.path.join("big.csv"), &csv).unwrap();
+    fs::write(test_dir.path.join("big.log"), &log).unwrap();
+    fs::write(test_dir.path.join("big.json"), &json).unwrap();
+    fs::write(test_dir.path.join("big.yml"), &yaml).unwrap();
+
+    let roots = [ProjectRoot::new(&test_dir.path)];
+    let files = scan_roots(&roots, 1000, false).unwrap();
+    assert!(files.iter().all(|file| file
+        .content
+        .as_deref()
+        .unwrap()
+        .starts_with(SIZE_PLACEHOLDER_PREFIX)));
+    let rules: Vec<SampleRule> = [
+        "*.csv:table=3",
+        "*.log:tail=2",
+        "*.json:outline",
+        "*.yml:outline",
+    ]
+    .iter()
+    .map(|rule| SampleRule::parse(rule).unwrap())
+    .collect();
+
+    // Without a way to read the files they stay placeholders
+    assert_eq!(sample_data_files(files.clone(), &rules, None), files);
+
+    let disk = DiskFiles::new(&roots, 1000);
+    let sampled = sample_data_files(files, &rules, Some(&disk));
+    let content = |path: &str| {
+        sampled
+            .iter()
+            .find(|file| file.path == path)
+            .and_then(|file| file.content.clone())
+            .unwrap()
+    };
+    assert_eq!(
+        content("big.csv"),
+        "[Table: 5000 rows, 2 columns; header and first 3 rows shown]\n\
+         id,name\n1,row 1\n2,row 2\n3,row 3\n[4997 more rows omitted]"
+    );
+    assert_eq!(
+        content("big.log"),
+        "[Lines 1-4998 of 5000 omitted]\nevent 4999\nevent 5000\n"
+    );
+    assert_eq!(
+        content("big.json"),
+        format!(
+            "[JSON outline of {} bytes]\n$: array (1000 items)\n  [0]: object (2 keys)\n    \
+             id: number = 0\n    name: string = \"item 0\"",
+            json.len()
+        )
+    );
+    assert_eq!(
+        content("big.yml"),
+        format!(
+            "[YAML outline of {} bytes]\nitems:\n  - name: item 0\n  [999 more items omitted]",
+            yaml.len()
+        )
+    );
+}
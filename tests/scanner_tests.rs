@@ -1,31 +1,13 @@
-use contextor::scanner::scan_project;
+mod common;
+
+use common::setup_test_dir;
+use contextor::scanner::{
+    is_ignored, scan_project, scan_project_with_ignores, slash_path, truncate_text,
+    TRUNCATION_MARKER,
+};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
-
-struct TestDir {
-    path: PathBuf,
-}
-
-impl Drop for TestDir {
-    fn drop(&mut self) {
-        if self.path.exists() {
-            fs::remove_dir_all(&self.path).expect("Failed to remove test directory");
-        }
-    }
-}
-
-fn setup_test_dir(test_name: &str) -> TestDir {
-    let test_dir = PathBuf::from(format!("test_dir_{}", test_name));
-
-    if test_dir.exists() {
-        fs::remove_dir_all(&test_dir).expect("Failed to remove old test directory");
-    }
-
-    fs::create_dir_all(&test_dir).expect("Failed to create test directory");
-
-    TestDir { path: test_dir }
-}
+use std::path::Path;
 
 #[test]
 fn test_scan_project() {
@@ -63,5 +45,124 @@ fn test_binary_detection() {
 
     assert_eq!(results[0].path, expected_relative_path.to_string_lossy());
     assert!(results[0].is_binary);
-    assert_eq!(results[0].content, None);
+    assert_eq!(
+        results[0].content.as_deref(),
+        Some("[Binary file: 4 bytes]")
+    );
+}
+
+#[test]
+fn test_oversize_binary_described() {
+    let test_dir = setup_test_dir("oversize_binary");
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend(640u32.to_be_bytes());
+    png.extend(480u32.to_be_bytes());
+    png.extend([8, 6, 0, 0, 0]);
+    png.resize(5_000, 0);
+    fs::write(test_dir.path.join("big.png"), &png).unwrap();
+
+    let mut zip_data = Vec::new();
+    {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_data));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        writer.start_file("data.bin", options).unwrap();
+        writer.write_all(&[7; 5_000]).unwrap();
+        writer.finish().unwrap();
+    }
+    fs::write(test_dir.path.join("big.zip"), &zip_data).unwrap();
+    fs::write(test_dir.path.join("big.txt"), "x".repeat(5_000)).unwrap();
+
+    let mut results = scan_project(test_dir.path.to_str().unwrap(), 1_000);
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    let contents: Vec<(&str, bool)> = results
+        .iter()
+        .map(|f| (f.content.as_deref().unwrap(), f.is_binary))
+        .collect();
+    assert_eq!(
+        contents,
+        [
+            (
+                "[Binary file: PNG image, 640x480, RGBA 8-bit, 5.0 KB]",
+                true
+            ),
+            ("[File size > 0.0MB (max: 0.0MB)]", false),
+            (
+                "[Binary file: ZIP archive, 1 entries: data.bin, 5.1 KB]",
+                true
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_ignore_patterns() {
+    let test_dir = setup_test_dir("ignore_patterns");
+    fs::create_dir_all(test_dir.path.join("build")).unwrap();
+    fs::write(test_dir.path.join("keep.rs"), "fn keep() {}").unwrap();
+    fs::write(test_dir.path.join("skip.log"), "noise").unwrap();
+    fs::write(test_dir.path.join("build/out.rs"), "fn out() {}").unwrap();
+
+    let patterns = vec!["*.log".to_string(), "build/".to_string()];
+    let results = scan_project_with_ignores(test_dir.path.to_str().unwrap(), 5_000_000, &patterns);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "keep.rs");
+
+    let root = test_dir.path.canonicalize().unwrap();
+    assert!(is_ignored(&root, Path::new("skip.log"), false, &patterns));
+    assert!(is_ignored(
+        &root,
+        Path::new("build/out.rs"),
+        false,
+        &patterns
+    ));
+    assert!(is_ignored(&root, Path::new(".git/config"), false, &[]));
+    assert!(!is_ignored(&root, Path::new("keep.rs"), false, &patterns));
+}
+
+#[test]
+fn test_truncate_text_multibyte() {
+    let truncated = |text: &str, max_len| truncate_text(text.to_string(), max_len);
+
+    // Cuts inside 'é' (2 bytes) and '😀' (4 bytes) back off to the boundary
+    assert_eq!(truncated("aé", 2), format!("a\n{}", TRUNCATION_MARKER));
+    assert_eq!(truncated("😀😀", 5), format!("😀\n{}", TRUNCATION_MARKER));
+    assert_eq!(truncated("😀", 3), TRUNCATION_MARKER);
+    // Whole lines are kept when one fits
+    assert_eq!(
+        truncated("first\nsecönd line", 10),
+        format!("first\n{}", TRUNCATION_MARKER)
+    );
+    // Text at the limit is untouched
+    assert_eq!(truncated("äöü", 6), "äöü");
+}
+
+#[test]
+fn test_lossy_content_respects_limit() {
+    let test_dir = setup_test_dir("lossy_limit");
+    // Each invalid byte becomes a 3-byte replacement character
+    fs::write(test_dir.path.join("latin1.txt"), b"ab\xff\xff").unwrap();
+
+    let results = scan_project(test_dir.path.to_str().unwrap(), 4);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0].content,
+        Some(format!("ab\n{}", TRUNCATION_MARKER))
+    );
+}
+
+#[test]
+fn test_paths_use_forward_slashes() {
+    let test_dir = setup_test_dir("forward_slashes");
+    fs::create_dir_all(test_dir.path.join("src").join("nested")).unwrap();
+    fs::write(test_dir.path.join("src").join("nested").join("a.rs"), "").unwrap();
+
+    let results = scan_project(test_dir.path.to_str().unwrap(), 100);
+    assert_eq!(results[0].path, "src/nested/a.rs");
+    assert_eq!(
+        slash_path(&Path::new("src").join("nested").join("a.rs")),
+        "src/nested/a.rs"
+    );
 }
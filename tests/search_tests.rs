@@ -0,0 +1,93 @@
+mod common;
+
+use common::text_file;
+use contextor::bundle::{render_bundle, BundleOptions};
+use contextor::formatter::build_tree;
+use contextor::search::{find_matches, tree_anchors, SearchOptions, MAX_MATCHES};
+
+#[test]
+fn test_find_matches() {
+    let text = "Foo foo f.o FOO";
+    let plain = SearchOptions::default();
+    assert_eq!(
+        find_matches(text, "foo", plain).unwrap(),
+        [0..3, 4..7, 12..15]
+    );
+    assert!(find_matches(text, "", plain).unwrap().is_empty());
+
+    let case_sensitive = SearchOptions {
+        case_sensitive: true,
+        ..plain
+    };
+    assert_eq!(
+        find_matches(text, "foo", case_sensitive).unwrap(),
+        vec![4..7]
+    );
+
+    // Plain text is matched literally, regular expressions are not
+    assert_eq!(
+        find_matches(text, "f.o", case_sensitive).unwrap(),
+        vec![8..11]
+    );
+    let regex = SearchOptions {
+        case_sensitive: true,
+        regex: true,
+    };
+    assert_eq!(find_matches(text, "f.o", regex).unwrap(), [4..7, 8..11]);
+    assert_eq!(
+        find_matches(text, r"\bF\w+", regex).unwrap(),
+        [0..3, 12..15]
+    );
+    // Empty matches are not counted
+    assert!(find_matches(text, "x*", regex).unwrap().is_empty());
+    assert!(find_matches(text, "(unclosed", regex).is_err());
+}
+
+#[test]
+fn test_find_matches_limit() {
+    let text = "a".repeat(MAX_MATCHES + 50);
+    let matches = find_matches(&text, "a", SearchOptions::default()).unwrap();
+    assert_eq!(matches.len(), MAX_MATCHES);
+}
+
+#[test]
+fn test_tree_anchors() {
+    let files = vec![
+        text_file("README.md", "# Demo\n"),
+        text_file("src/a.rs", "fn a() {}\n"),
+        // Looks like the header of a section that was already written
+        text_file("src/b.rs", "fn b() {}\n\nsrc/a.rs:\n"),
+    ];
+    let tree = build_tree(&files);
+    let bundle = render_bundle(&files, &BundleOptions::default());
+    let anchors = tree_anchors(&bundle, &tree);
+
+    let lines: Vec<&str> = anchors.iter().map(|a| a.line.as_str()).collect();
+    assert_eq!(
+        lines,
+        [
+            "│   └── README.md",
+            "└── src",
+            "    ├── a.rs",
+            "    └── b.rs"
+        ]
+    );
+    for anchor in &anchors {
+        match anchor.path.as_str() {
+            "src" => assert_eq!(anchor.offset, None),
+            path => {
+                let offset = anchor.offset.expect("file without an offset");
+                let header = format!("{}:\n", path);
+                assert!(bundle[offset..].starts_with(&header), "{}", path);
+                assert!(bundle[..offset].ends_with("\n\n"), "{}", path);
+            }
+        }
+    }
+    let a = anchors.iter().find(|a| a.path == "src/a.rs").unwrap();
+    assert!(bundle[a.offset.unwrap()..].starts_with("src/a.rs:\nfn a() {}"));
+
+    // A preview that is not a bundle has nothing to jump to
+    assert!(tree_anchors("Selected folder", &tree)
+        .iter()
+        .all(|a| a.offset.is_none()));
+}
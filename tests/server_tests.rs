@@ -0,0 +1,211 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::server::{BundleServer, ServerConfig};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+
+fn start_server(allowed_roots: Vec<PathBuf>) -> SocketAddr {
+    let config = ServerConfig {
+        allowed_roots,
+        use_cache: false,
+        ..ServerConfig::default()
+    };
+    let server = BundleServer::bind("127.0.0.1:0", config).expect("Failed to bind");
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    address
+}
+
+/// Sends one HTTP/1.1 request and returns the status code, the raw headers
+/// and the de-chunked body.
+fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String, String) {
+    request_with_headers(address, method, path, "Host: localhost\r\n", body)
+}
+
+/// Like [`request`], with `headers` (each ending in CRLF) instead of the
+/// default `Host`.
+fn request_with_headers(
+    address: SocketAddr,
+    method: &str,
+    path: &str,
+    headers: &str,
+    body: &str,
+) -> (u16, String, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        headers,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let (head, mut rest) = response.split_once("\r\n\r\n").unwrap();
+    let status = head[9..12].parse().unwrap();
+    let mut body = String::new();
+    if head.to_lowercase().contains("transfer-encoding: chunked") {
+        loop {
+            let (size, after) = rest.split_once("\r\n").unwrap();
+            let size = usize::from_str_radix(size.trim(), 16).unwrap();
+            if size == 0 {
+                break;
+            }
+            body.push_str(&after[..size]);
+            rest = &after[size + 2..];
+        }
+    } else {
+        body.push_str(rest);
+    }
+    (status, head.to_string(), body)
+}
+
+#[test]
+fn test_serve_bundle() {
+    let test_dir = setup_test_dir("serve_bundle");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(project.join("notes.log"), "a\nb\nc\n").unwrap();
+    let address = start_server(vec![project.clone()]);
+
+    let (status, head, body) = request(address, "POST", "/bundle", "{}");
+    assert_eq!(status, 200);
+    assert!(head.to_lowercase().contains("transfer-encoding: chunked"));
+    assert!(body.contains("src/main.rs"));
+    assert!(body.contains("fn main() {}"));
+
+    let (status, _, body) = request(
+        address,
+        "POST",
+        "/bundle",
+        r#"{"roots": ["src"], "line_numbers": true, "ignore": ["*.log"]}"#,
+    );
+    assert_eq!(status, 200);
+    assert!(body.contains("main.rs"));
+    assert!(!body.contains("notes.log"));
+    assert!(body.contains("1 | fn main() {}"));
+
+    let (status, _, body) = request(address, "GET", "/roots", "");
+    assert_eq!(status, 200);
+    let roots: Vec<String> = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        roots,
+        vec![project.canonicalize().unwrap().to_string_lossy()]
+    );
+}
+
+#[test]
+fn test_serve_rejects_paths_outside_allow_list() {
+    let test_dir = setup_test_dir("serve_allow_list");
+    let allowed = test_dir.path.join("allowed");
+    let secret = test_dir.path.join("secret");
+    fs::create_dir_all(&allowed).unwrap();
+    fs::create_dir_all(&secret).unwrap();
+    fs::write(secret.join("key.txt"), "hunter2").unwrap();
+    let address = start_server(vec![allowed]);
+
+    for roots in [
+        "[\"../secret\"]".to_string(),
+        serde_json::to_string(&[secret.canonicalize().unwrap()]).unwrap(),
+    ] {
+        let (status, _, body) = request(
+            address,
+            "POST",
+            "/bundle",
+            &format!("{{\"roots\": {}}}", roots),
+        );
+        assert_eq!(status, 403);
+        assert!(!body.contains("hunter2"));
+    }
+
+    let (status, _, _) = request(address, "POST", "/bundle", r#"{"roots": ["missing"]}"#);
+    assert_eq!(status, 404);
+    let (status, _, _) = request(address, "POST", "/bundle", r#"{"bogus": 1}"#);
+    assert_eq!(status, 400);
+    let (status, _, _) = request(address, "POST", "/bundle", r#"{"truncate": ["*.rs"]}"#);
+    assert_eq!(status, 400);
+    let (status, _, _) = request(address, "GET", "/bundle", "");
+    assert_eq!(status, 405);
+    let (status, _, _) = request(address, "GET", "/", "");
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn test_serve_checks_roots_before_responding() {
+    let test_dir = setup_test_dir("serve_archive_errors");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(project.join("broken.zip"), "not a zip").unwrap();
+    fs::write(project.join("big.txt"), "0123456789\n".repeat(10)).unwrap();
+    let config = ServerConfig {
+        allowed_roots: vec![project],
+        max_file_size: 50,
+        use_cache: false,
+    };
+    let server = BundleServer::bind("127.0.0.1:0", config).expect("Failed to bind");
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let (status, _, body) = request(address, "POST", "/bundle", r#"{"roots": ["broken.zip"]}"#);
+    assert_eq!(status, 500);
+    assert!(body.contains("cannot read archive"), "{}", body);
+
+    // Requests cannot raise the server's size limit
+    let (status, _, body) = request(address, "POST", "/bundle", r#"{"max_size": 1000000}"#);
+    assert_eq!(status, 200);
+    assert!(body.contains("[File size > "), "{}", body);
+}
+
+#[test]
+fn test_serve_rejects_foreign_hosts() {
+    let test_dir = setup_test_dir("serve_hosts");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(&project).unwrap();
+    let address = start_server(vec![project]);
+    let port = address.port();
+
+    // A page on another domain, rebound to 127.0.0.1, still names itself
+    for headers in [
+        format!("Host: evil.example:{}\r\n", port),
+        format!("Host: localhost:{}\r\n", port + 1),
+        format!(
+            "Host: 127.0.0.1:{}\r\nOrigin: http://evil.example\r\n",
+            port
+        ),
+    ] {
+        let (status, _, body) = request_with_headers(address, "GET", "/roots", &headers, "");
+        assert_eq!(status, 403, "{}", headers);
+        assert!(!body.contains("project"));
+    }
+
+    for headers in [
+        format!("Host: 127.0.0.1:{}\r\n", port),
+        format!(
+            "Host: localhost:{}\r\nOrigin: http://localhost:{}\r\n",
+            port, port
+        ),
+        "Host: [::1]\r\n".to_string(),
+    ] {
+        let (status, _, _) = request_with_headers(address, "GET", "/roots", &headers, "");
+        assert_eq!(status, 200, "{}", headers);
+    }
+}
+
+#[test]
+fn test_bind_requires_existing_roots() {
+    assert!(BundleServer::bind("127.0.0.1:0", ServerConfig::default()).is_err());
+    let config = ServerConfig {
+        allowed_roots: vec![PathBuf::from("test_dir_does_not_exist")],
+        ..ServerConfig::default()
+    };
+    assert!(BundleServer::bind("127.0.0.1:0", config).is_err());
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::{setup_test_dir, TestDir};
+use contextor::bundle::{render_bundle, select_files, write_bundle, BundleOptions};
+use contextor::order::FileOrder;
+use contextor::roots::{scan_roots, ProjectRoot};
+use contextor::stream::{can_stream, write_roots_bundle};
+use contextor::truncate::TruncateRule;
+use std::fs;
+use std::io::{self, Write};
+
+fn setup_roots(test_dir: &TestDir) -> Vec<ProjectRoot> {
+    let backend = test_dir.path.join("backend");
+    let frontend = test_dir.path.join("frontend");
+    fs::create_dir_all(backend.join("src")).unwrap();
+    fs::create_dir_all(frontend.join("logs")).unwrap();
+    fs::write(backend.join("src/main.rs"), "mod db;\nfn main() {}\n").unwrap();
+    fs::write(backend.join("src/db.rs"), "pub fn connect() {}\n").unwrap();
+    fs::write(backend.join("logo.png"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+    fs::write(backend.join("empty.txt"), "").unwrap();
+    let log: String = (1..=50).map(|i| format!("line {}\n", i)).collect();
+    fs::write(frontend.join("logs/app.log"), log).unwrap();
+    fs::write(frontend.join("index.js"), "console.log('hi');\n").unwrap();
+    vec![ProjectRoot::new(backend), ProjectRoot::new(frontend)]
+}
+
+/// Renders the bundle the way the GUI does: everything in memory.
+fn render_in_memory(roots: &[ProjectRoot], options: &BundleOptions) -> String {
+    let files = select_files(scan_roots(roots, 1000, false).unwrap(), options);
+    render_bundle(&files, options)
+}
+
+fn write_to_string(roots: &[ProjectRoot], options: &BundleOptions) -> String {
+    let mut output = Vec::new();
+    write_roots_bundle(&mut output, roots, 1000, false, options).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_streamed_bundle_matches_rendered_bundle() {
+    let test_dir = setup_test_dir("stream_matches");
+    let roots = setup_roots(&test_dir);
+
+    let mut options = BundleOptions::default();
+    options.format.line_numbers = true;
+    options
+        .truncation
+        .push(TruncateRule::parse("*.log:tail=5").unwrap());
+    assert!(can_stream(&roots, &options));
+
+    let streamed = write_to_string(&roots, &options);
+    assert_eq!(streamed, render_in_memory(&roots, &options));
+    assert!(streamed.contains("[Lines 1-45 of 50 omitted]"));
+    assert!(streamed.contains("backend/src/db.rs:\n1 | pub fn connect() {}\n"));
+
+    // A single root is not prefixed
+    let single = &roots[..1];
+    assert_eq!(
+        write_to_string(single, &options),
+        render_in_memory(single, &options)
+    );
+}
+
+#[test]
+fn test_whole_project_options_fall_back_to_scanning() {
+    let test_dir = setup_test_dir("stream_fallback");
+    let roots = setup_roots(&test_dir);
+
+    let options = BundleOptions {
+        order: FileOrder::Dependencies,
+        ..BundleOptions::default()
+    };
+    assert!(!can_stream(&roots, &options));
+    assert_eq!(
+        write_to_string(&roots, &options),
+        render_in_memory(&roots, &options)
+    );
+
+    let mut archives = roots.clone();
+    archives[0].expand_archives = true;
+    assert!(!can_stream(&archives, &BundleOptions::default()));
+}
+
+#[test]
+fn test_write_bundle_matches_render_bundle() {
+    let test_dir = setup_test_dir("write_bundle");
+    let roots = setup_roots(&test_dir);
+    let files = scan_roots(&roots, 1000, false).unwrap();
+    let options = BundleOptions::default();
+
+    let mut output = Vec::new();
+    write_bundle(&mut output, &files, &options).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        render_bundle(&files, &options)
+    );
+}
+
+/// Accepts a few writes, then fails like a closed pipe.
+struct ClosingWriter {
+    writes_left: usize,
+}
+
+impl Write for ClosingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.writes_left == 0 {
+            return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+        }
+        self.writes_left -= 1;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_errors_stop_the_stream() {
+    let test_dir = setup_test_dir("stream_errors");
+    let roots = setup_roots(&test_dir);
+
+    let mut out = ClosingWriter { writes_left: 3 };
+    let result = write_roots_bundle(&mut out, &roots, 1000, false, &BundleOptions::default());
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+}
+
+/// Deletes a file on the first write, after the streamed walk is done.
+struct DeletingWriter {
+    output: Vec<u8>,
+    delete: Option<std::path::PathBuf>,
+}
+
+impl Write for DeletingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(path) = self.delete.take() {
+            fs::remove_file(path).unwrap();
+        }
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_file_deleted_while_streaming_keeps_its_section() {
+    let test_dir = setup_test_dir("stream_deleted");
+    let roots = setup_roots(&test_dir);
+    let options = BundleOptions::default();
+    let db = roots[0].path.join("src/db.rs");
+
+    let mut out = DeletingWriter {
+        output: Vec::new(),
+        delete: Some(db.clone()),
+    };
+    write_roots_bundle(&mut out, &roots, 1000, false, &options).unwrap();
+    let streamed = String::from_utf8(out.output).unwrap();
+
+    // Same as scanning a project where the file could not be read
+    fs::write(&db, "").unwrap();
+    assert_eq!(streamed, render_in_memory(&roots, &options));
+    assert!(streamed.contains("backend/src/db.rs:\n\n\n"));
+}
@@ -0,0 +1,223 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::formatter::number_lines;
+use contextor::roots::{scan_roots, DiskFiles, ProjectRoot};
+use contextor::scanner::{ScannedFile, TRUNCATION_MARKER};
+use contextor::truncate::{apply_truncation, elided_range_end, TruncateRule, TruncateStrategy};
+use std::fs::{self, File};
+
+fn numbered_text(lines: usize) -> String {
+    (1..=lines).map(|i| format!("line {}\n", i)).collect()
+}
+
+#[test]
+fn test_parse_strategies() {
+    assert_eq!(
+        TruncateStrategy::parse("head=5"),
+        Ok(TruncateStrategy::Head(5))
+    );
+    assert_eq!(
+        TruncateStrategy::parse("tail=3"),
+        Ok(TruncateStrategy::Tail(3))
+    );
+    assert_eq!(
+        TruncateStrategy::parse("head=2, tail=1"),
+        Ok(TruncateStrategy::HeadTail { head: 2, tail: 1 })
+    );
+    assert_eq!(
+        TruncateStrategy::parse("100-220,7"),
+        Ok(TruncateStrategy::Ranges(vec![(100, 220), (7, 7)]))
+    );
+    assert!(TruncateStrategy::parse("head=x").is_err());
+    assert!(TruncateStrategy::parse("0-4").is_err());
+    assert!(TruncateStrategy::parse("9-4").is_err());
+    assert!(TruncateStrategy::parse("head=2,5-6").is_err());
+
+    let rule = TruncateRule::parse("src/app.rs:100-220").unwrap();
+    assert_eq!(rule.pattern, "src/app.rs");
+    assert_eq!(rule.strategy, TruncateStrategy::Ranges(vec![(100, 220)]));
+    assert!(TruncateRule::parse("head=5").is_err());
+    assert!(TruncateRule::parse(":head=5").is_err());
+}
+
+#[test]
+fn test_apply_strategies() {
+    let text = numbered_text(10);
+
+    assert_eq!(
+        TruncateStrategy::Head(2).apply(&text),
+        "line 1\nline 2\n[Lines 3-10 of 10 omitted]"
+    );
+    assert_eq!(
+        TruncateStrategy::Tail(2).apply(&text),
+        "[Lines 1-8 of 10 omitted]\nline 9\nline 10\n"
+    );
+    assert_eq!(
+        TruncateStrategy::HeadTail { head: 1, tail: 1 }.apply(&text),
+        "line 1\n[Lines 2-9 of 10 omitted]\nline 10\n"
+    );
+    // Overlapping and out-of-range ranges are merged and clamped
+    assert_eq!(
+        TruncateStrategy::Ranges(vec![(4, 5), (3, 4), (9, 40)]).apply(&text),
+        "[Lines 1-2 of 10 omitted]\nline 3\nline 4\nline 5\n[Lines 6-8 of 10 omitted]\nline 9\nline 10\n"
+    );
+    // Nothing to cut
+    assert_eq!(TruncateStrategy::Head(20).apply(&text), text);
+    assert_eq!(
+        TruncateStrategy::HeadTail { head: 6, tail: 6 }.apply(&text),
+        text
+    );
+}
+
+#[test]
+fn test_line_numbers_across_elisions() {
+    let (text, origins) =
+        TruncateStrategy::Ranges(vec![(2, 2), (9, 10)]).apply_with_origins(&numbered_text(10));
+    assert_eq!(elided_range_end("[Lines 3-8 of 10 omitted]"), Some(8));
+    assert_eq!(elided_range_end("[Lines of code]"), None);
+
+    assert_eq!(
+        number_lines(&text, Some(&origins)),
+        "   | [Lines 1-1 of 10 omitted]\n 2 | line 2\n   | [Lines 3-8 of 10 omitted]\n 9 | line 9\n10 | line 10\n"
+    );
+
+    // Lines of the file that read like markers are numbered all the same
+    let text = "[Lines 3-5](#usage)\n[Truncated: File too large]\n[File size > 1MB]\nend\n";
+    let (kept, origins) = TruncateStrategy::Ranges(vec![(1, 2), (4, 4)]).apply_with_origins(text);
+    assert_eq!(
+        number_lines(&kept, Some(&origins)),
+        "1 | [Lines 3-5](#usage)\n2 | [Truncated: File too large]\n  | [Lines 3-3 of 4 omitted]\n4 | end\n"
+    );
+    assert_eq!(
+        number_lines(text, None),
+        "1 | [Lines 3-5](#usage)\n2 | [Truncated: File too large]\n3 | [File size > 1MB]\n4 | end\n"
+    );
+}
+
+#[test]
+fn test_apply_truncation_rules() {
+    let test_dir = setup_test_dir("truncation_rules");
+    fs::write(test_dir.path.join("big.log"), numbered_text(5000)).unwrap();
+    let roots = [ProjectRoot::new(&test_dir.path)];
+    let big = scan_roots(&roots, 1000, false).unwrap().remove(0);
+    assert_eq!(
+        big.content.as_deref(),
+        Some("[File size > 0.0MB (max: 0.0MB)]")
+    );
+
+    let file = |path: &str, content: &str, is_binary: bool| ScannedFile {
+        path: path.to_string(),
+        content: Some(content.to_string()),
+        is_binary,
+        line_origins: None,
+    };
+    let files = vec![
+        file("logs/app.log", "a\nb\nc", false),
+        file("src/app.rs", "a\nb\nc", false),
+        file("src/lib.rs", "a\nb\nc", false),
+        file("data.log", "a\0b\nc", true),
+        big.clone(),
+    ];
+    let rules = vec![
+        TruncateRule::parse("*.log:tail=1").unwrap(),
+        TruncateRule::parse("src/*.rs:head=1").unwrap(),
+        TruncateRule::parse("src/app.rs:2").unwrap(),
+    ];
+
+    let disk = DiskFiles::new(&roots, 1000);
+    let result = apply_truncation(files, &rules, Some(&disk));
+    let contents: Vec<&str> = result
+        .iter()
+        .map(|f| f.content.as_deref().unwrap())
+        .collect();
+    assert_eq!(
+        contents,
+        vec![
+            "[Lines 1-2 of 3 omitted]\nc",
+            "[Lines 1-1 of 3 omitted]\nb\n[Lines 3-3 of 3 omitted]",
+            "a\n[Lines 2-3 of 3 omitted]",
+            "a\0b\nc",
+            "[Lines 1-4999 of 5000 omitted]\nline 5000\n",
+        ]
+    );
+
+    // Without a way to read the file it stays a placeholder
+    let result = apply_truncation(vec![big.clone()], &rules, None);
+    assert_eq!(result, [big]);
+}
+
+#[test]
+fn test_truncation_of_files_cut_by_the_scan() {
+    let test_dir = setup_test_dir("truncation_cut");
+    // Replacing the invalid bytes pushes the text over the size limit
+    let mut data = numbered_text(9).into_bytes();
+    data.extend([0xff; 30]);
+    fs::write(test_dir.path.join("app.log"), &data).unwrap();
+    let roots = [ProjectRoot::new(&test_dir.path)];
+    let cut = scan_roots(&roots, 100, false).unwrap().remove(0);
+    assert!(cut.is_cut());
+    assert!(cut.content.as_deref().unwrap().ends_with(TRUNCATION_MARKER));
+
+    let rules = [TruncateRule::parse("*.log:tail=2").unwrap()];
+    let disk = DiskFiles::new(&roots, 100);
+    let from_disk = apply_truncation(vec![cut.clone()], &rules, Some(&disk)).remove(0);
+    assert_eq!(
+        from_disk.content.as_deref(),
+        Some(
+            format!(
+                "[Lines 1-8 of 10 omitted]\nline 9\n{}",
+                "\u{fffd}".repeat(30)
+            )
+            .as_str()
+        )
+    );
+
+    // The scan's marker is not taken for a line of the file
+    let kept = apply_truncation(vec![cut], &rules, None).remove(0);
+    let content = kept.content.as_deref().unwrap();
+    assert_eq!(
+        number_lines(content, kept.line_origins.as_deref()),
+        format!(
+            "  | [Lines 1-7 of 9 omitted]\n8 | line 8\n9 | line 9\n  | {}",
+            TRUNCATION_MARKER
+        )
+    );
+}
+
+#[test]
+fn test_truncate_file() {
+    let test_dir = setup_test_dir("truncate_file");
+    let path = test_dir.path.join("lines.txt");
+    let text = numbered_text(30).replace("line 7\n", "line 7\r\n") + "last";
+    fs::write(&path, &text).unwrap();
+
+    // Reading the file keeps the same lines as reading the text
+    for strategy in [
+        TruncateStrategy::Head(3),
+        TruncateStrategy::Tail(3),
+        TruncateStrategy::HeadTail { head: 8, tail: 2 },
+        TruncateStrategy::Ranges(vec![(5, 9), (20, 40)]),
+    ] {
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(
+            strategy.apply_file(&mut file, usize::MAX).unwrap(),
+            strategy.apply_with_origins(&text),
+            "{:?}",
+            strategy
+        );
+    }
+
+    // Kept lines beyond the limit are left out
+    let mut file = File::open(&path).unwrap();
+    assert_eq!(
+        TruncateStrategy::Ranges(vec![(2, 4)])
+            .apply_file(&mut file, 14)
+            .unwrap()
+            .0,
+        format!(
+            "[Lines 1-1 of 31 omitted]\nline 2\nline 3\n{}",
+            TRUNCATION_MARKER
+        )
+    );
+}
@@ -0,0 +1,244 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::bundle::{render_bundle, select_files, BundleOptions};
+use contextor::graph::GraphExportOptions;
+use contextor::manifest::ManifestOptions;
+use contextor::order::FileOrder;
+use contextor::roots::{scan_roots, ProjectRoot};
+use contextor::sample::SampleRule;
+use contextor::truncate::TruncateRule;
+use contextor::unpack::{parse_bundle, unpack_bundle};
+use std::fs;
+
+fn bundle(roots: &[ProjectRoot], max_file_size: u64, options: &BundleOptions) -> String {
+    let files = select_files(scan_roots(roots, max_file_size, false).unwrap(), options);
+    render_bundle(&files, options)
+}
+
+#[test]
+fn test_unpack_round_trip() {
+    let test_dir = setup_test_dir("unpack_round_trip");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(project.join("src/util")).unwrap();
+    let files = [
+        ("README.md", "# Title\n\nSome text:\n\n\n"),
+        ("src/lib.rs", "pub mod util;\n"),
+        // Looks like the header of a section that was already read
+        (
+            "src/main.rs",
+            "fn main() {}\n\nsrc/lib.rs:\nno newline at the end",
+        ),
+        ("src/util/mod.rs", ""),
+        ("Cargo.toml", "[package]\nname = \"demo\"\n"),
+    ];
+    for (path, content) in files {
+        fs::write(project.join(path), content).unwrap();
+    }
+
+    let roots = vec![ProjectRoot::new(&project)];
+    let mut options = BundleOptions {
+        manifest: Some(ManifestOptions {
+            roots: roots.clone(),
+            section: true,
+            sidecar: None,
+        }),
+        graph: Some(GraphExportOptions::default()),
+        manifest_hash: true,
+        ..BundleOptions::default()
+    };
+    options.format.line_numbers = true;
+    let text = bundle(&roots, 1_000_000, &options);
+
+    let paths: Vec<String> = parse_bundle(&text)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.path)
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "Cargo.toml",
+            "README.md",
+            "src/lib.rs",
+            "src/main.rs",
+            "src/util/mod.rs"
+        ]
+    );
+
+    let target = test_dir.path.join("out");
+    let report = unpack_bundle(&text, &target, false).unwrap();
+    assert_eq!(report.written.len(), files.len());
+    assert!(report.skipped.is_empty(), "{:?}", report.skipped);
+    for (path, content) in files {
+        assert_eq!(fs::read_to_string(target.join(path)).unwrap(), content);
+    }
+
+    assert!(parse_bundle("Just some text\n\nwith paragraphs").is_err());
+}
+
+#[test]
+fn test_parse_bundle_skips_headers_of_later_sections() {
+    let test_dir = setup_test_dir("unpack_later_headers");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(project.join("src")).unwrap();
+    // Each body names a file whose section comes after it in one of the orders
+    let files = [
+        (
+            "CHANGELOG.md",
+            "Bumped the version in\n\nCargo.toml:\n1.0\n",
+        ),
+        ("Cargo.toml", "[package]\nname = \"demo\"\n"),
+        (
+            "src/main.rs",
+            "// Settings are in\n\nCargo.toml:\nfn main() {}\n",
+        ),
+    ];
+    for (path, content) in files {
+        fs::write(project.join(path), content).unwrap();
+    }
+
+    let roots = vec![ProjectRoot::new(&project)];
+    for order in FileOrder::ALL {
+        let options = BundleOptions {
+            order,
+            ..BundleOptions::default()
+        };
+        let sections = parse_bundle(&bundle(&roots, 1_000_000, &options)).unwrap();
+        assert_eq!(sections.len(), files.len(), "{:?}", order);
+        for (path, content) in files {
+            let section = sections.iter().find(|s| s.path == path).unwrap();
+            assert_eq!(section.body, content, "{:?}", order);
+        }
+    }
+}
+
+#[test]
+fn test_unpack_reads_the_bundle_as_written() {
+    let test_dir = setup_test_dir("unpack_as_written");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(&project).unwrap();
+    // Text that looks like line numbers or the parts after the sections
+    let files = [
+        (
+            "a.md",
+            "Intro\n\nManifest:\nscope all\n\nDependency graph:\nx\n",
+        ),
+        ("b.txt", "1 | one\n2 | two\n"),
+        ("c.txt", "last\n\nManifest hash: sha256:00\n"),
+    ];
+    for (path, content) in files {
+        fs::write(project.join(path), content).unwrap();
+    }
+    fs::write(project.join("big.txt"), "x".repeat(2000)).unwrap();
+
+    let roots = vec![ProjectRoot::new(&project)];
+    for line_numbers in [false, true] {
+        let mut options = BundleOptions::default();
+        options.format.line_numbers = line_numbers;
+        let text = bundle(&roots, 1000, &options);
+        assert_eq!(parse_bundle(&text).unwrap().len(), files.len() + 1);
+
+        let target = test_dir.path.join(format!("out_{}", line_numbers));
+        let report = unpack_bundle(&text, &target, false).unwrap();
+        assert_eq!(report.skipped[0].path, "big.txt");
+        for (path, content) in files {
+            assert_eq!(
+                fs::read_to_string(target.join(path)).unwrap(),
+                content,
+                "{} with line numbers: {}",
+                path,
+                line_numbers
+            );
+        }
+    }
+}
+
+#[test]
+fn test_unpack_skips_partial_files() {
+    let test_dir = setup_test_dir("unpack_partial");
+    let project = test_dir.path.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(project.join("logo.png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    fs::write(project.join("big.txt"), "x".repeat(2000)).unwrap();
+    let log: String = (1..=30).map(|i| format!("line {}\n", i)).collect();
+    fs::write(project.join("app.log"), &log).unwrap();
+    let csv: String = (0..50).map(|i| format!("{},{}\n", i, i * 2)).collect();
+    fs::write(project.join("data.csv"), format!("a,b\n{}", csv)).unwrap();
+    fs::write(project.join("kept.txt"), "kept\n").unwrap();
+
+    let options = BundleOptions {
+        truncation: vec![TruncateRule::parse("*.log:tail=5").unwrap()],
+        sampling: SampleRule::defaults(),
+        ..BundleOptions::default()
+    };
+    let text = bundle(&[ProjectRoot::new(&project)], 1000, &options);
+
+    let target = test_dir.path.join("out");
+    fs::create_dir_all(&target).unwrap();
+    fs::write(target.join("kept.txt"), "mine\n").unwrap();
+    let report = unpack_bundle(&text, &target, false).unwrap();
+    assert!(report.written.is_empty());
+    let skipped: Vec<(&str, &str)> = report
+        .skipped
+        .iter()
+        .map(|s| (s.path.as_str(), s.reason.as_str()))
+        .collect();
+    assert_eq!(
+        skipped,
+        [
+            ("app.log", "lines omitted"),
+            ("big.txt", "larger than the size limit"),
+            ("data.csv", "sampled"),
+            ("kept.txt", "already exists"),
+            ("logo.png", "binary file"),
+        ]
+    );
+    assert_eq!(
+        fs::read_to_string(target.join("kept.txt")).unwrap(),
+        "mine\n"
+    );
+
+    let report = unpack_bundle(&text, &target, true).unwrap();
+    assert_eq!(report.written, vec!["kept.txt"]);
+    assert_eq!(
+        fs::read_to_string(target.join("kept.txt")).unwrap(),
+        "kept\n"
+    );
+}
+
+#[test]
+fn test_unpack_refuses_escaping_paths() {
+    let test_dir = setup_test_dir("unpack_escape");
+    let target = test_dir.path.join("out");
+    let text = "\
+├── ..
+│   └── evil.txt
+└── ok
+    └── fine.txt
+
+../evil.txt:
+boom
+
+ok/fine.txt:
+fine
+
+";
+    let report = unpack_bundle(text, &target, false).unwrap();
+    assert_eq!(report.written, vec!["ok/fine.txt"]);
+    assert_eq!(report.skipped[0].path, "../evil.txt");
+    assert_eq!(report.skipped[0].reason, "outside the target folder");
+    assert!(!test_dir.path.join("evil.txt").exists());
+
+    #[cfg(unix)]
+    {
+        let outside = test_dir.path.join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(fs::canonicalize(&outside).unwrap(), target.join("link"))
+            .unwrap();
+        let text = "└── link\n    └── x.txt\n\nlink/x.txt:\nx\n\n";
+        let report = unpack_bundle(text, &target, false).unwrap();
+        assert_eq!(report.skipped[0].reason, "outside the target folder");
+        assert!(!outside.join("x.txt").exists());
+    }
+}
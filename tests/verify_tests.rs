@@ -0,0 +1,124 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::bundle::{render_bundle, select_files, BundleOptions};
+use contextor::manifest::{Manifest, ManifestOptions};
+use contextor::roots::{scan_roots, ProjectRoot};
+use contextor::verify::check_manifest;
+use std::fs;
+
+fn bundle_options(roots: &[ProjectRoot]) -> BundleOptions {
+    BundleOptions {
+        manifest: Some(ManifestOptions {
+            roots: roots.to_vec(),
+            section: true,
+            sidecar: None,
+        }),
+        ..BundleOptions::default()
+    }
+}
+
+fn bundle_with_manifest(roots: &[ProjectRoot]) -> String {
+    let options = bundle_options(roots);
+    let files = select_files(scan_roots(roots, 1_000_000, false).unwrap(), &options);
+    render_bundle(&files, &options)
+}
+
+#[test]
+fn test_parse_manifest_section_and_json() {
+    let test_dir = setup_test_dir("verify_parse");
+    let root = &test_dir.path;
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    // A file that itself mentions a manifest does not confuse the parser
+    fs::write(root.join("notes.md"), "Manifest:\nfile nope 1 1 x\n").unwrap();
+
+    let mut root = ProjectRoot::new(root);
+    root.ignore_patterns = vec!["*.lock".to_string()];
+    let bundle = bundle_with_manifest(&[root.clone()]);
+    let expected = Manifest::build(
+        &[root],
+        &["notes.md".to_string(), "src/main.rs".to_string()],
+    );
+
+    let parsed = Manifest::parse(&bundle).unwrap();
+    assert_eq!(parsed.files, expected.files);
+    assert_eq!(parsed.roots[0].ignore_patterns, vec!["*.lock"]);
+    assert_eq!(parsed.roots[0].path, expected.roots[0].path);
+    assert_eq!(Manifest::parse(&expected.to_json()).unwrap(), expected);
+
+    assert!(Manifest::parse("src/main.rs:\nfn main() {}\n").is_err());
+    assert!(Manifest::parse("{ not json").is_err());
+}
+
+#[test]
+fn test_check_manifest() {
+    let test_dir = setup_test_dir("verify_check");
+    let root = &test_dir.path;
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+    fs::write(root.join("README.md"), "# Project\n").unwrap();
+    fs::write(root.join("debug.log"), "ignored\n").unwrap();
+
+    let mut project = ProjectRoot::new(root);
+    project.ignore_patterns = vec!["*.log".to_string()];
+    let manifest = Manifest::parse(&bundle_with_manifest(&[project])).unwrap();
+
+    let report = check_manifest(&manifest);
+    assert!(report.is_current(), "{}", report.render());
+    assert_eq!(report.checked, 3);
+    assert!(report.render().contains("All 3 files are up to date"));
+
+    fs::write(root.join("src/main.rs"), "fn main() { run(); }\n").unwrap();
+    fs::remove_file(root.join("README.md")).unwrap();
+    fs::write(root.join("src/new.rs"), "").unwrap();
+    fs::write(root.join("other.log"), "still ignored\n").unwrap();
+
+    let report = check_manifest(&manifest);
+    assert!(!report.is_current());
+    assert_eq!(report.changed, vec!["src/main.rs"]);
+    assert_eq!(report.added, vec!["src/new.rs"]);
+    assert_eq!(report.deleted, vec!["README.md"]);
+    assert!(report
+        .render()
+        .ends_with("1 changed, 1 added, 1 deleted since the bundle of 3 files\n"));
+
+    // Files a query left out are not reported as added
+    let options = BundleOptions {
+        query: Some("main".to_string()),
+        ..bundle_options(&[ProjectRoot::new(root)])
+    };
+    let files = select_files(
+        scan_roots(&[ProjectRoot::new(root)], 1_000_000, false).unwrap(),
+        &options,
+    );
+    let manifest = Manifest::parse(&render_bundle(&files, &options)).unwrap();
+    assert!(!manifest.complete);
+    assert_eq!(manifest.files.len(), 1);
+    assert!(check_manifest(&manifest).is_current());
+    fs::write(root.join("src/other.rs"), "").unwrap();
+    assert!(check_manifest(&manifest).is_current());
+}
+
+#[test]
+fn test_check_manifest_multiple_roots() {
+    let test_dir = setup_test_dir("verify_roots");
+    let backend = test_dir.path.join("backend");
+    let frontend = test_dir.path.join("frontend");
+    fs::create_dir_all(&backend).unwrap();
+    fs::create_dir_all(&frontend).unwrap();
+    fs::write(backend.join("app.py"), "print('up')\n").unwrap();
+    fs::write(frontend.join("app.js"), "console.log('up')\n").unwrap();
+
+    let roots = vec![ProjectRoot::new(&backend), ProjectRoot::new(&frontend)];
+    let manifest = Manifest::parse(&bundle_with_manifest(&roots)).unwrap();
+    assert!(check_manifest(&manifest).is_current());
+
+    fs::write(frontend.join("app.js"), "console.log('down')\n").unwrap();
+    fs::write(backend.join("db.py"), "").unwrap();
+    let report = check_manifest(&manifest);
+    assert_eq!(report.changed, vec!["frontend/app.js"]);
+    assert_eq!(report.added, vec!["backend/db.py"]);
+    assert!(report.deleted.is_empty());
+}
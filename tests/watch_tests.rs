@@ -0,0 +1,110 @@
+mod common;
+
+use common::setup_test_dir;
+use contextor::watch::{ProjectState, ProjectWatcher};
+use std::fs;
+use std::time::{Duration, Instant};
+
+fn paths(state: &ProjectState) -> Vec<&str> {
+    state.files().iter().map(|f| f.path.as_str()).collect()
+}
+
+#[test]
+fn test_apply_changes_updates_only_affected_files() {
+    let test_dir = setup_test_dir("watch_apply_changes");
+    fs::write(test_dir.path.join("a.txt"), "one").unwrap();
+    fs::write(test_dir.path.join("b.txt"), "two").unwrap();
+
+    let mut state = ProjectState::scan(&test_dir.path, 1_000_000);
+    assert_eq!(paths(&state), vec!["a.txt", "b.txt"]);
+
+    let root = state.root().to_path_buf();
+
+    // Modify one file, add another, delete a third
+    fs::write(root.join("a.txt"), "changed").unwrap();
+    fs::write(root.join("c.txt"), "three").unwrap();
+    fs::remove_file(root.join("b.txt")).unwrap();
+
+    let changed =
+        state.apply_changes(&[root.join("a.txt"), root.join("b.txt"), root.join("c.txt")]);
+
+    assert!(changed);
+    assert_eq!(paths(&state), vec!["a.txt", "c.txt"]);
+    assert_eq!(state.files()[0].content, Some("changed".to_string()));
+
+    // Re-applying without changes on disk reports nothing new
+    assert!(!state.apply_changes(&[root.join("a.txt")]));
+}
+
+#[test]
+fn test_apply_changes_new_directory_and_ignored_files() {
+    let test_dir = setup_test_dir("watch_new_directory");
+    fs::write(test_dir.path.join(".gitignore"), "*.log\n").unwrap();
+
+    let mut state = ProjectState::scan(&test_dir.path, 1_000_000);
+    let root = state.root().to_path_buf();
+
+    fs::create_dir_all(root.join("sub/inner")).unwrap();
+    fs::write(root.join("sub/inner/x.rs"), "fn x() {}").unwrap();
+    fs::write(root.join("debug.log"), "noise").unwrap();
+
+    assert!(state.apply_changes(&[root.join("sub"), root.join("debug.log")]));
+    assert_eq!(paths(&state), vec![".gitignore", "sub/inner/x.rs"]);
+
+    fs::remove_dir_all(root.join("sub")).unwrap();
+    assert!(state.apply_changes(&[root.join("sub")]));
+    assert_eq!(paths(&state), vec![".gitignore"]);
+}
+
+#[test]
+fn test_rescanned_directory_drops_missing_files() {
+    let test_dir = setup_test_dir("watch_rescan_directory");
+    fs::create_dir_all(test_dir.path.join("sub")).unwrap();
+    fs::write(test_dir.path.join("sub/a.txt"), "a").unwrap();
+    fs::write(test_dir.path.join("sub/b.txt"), "b").unwrap();
+    fs::write(test_dir.path.join("subway.txt"), "c").unwrap();
+
+    let mut state = ProjectState::scan(&test_dir.path, 1_000_000);
+    let root = state.root().to_path_buf();
+
+    // Only the folder is reported, not the file that went away
+    fs::remove_file(root.join("sub/b.txt")).unwrap();
+    assert!(state.apply_changes(&[root.join("sub")]));
+    assert_eq!(paths(&state), vec!["sub/a.txt", "subway.txt"]);
+    assert!(!state.apply_changes(&[root.join("sub")]));
+}
+
+#[test]
+fn test_excluded_output_file() {
+    let test_dir = setup_test_dir("watch_excluded");
+    fs::write(test_dir.path.join("a.txt"), "one").unwrap();
+    fs::write(test_dir.path.join("bundle.txt"), "old bundle").unwrap();
+
+    let mut state = ProjectState::scan(&test_dir.path, 1_000_000);
+    state.exclude(&test_dir.path.join("bundle.txt"));
+    assert_eq!(paths(&state), vec!["a.txt"]);
+
+    let root = state.root().to_path_buf();
+    fs::write(root.join("bundle.txt"), "new bundle").unwrap();
+    assert!(!state.apply_changes(&[root.join("bundle.txt")]));
+}
+
+#[test]
+fn test_watcher_reports_changes() {
+    let test_dir = setup_test_dir("watch_watcher");
+    let state = ProjectState::scan(&test_dir.path, 1_000_000);
+    let watcher = ProjectWatcher::new(state.root(), Duration::from_millis(50)).unwrap();
+
+    fs::write(state.root().join("new.txt"), "hello").unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut seen = Vec::new();
+    while Instant::now() < deadline && !seen.contains(&state.root().join("new.txt")) {
+        if let Some(batch) = watcher.try_next_batch() {
+            seen.extend(batch);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    assert!(seen.contains(&state.root().join("new.txt")));
+}